@@ -1,8 +1,12 @@
 use std::thread;
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::AtomicI8;
+use std::sync::atomic::Ordering as AtomicOrdering;
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::process::Command;
+use std::iter;
 use std::collections::VecDeque;
 use std::time::Duration;
 use failure::{Error, ResultExt};
@@ -30,7 +34,9 @@ use view::confirmation::Confirmation;
 use view::intermission::Intermission;
 use view::notification::Notification;
 use device::CURRENT_DEVICE;
-use font::Fonts;
+use font::{Fonts, open_fallback_fonts};
+use dictionary::{Dictionaries, DICTIONARIES_DIRNAME, spawn_loader};
+use fulltext::Indexer;
 
 pub const APP_NAME: &str = "Plato";
 
@@ -39,11 +45,22 @@ const BATTERY_REFRESH_INTERVAL: Duration = Duration::from_secs(299);
 const SUSPEND_WAIT_DELAY: Duration = Duration::from_secs(15);
 const PREPARE_SUSPEND_WAIT_DELAY: Duration = Duration::from_secs(3);
 
+#[derive(Debug, Copy, Clone)]
+pub struct Display {
+    pub dims: (u32, u32),
+    pub rotation: i8,
+}
+
 pub struct Context {
+    pub display: Display,
     pub settings: Settings,
     pub metadata: Metadata,
     pub filename: PathBuf,
     pub fonts: Fonts,
+    pub dictionaries: Option<Dictionaries>,
+    // Receives the dictionaries once they're loaded.
+    pub dictionaries_loader: Option<Receiver<Dictionaries>>,
+    pub indexer: Option<Indexer>,
     pub frontlight: Box<Frontlight>,
     pub battery: Box<Battery>,
     pub lightsensor: Box<LightSensor>,
@@ -53,16 +70,18 @@ pub struct Context {
     pub plugged: bool,
     pub covered: bool,
     pub shared: bool,
+    pub passwords: FnvHashMap<PathBuf, String>,
 }
 
 impl Context {
     pub fn new(settings: Settings, metadata: Metadata,
                filename: PathBuf, fonts: Fonts, battery: Box<Battery>,
                frontlight: Box<Frontlight>, lightsensor: Box<LightSensor>) -> Context {
-        Context { settings, metadata, filename, fonts, battery,
-                  frontlight, lightsensor, notification_index: 0,
+        let display = Display { dims: CURRENT_DEVICE.dims, rotation: 0 };
+        Context { display, settings, metadata, filename, fonts, dictionaries: None,
+                  dictionaries_loader: None, indexer: None, battery, frontlight, lightsensor, notification_index: 0,
                   inverted: false, monochrome: false, plugged: false,
-                  covered: false, shared: false }
+                  covered: false, shared: false, passwords: FnvHashMap::default() }
     }
 }
 
@@ -103,7 +122,11 @@ fn build_context() -> Result<Context, Error> {
                                                       &Vec::new(),
                                                       &settings.import.allowed_kinds))
                              .unwrap_or_default();
-    let fonts = Fonts::load().context("Can't load fonts.")?;
+    let mut fonts = Fonts::load().context("Can't load fonts.")?;
+
+    if let Ok(fallbacks) = open_fallback_fonts(&settings.fallback_fonts) {
+        fonts.set_fallbacks(&fallbacks);
+    }
 
     let battery = Box::new(KoboBattery::new().context("Can't create battery.")?) as Box<Battery>;
 
@@ -164,6 +187,24 @@ fn resume(id: TaskId, tasks: &mut Vec<Task>, view: &mut View, hub: &Sender<Event
     }
 }
 
+// Rotate the display and adapt every view to its new rectangle.
+pub fn rotate(n: i8, fb: &mut Framebuffer, view: &mut Box<View>, history: &mut Vec<Box<View>>, hub: &Sender<Event>, context: &mut Context) -> Result<Rectangle, Error> {
+    let dims = fb.set_rotation(n)?;
+    context.display = Display { dims, rotation: n };
+    let rect = fb.rect();
+
+    for v in history.iter_mut().chain(iter::once(view)) {
+        if let Some(r) = v.downcast_mut::<Reader>() {
+            r.resize(rect, hub, context);
+        } else if let Some(h) = v.downcast_mut::<Home>() {
+            h.resize(rect, hub, context);
+        }
+    }
+
+    hub.send(Event::Render(rect, UpdateMode::Full)).unwrap();
+    Ok(rect)
+}
+
 fn power_off(history: &mut Vec<Box<View>>, fb: &mut Framebuffer, updating: &mut FnvHashMap<u32, Rectangle>, context: &mut Context) {
     let (tx, rx) = mpsc::channel();
     while let Some(mut view) = history.pop() {
@@ -181,10 +222,20 @@ fn power_off(history: &mut Vec<Box<View>>, fb: &mut Framebuffer, updating: &mut
 pub fn run() -> Result<(), Error> {
     let mut context = build_context().context("Can't build context.")?;
     let mut fb = KoboFramebuffer::new("/dev/fb0").context("Can't create framebuffer.")?;
+    let initial_dims = fb.dims();
+
+    if context.settings.rotation != 0 {
+        match fb.set_rotation(context.settings.rotation) {
+            Ok(dims) => context.display = Display { dims, rotation: context.settings.rotation },
+            Err(e) => eprintln!("Can't rotate the display: {}", e),
+        }
+    }
 
     let paths = vec!["/dev/input/event0".to_string(),
                      "/dev/input/event1".to_string()];
-    let touch_screen = gesture_events(device_events(raw_events(paths), fb.dims()));
+    let raw_receiver = raw_events(paths, context.settings.reader.keys.clone());
+    let touch_rotation = Arc::new(AtomicI8::new(context.display.rotation));
+    let touch_screen = gesture_events(device_events(raw_receiver, initial_dims, Arc::clone(&touch_rotation)));
     let usb_port = usb_events();
 
     let (tx, rx) = mpsc::channel();
@@ -219,7 +270,7 @@ pub fn run() -> Result<(), Error> {
         }
     });
 
-    let fb_rect = fb.rect();
+    let mut fb_rect = fb.rect();
 
     if context.settings.wifi {
         Command::new("scripts/wifi-enable.sh").status().ok();
@@ -240,6 +291,11 @@ pub fn run() -> Result<(), Error> {
     let mut history: Vec<Box<View>> = Vec::new();
     let mut view: Box<View> = Box::new(Home::new(fb_rect, &tx, &mut context)?);
 
+    context.indexer = Some(Indexer::new(&context.settings.library_path,
+                                        &context.metadata,
+                                        &context.settings.reader));
+    context.dictionaries_loader = Some(spawn_loader(DICTIONARIES_DIRNAME));
+
     let mut updating = FnvHashMap::default();
 
     println!("{} is running on a Kobo {}.", APP_NAME,
@@ -313,6 +369,7 @@ pub fn run() -> Result<(), Error> {
                         let notif = Notification::new(ViewId::NetUpNotif,
                                                       format!("Network is up ({}, {}).", ip, essid),
                                                       &mut context.notification_index,
+                                                      context.display.dims,
                                                       &mut context.fonts,
                                                       &tx);
                         view.children_mut().push(Box::new(notif) as Box<View>);
@@ -349,6 +406,7 @@ pub fn run() -> Result<(), Error> {
                                 let confirm = Confirmation::new(ViewId::ConfirmShare,
                                                                 Event::PrepareShare,
                                                                 "Share storage via USB?".to_string(),
+                                                                context.display.dims,
                                                                 &mut context.fonts);
                                 tx.send(Event::Render(*confirm.rect(), UpdateMode::Gui)).unwrap();
                                 view.children_mut().push(Box::new(confirm) as Box<View>);
@@ -403,6 +461,12 @@ pub fn run() -> Result<(), Error> {
                                     context.metadata.append(&mut metadata.unwrap());
                                 }
                             }
+                            if let Some(indexer) = context.indexer.take() {
+                                indexer.stop();
+                            }
+                            context.indexer = Some(Indexer::new(&context.settings.library_path,
+                                                                &context.metadata,
+                                                                &context.settings.reader));
                             view.handle_event(&Event::Reseed, &tx, &mut bus, &mut context);
                         } else {
                             context.plugged = false;
@@ -426,6 +490,10 @@ pub fn run() -> Result<(), Error> {
                     }
                 }
             },
+            Event::SaveSettings => {
+                let path = Path::new(SETTINGS_PATH);
+                save_toml(&context.settings, path).map_err(|e| eprintln!("Can't save settings: {}", e)).ok();
+            },
             Event::CheckBattery => {
                 schedule_task(TaskId::CheckBattery, Event::CheckBattery,
                               BATTERY_REFRESH_INTERVAL, &tx, &mut tasks);
@@ -441,6 +509,7 @@ pub fn run() -> Result<(), Error> {
                         let notif = Notification::new(ViewId::LowBatteryNotif,
                                                       "The battery capacity is getting low.".to_string(),
                                                       &mut context.notification_index,
+                                                      context.display.dims,
                                                       &mut context.fonts,
                                                       &tx);
                         view.children_mut().push(Box::new(notif) as Box<View>);
@@ -484,6 +553,9 @@ pub fn run() -> Result<(), Error> {
                 }
 
                 tasks.clear();
+                if let Some(indexer) = context.indexer.take() {
+                    indexer.stop();
+                }
                 while let Some(v) = history.pop() {
                     view.handle_event(&Event::Back, &tx, &mut bus, &mut context);
                     view = v;
@@ -518,7 +590,13 @@ pub fn run() -> Result<(), Error> {
                     GestureEvent::HoldButton(ButtonCode::Power) => {
                         power_off(&mut history, &mut fb, &mut updating, &mut context);
                         break;
-                    }
+                    },
+                    // The reader maps the gestures to actions itself.
+                    GestureEvent::Rotate { quarter_turns, .. } if quarter_turns != 0 &&
+                                                                  view.downcast_ref::<Reader>().is_none() => {
+                        let n = (context.display.rotation + quarter_turns).rem_euclid(4);
+                        tx.send(Event::Select(EntryId::Rotate(n))).unwrap();
+                    },
                     _ => {
                         handle_event(view.as_mut(), &evt, &tx, &mut bus, &mut context);
                     },
@@ -555,13 +633,32 @@ pub fn run() -> Result<(), Error> {
                     updating.insert(tok, rect);
                 }
             },
-            Event::Open(info) => {
+            Event::Open(info, location) => {
                 let info2 = info.clone();
-                if let Some(r) = Reader::new(fb_rect, *info, &tx, &mut context) {
+                let n = info.reader.as_ref().and_then(|r| r.rotation)
+                            .unwrap_or(context.settings.rotation);
+                let rect = if (n - context.display.rotation) % 2 == 0 {
+                    fb_rect
+                } else {
+                    rect![0, 0, fb_rect.height() as i32, fb_rect.width() as i32]
+                };
+                if let Some(mut r) = Reader::new(rect, *info, location, &tx, &mut context) {
+                    if n != context.display.rotation {
+                        match rotate(n, &mut fb, &mut view, &mut history, &tx, &mut context) {
+                            Ok(rect) => {
+                                touch_rotation.store(n, AtomicOrdering::Relaxed);
+                                fb_rect = rect;
+                            },
+                            Err(e) => {
+                                eprintln!("Can't rotate the display: {}", e);
+                                r.resize(fb_rect, &tx, &mut context);
+                            },
+                        }
+                    }
                     history.push(view as Box<View>);
                     view = Box::new(r) as Box<View>;
                 } else {
-                    handle_event(view.as_mut(), &Event::Invalid(info2), &tx, &mut bus, &mut context);
+                    handle_event(view.as_mut(), &Event::Invalid(info2, location), &tx, &mut bus, &mut context);
                 }
             },
             Event::OpenToc(ref toc, current_page) => {
@@ -569,9 +666,22 @@ pub fn run() -> Result<(), Error> {
                 history.push(view as Box<View>);
                 view = Box::new(r) as Box<View>;
             },
+            Event::OpenStatistics(ref html) => {
+                let r = Reader::from_html(fb_rect, html, "Statistics", "css/statistics.css", None, &tx, &mut context);
+                history.push(view as Box<View>);
+                view = Box::new(r) as Box<View>;
+            },
             Event::Back => {
                 if let Some(v) = history.pop() {
                     view = v;
+                    let n = context.settings.rotation;
+                    if view.is::<Home>() && n != context.display.rotation {
+                        if let Ok(rect) = rotate(n, &mut fb, &mut view, &mut history, &tx, &mut context)
+                                                .map_err(|e| eprintln!("Can't rotate the display: {}", e)) {
+                            touch_rotation.store(n, AtomicOrdering::Relaxed);
+                            fb_rect = rect;
+                        }
+                    }
                     view.handle_event(&Event::Reseed, &tx, &mut bus, &mut context);
                 }
             },
@@ -584,6 +694,7 @@ pub fn run() -> Result<(), Error> {
                     let preset_menu = Menu::new(rect, ViewId::PresetMenu, MenuKind::Contextual,
                                                 vec![EntryKind::Command("Remove".to_string(),
                                                                         EntryId::RemovePreset(index))],
+                                                context.display.dims,
                                                 &mut context.fonts);
                     tx.send(Event::Render(*preset_menu.rect(), UpdateMode::Gui)).unwrap();
                     view.children_mut().push(Box::new(preset_menu) as Box<View>);
@@ -621,6 +732,24 @@ pub fn run() -> Result<(), Error> {
                 context.monochrome = !context.monochrome;
                 tx.send(Event::Render(fb_rect, UpdateMode::Gui)).unwrap();
             },
+            Event::Select(EntryId::Rotate(n)) if n != context.display.rotation => {
+                let previous = context.settings.rotation;
+                context.settings.rotation = n;
+                if let Ok(rect) = rotate(n, &mut fb, &mut view, &mut history, &tx, &mut context)
+                                        .map_err(|e| eprintln!("Can't rotate the display: {}", e)) {
+                    touch_rotation.store(n, AtomicOrdering::Relaxed);
+                    fb_rect = rect;
+                } else {
+                    context.settings.rotation = previous;
+                }
+            },
+            Event::Rotate(n) if n != context.display.rotation => {
+                if let Ok(rect) = rotate(n, &mut fb, &mut view, &mut history, &tx, &mut context)
+                                        .map_err(|e| eprintln!("Can't rotate the display: {}", e)) {
+                    touch_rotation.store(n, AtomicOrdering::Relaxed);
+                    fb_rect = rect;
+                }
+            },
             Event::Select(EntryId::ToggleWifi) => {
                 context.settings.wifi = !context.settings.wifi;
                 if context.settings.wifi {
@@ -642,6 +771,7 @@ pub fn run() -> Result<(), Error> {
                 let notif = Notification::new(ViewId::TakeScreenshotNotif,
                                               msg,
                                               &mut context.notification_index,
+                                              context.display.dims,
                                               &mut context.fonts,
                                               &tx);
                 view.children_mut().push(Box::new(notif) as Box<View>);
@@ -0,0 +1,118 @@
+use std::path::Path;
+use failure::{Error, ResultExt};
+use super::dictzip::{DictFile, read_file};
+use super::{Dictionary, Definition, compare_words, equal_range, escape_text, with_extensions};
+
+const BASE64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub struct Dictd {
+    name: String,
+    // Sorted case insensitively.
+    words: Vec<Entry>,
+    data: DictFile,
+}
+
+struct Entry {
+    word: String,
+    offset: u64,
+    size: u64,
+}
+
+impl Dictd {
+    pub fn new<P: AsRef<Path>>(index_path: P) -> Result<Dictd, Error> {
+        let index_path = index_path.as_ref();
+        let buf = read_file(index_path).context("Can't read index file.")?;
+        let index = String::from_utf8_lossy(&buf);
+        let mut words = Vec::new();
+
+        for line in index.lines() {
+            let mut fields = line.split('\t');
+            if let (Some(word), Some(offset), Some(size)) = (fields.next(), fields.next(), fields.next()) {
+                if let (Some(offset), Some(size)) = (decode_number(offset), decode_number(size)) {
+                    words.push(Entry { word: word.to_string(), offset, size });
+                }
+            }
+        }
+
+        words.sort_by(|a, b| compare_words(&a.word, &b.word));
+
+        let dict_path = with_extensions(index_path, &["dict.dz", "dict"])
+                                      .ok_or_else(|| format_err!("Missing data file."))?;
+        let data = DictFile::open(&dict_path)?;
+
+        let mut dict = Dictd {
+            name: index_path.file_stem().unwrap().to_string_lossy().into_owned(),
+            words,
+            data,
+        };
+
+        // The name of the database is stored in a special entry.
+        for key in &["00-database-short", "00databaseshort"] {
+            if let Ok(definitions) = dict.raw_lookup(key) {
+                if let Some(text) = definitions.first() {
+                    let name = text.lines().skip(1).map(str::trim)
+                                   .find(|l| !l.is_empty())
+                                   .unwrap_or_else(|| text.trim());
+                    if !name.is_empty() {
+                        dict.name = name.to_string();
+                    }
+                    break;
+                }
+            }
+        }
+
+        Ok(dict)
+    }
+
+    fn raw_lookup(&mut self, word: &str) -> Result<Vec<String>, Error> {
+        let range = equal_range(&self.words, |e| compare_words(&e.word, word));
+        let mut texts = Vec::with_capacity(range.len());
+        for index in range {
+            let (offset, size) = (self.words[index].offset, self.words[index].size);
+            let buf = self.data.read(offset, size as usize)?;
+            texts.push(String::from_utf8_lossy(&buf).into_owned());
+        }
+        Ok(texts)
+    }
+}
+
+impl Dictionary for Dictd {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn lookup(&mut self, word: &str) -> Result<Vec<Definition>, Error> {
+        let range = equal_range(&self.words, |e| compare_words(&e.word, word));
+        let headwords: Vec<String> = self.words[range].iter().map(|e| e.word.clone()).collect();
+        let texts = self.raw_lookup(word)?;
+
+        Ok(headwords.into_iter().zip(texts.into_iter()).map(|(headword, text)| {
+            let content = text.trim().split("\n\n")
+                              .map(|p| format!("<p>{}</p>", escape_text(p.trim())))
+                              .collect::<Vec<String>>().join("");
+            Definition { headword, content }
+        }).collect())
+    }
+}
+
+// Offsets and sizes are written in base 64.
+fn decode_number(text: &str) -> Option<u64> {
+    text.bytes().try_fold(0u64, |n, b| {
+        BASE64_ALPHABET.iter().position(|&c| c == b)
+                       .map(|d| n << 6 | d as u64)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_number;
+
+    #[test]
+    fn test_decode_number() {
+        assert_eq!(decode_number("A"), Some(0));
+        assert_eq!(decode_number("/"), Some(63));
+        assert_eq!(decode_number("BA"), Some(64));
+        assert_eq!(decode_number("c7"), Some(1851));
+        assert_eq!(decode_number("a!"), None);
+    }
+}
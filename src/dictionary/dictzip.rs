@@ -0,0 +1,214 @@
+//! Random access to dictionary data files.
+//!
+//! The *dictzip* format is a gzip file whose extra field lists the sizes of
+//! independently compressed chunks, which allows decompressing only the chunks
+//! that cover a given range of the uncompressed data.
+
+use std::fs::File;
+use std::path::Path;
+use std::io::{Read, Seek, SeekFrom};
+use flate2::{Decompress, FlushDecompress};
+use flate2::read::GzDecoder;
+use failure::{Error, ResultExt};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const GZIP_DEFLATE: u8 = 8;
+
+const FLAG_HCRC: u8 = 1 << 1;
+const FLAG_EXTRA: u8 = 1 << 2;
+const FLAG_NAME: u8 = 1 << 3;
+const FLAG_COMMENT: u8 = 1 << 4;
+
+pub enum DictFile {
+    Plain(File),
+    Chunked(DictZip),
+    Memory(Vec<u8>),
+}
+
+pub struct DictZip {
+    file: File,
+    chunk_length: usize,
+    // Offsets of the compressed chunks, plus the end offset of the last chunk.
+    offsets: Vec<u64>,
+}
+
+impl DictFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<DictFile, Error> {
+        let path = path.as_ref();
+        let mut file = File::open(path).context("Can't open dictionary data file.")?;
+
+        if !path.extension().map_or(false, |e| e == "dz" || e == "gz") {
+            return Ok(DictFile::Plain(file));
+        }
+
+        if let Some(dz) = DictZip::new(&mut file)? {
+            return Ok(DictFile::Chunked(dz));
+        }
+
+        file.seek(SeekFrom::Start(0))?;
+        let mut buf = Vec::new();
+        GzDecoder::new(file).read_to_end(&mut buf)
+                            .context("Can't decompress dictionary data file.")?;
+        Ok(DictFile::Memory(buf))
+    }
+
+    pub fn read(&mut self, offset: u64, size: usize) -> Result<Vec<u8>, Error> {
+        match *self {
+            DictFile::Plain(ref mut file) => {
+                let mut buf = vec![0; size];
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(&mut buf)?;
+                Ok(buf)
+            },
+            DictFile::Chunked(ref mut dz) => dz.read(offset, size),
+            DictFile::Memory(ref buf) => {
+                let start = offset as usize;
+                start.checked_add(size)
+                     .and_then(|end| buf.get(start..end))
+                     .map(|data| data.to_vec())
+                     .ok_or_else(|| format_err!("Out of bounds read: {}+{}.", start, size))
+            },
+        }
+    }
+}
+
+impl DictZip {
+    // Returns `None` if the file is a regular gzip file.
+    fn new(file: &mut File) -> Result<Option<DictZip>, Error> {
+        let mut header = [0u8; 10];
+        file.read_exact(&mut header)?;
+
+        if header[..2] != GZIP_MAGIC || header[2] != GZIP_DEFLATE {
+            return Err(format_err!("Invalid gzip header."));
+        }
+
+        let flags = header[3];
+        let mut chunk_length = 0;
+        let mut sizes = Vec::new();
+
+        if flags & FLAG_EXTRA != 0 {
+            let xlen = read_u16_le(file)? as usize;
+            let mut extra = vec![0; xlen];
+            file.read_exact(&mut extra)?;
+            let mut i = 0;
+            while i + 4 <= xlen {
+                let len = u16_le(&extra[i+2..]) as usize;
+                let data = &extra[i+4..xlen.min(i+4+len)];
+                // Random access subfield: version, chunk length, chunks count, chunk sizes.
+                if &extra[i..i+2] == b"RA" && data.len() >= 6 {
+                    chunk_length = u16_le(&data[2..]) as usize;
+                    let count = u16_le(&data[4..]) as usize;
+                    sizes = data[6..].chunks(2).take(count)
+                                     .filter(|c| c.len() == 2)
+                                     .map(|c| u16_le(c) as u64).collect();
+                }
+                i += 4 + len;
+            }
+        }
+
+        if chunk_length == 0 || sizes.is_empty() {
+            return Ok(None);
+        }
+
+        for &flag in &[FLAG_NAME, FLAG_COMMENT] {
+            if flags & flag != 0 {
+                let mut byte = [0u8; 1];
+                loop {
+                    file.read_exact(&mut byte)?;
+                    if byte[0] == 0 {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if flags & FLAG_HCRC != 0 {
+            read_u16_le(file)?;
+        }
+
+        let mut offset = file.seek(SeekFrom::Current(0))?;
+        let mut offsets = Vec::with_capacity(sizes.len() + 1);
+        offsets.push(offset);
+        for size in sizes {
+            offset += size;
+            offsets.push(offset);
+        }
+
+        Ok(Some(DictZip {
+            file: file.try_clone()?,
+            chunk_length,
+            offsets,
+        }))
+    }
+
+    fn read(&mut self, offset: u64, size: usize) -> Result<Vec<u8>, Error> {
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+
+        let first = offset as usize / self.chunk_length;
+        let last = (offset as usize + size - 1) / self.chunk_length;
+
+        if last + 1 >= self.offsets.len() {
+            return Err(format_err!("Out of bounds chunk: {}.", last));
+        }
+
+        let mut buf = Vec::with_capacity((last - first + 1) * self.chunk_length);
+
+        for index in first..=last {
+            let start = self.offsets[index];
+            let mut input = vec![0; (self.offsets[index+1] - start) as usize];
+            self.file.seek(SeekFrom::Start(start))?;
+            self.file.read_exact(&mut input)?;
+            // Each chunk is flushed, hence it can be inflated on its own.
+            let mut output = Vec::with_capacity(self.chunk_length);
+            Decompress::new(false).decompress_vec(&input, &mut output, FlushDecompress::Sync)
+                                  .context("Can't inflate dictzip chunk.")?;
+            buf.extend_from_slice(&output);
+        }
+
+        // A truncated or corrupt chunk inflates to less data than expected.
+        let start = offset as usize - first * self.chunk_length;
+        buf.get(start..start + size)
+           .map(|data| data.to_vec())
+           .ok_or_else(|| format_err!("Truncated dictzip chunk: {}.", last))
+    }
+}
+
+// Reads a whole file, decompressing it if its name ends with *.gz*.
+pub fn read_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, Error> {
+    let path = path.as_ref();
+    let file = File::open(path)?;
+    let mut buf = Vec::new();
+    if path.extension().map_or(false, |e| e == "gz") {
+        GzDecoder::new(file).read_to_end(&mut buf)?;
+    } else {
+        let mut file = file;
+        file.read_to_end(&mut buf)?;
+    }
+    Ok(buf)
+}
+
+#[inline]
+fn u16_le(buf: &[u8]) -> u16 {
+    u16::from(buf[0]) | u16::from(buf[1]) << 8
+}
+
+fn read_u16_le(file: &mut File) -> Result<u16, Error> {
+    let mut buf = [0u8; 2];
+    file.read_exact(&mut buf)?;
+    Ok(u16_le(&buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DictFile;
+
+    #[test]
+    fn test_memory_read_bounds() {
+        let mut dict = DictFile::Memory(vec![1, 2, 3, 4]);
+        assert_eq!(dict.read(1, 2).ok(), Some(vec![2, 3]));
+        assert!(dict.read(3, 2).is_err());
+        assert!(dict.read(u64::max_value(), 2).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+//! Offline dictionaries in the *StarDict* and *dictd* formats.
+//!
+//! The dictionaries are read from the `dictionaries` directory. The name of a sub-directory of
+//! this directory is considered to be the language of the dictionaries it contains.
+
+mod dictzip;
+mod stardict;
+mod dictd;
+
+use std::thread;
+use std::ops::Range;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver};
+use glob::glob;
+use failure::Error;
+use self::stardict::StarDict;
+use self::dictd::Dictd;
+
+pub const DICTIONARIES_DIRNAME: &str = "dictionaries";
+
+pub type Dictionaries = BTreeMap<String, Vec<Box<Dictionary>>>;
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub headword: String,
+    // HTML fragment.
+    pub content: String,
+}
+
+pub trait Dictionary: Send {
+    fn name(&self) -> &str;
+    fn lookup(&mut self, word: &str) -> Result<Vec<Definition>, Error>;
+}
+
+pub fn load_dictionaries<P: AsRef<Path>>(dir: P) -> Dictionaries {
+    let dir = dir.as_ref();
+    let mut dictionaries = Dictionaries::new();
+
+    for &(extension, is_stardict) in &[("ifo", true), ("index", false)] {
+        let pattern = dir.join(&format!("**/*.{}", extension));
+        let paths = match glob(&pattern.to_string_lossy()) {
+            Ok(paths) => paths,
+            Err(e) => {
+                eprintln!("Invalid pattern: {}.", e);
+                continue;
+            },
+        };
+
+        for path in paths.filter_map(Result::ok) {
+            let dictionary = if is_stardict {
+                StarDict::new(&path).map(|d| Box::new(d) as Box<Dictionary>)
+            } else {
+                Dictd::new(&path).map(|d| Box::new(d) as Box<Dictionary>)
+            };
+
+            match dictionary {
+                Ok(dictionary) => {
+                    let language = path.strip_prefix(dir).ok()
+                                       .filter(|p| p.components().count() > 1)
+                                       .and_then(|p| p.components().next())
+                                       .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                                       .unwrap_or_default();
+                    dictionaries.entry(language).or_insert_with(Vec::new).push(dictionary);
+                },
+                Err(e) => eprintln!("Can't load {}: {}.", path.display(), e),
+            }
+        }
+    }
+
+    for list in dictionaries.values_mut() {
+        list.sort_by(|a, b| a.name().cmp(b.name()));
+    }
+
+    dictionaries
+}
+
+// Loads the dictionaries in a background thread: the index of a large dictionary
+// can take a few seconds to read.
+pub fn spawn_loader<P: AsRef<Path>>(dir: P) -> Receiver<Dictionaries> {
+    let dir = dir.as_ref().to_path_buf();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        tx.send(load_dictionaries(&dir)).ok();
+    });
+    rx
+}
+
+// Looks up the first lemma of *word* that has definitions.
+pub fn lookup(dictionary: &mut Dictionary, word: &str) -> Vec<Definition> {
+    for lemma in lemmas(word) {
+        match dictionary.lookup(&lemma) {
+            Ok(definitions) => {
+                if !definitions.is_empty() {
+                    return definitions;
+                }
+            },
+            Err(e) => {
+                eprintln!("Can't look up {} in {}: {}.", lemma, dictionary.name(), e);
+                break;
+            },
+        }
+    }
+    Vec::new()
+}
+
+// Returns the words that should be looked up, in order, to find the definition
+// of the given word: the trimmed word itself, then its lower case version, then
+// the results of a few crude lemmatisation rules.
+pub fn lemmas(word: &str) -> Vec<String> {
+    let mut candidates: Vec<String> = Vec::new();
+    let word: String = word.trim_matches(|c: char| !c.is_alphanumeric())
+                           .chars().filter(|&c| c != '\u{AD}').collect();
+
+    if word.is_empty() {
+        return candidates;
+    }
+
+    candidates.push(word.clone());
+
+    let mut lower = word.to_lowercase();
+    candidates.push(lower.clone());
+
+    for suffix in &["'s", "’s"] {
+        if lower.ends_with(suffix) {
+            let len = lower.len() - suffix.len();
+            lower.truncate(len);
+            candidates.push(lower.clone());
+            break;
+        }
+    }
+
+    let rules = [("ies", "y"), ("ves", "f"), ("ves", "fe"), ("s", ""), ("es", ""),
+                 ("ied", "y"), ("ed", "e"), ("ed", ""), ("ing", "e"), ("ing", ""),
+                 ("ier", "y"), ("iest", "y"), ("er", ""), ("est", ""), ("ly", "")];
+
+    for &(suffix, replacement) in &rules {
+        if lower.ends_with(suffix) && lower.chars().count() > suffix.len() + 1 {
+            let stem = &lower[..lower.len() - suffix.len()];
+            candidates.push(format!("{}{}", stem, replacement));
+            // Doubled final consonant: *stopped*, *running*.
+            let mut chars = stem.chars().rev();
+            if let (Some(a), Some(b)) = (chars.next(), chars.next()) {
+                if a == b && replacement.is_empty() && !"aeiouls".contains(a) {
+                    candidates.push(stem[..stem.len() - a.len_utf8()].to_string());
+                }
+            }
+        }
+    }
+
+    let mut unique = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        if !unique.contains(&candidate) {
+            unique.push(candidate);
+        }
+    }
+
+    unique
+}
+
+fn compare_words(a: &str, b: &str) -> Ordering {
+    a.chars().flat_map(char::to_lowercase)
+     .cmp(b.chars().flat_map(char::to_lowercase))
+}
+
+// The range of the elements of a sorted slice for which *f* returns `Equal`.
+fn equal_range<T, F>(v: &[T], f: F) -> Range<usize> where F: Fn(&T) -> Ordering {
+    let start = v.partition_point(|x| f(x) == Ordering::Less);
+    let end = start + v[start..].partition_point(|x| f(x) != Ordering::Greater);
+    start..end
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\n', "<br/>")
+}
+
+// Finds the sibling of the given file that has one of the given extensions.
+fn with_extensions(path: &Path, extensions: &[&str]) -> Option<PathBuf> {
+    let stem = path.file_stem()?;
+    extensions.iter().map(|e| {
+        let mut name = stem.to_os_string();
+        name.push(".");
+        name.push(e);
+        path.with_file_name(name)
+    }).find(|p| p.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lemmas;
+
+    #[test]
+    fn test_lemmas() {
+        assert_eq!(lemmas("“Whales,”"), vec!["Whales", "whales", "whale", "whal"]);
+        assert!(lemmas("boxes").contains(&"box".to_string()));
+        assert!(lemmas("stopped").contains(&"stop".to_string()));
+        assert!(lemmas("berries").contains(&"berry".to_string()));
+        assert!(lemmas("Captain's").contains(&"captain".to_string()));
+        assert!(lemmas("...").is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+use std::path::Path;
+use fnv::FnvHashMap;
+use failure::{Error, ResultExt};
+use super::dictzip::{DictFile, read_file};
+use super::{Dictionary, Definition, compare_words, equal_range, escape_text, with_extensions};
+
+pub struct StarDict {
+    name: String,
+    words: Vec<Entry>,
+    // Indices of *words*, sorted case insensitively.
+    order: Vec<u32>,
+    // Synonyms and the index of the word they refer to, sorted case insensitively.
+    synonyms: Vec<(String, u32)>,
+    same_type_sequence: Option<String>,
+    data: DictFile,
+}
+
+struct Entry {
+    word: String,
+    offset: u64,
+    size: u32,
+}
+
+impl StarDict {
+    pub fn new<P: AsRef<Path>>(ifo_path: P) -> Result<StarDict, Error> {
+        let ifo_path = ifo_path.as_ref();
+        let ifo = String::from_utf8(read_file(ifo_path)?)?;
+        let mut info = FnvHashMap::default();
+
+        for line in ifo.lines().skip(1) {
+            if let Some(index) = line.find('=') {
+                info.insert(line[..index].trim().to_string(),
+                            line[index+1..].trim().to_string());
+            }
+        }
+
+        let name = info.get("bookname").cloned().unwrap_or_else(|| {
+            ifo_path.file_stem().unwrap().to_string_lossy().into_owned()
+        });
+        let offset_size = if info.get("idxoffsetbits").map(String::as_str) == Some("64") { 8 } else { 4 };
+        let same_type_sequence = info.get("sametypesequence").cloned().filter(|s| !s.is_empty());
+
+        let idx_path = with_extensions(ifo_path, &["idx", "idx.gz"])
+                                     .ok_or_else(|| format_err!("Missing index file."))?;
+        let buf = read_file(&idx_path).context("Can't read index file.")?;
+        let mut words = Vec::new();
+        let mut i = 0;
+
+        while let Some(len) = buf[i..].iter().position(|&b| b == 0) {
+            let word = String::from_utf8_lossy(&buf[i..i+len]).into_owned();
+            i += len + 1;
+            if i + offset_size + 4 > buf.len() {
+                break;
+            }
+            let offset = read_be(&buf[i..i+offset_size]);
+            i += offset_size;
+            let size = read_be(&buf[i..i+4]) as u32;
+            i += 4;
+            words.push(Entry { word, offset, size });
+        }
+
+        let mut order: Vec<u32> = (0..words.len() as u32).collect();
+        order.sort_by(|&a, &b| compare_words(&words[a as usize].word, &words[b as usize].word));
+
+        let mut synonyms = Vec::new();
+
+        if let Some(syn_path) = with_extensions(ifo_path, &["syn", "syn.gz"]) {
+            let buf = read_file(&syn_path).context("Can't read synonyms file.")?;
+            let mut i = 0;
+            while let Some(len) = buf[i..].iter().position(|&b| b == 0) {
+                let word = String::from_utf8_lossy(&buf[i..i+len]).into_owned();
+                i += len + 1;
+                if i + 4 > buf.len() {
+                    break;
+                }
+                let index = read_be(&buf[i..i+4]) as u32;
+                i += 4;
+                if (index as usize) < words.len() {
+                    synonyms.push((word, index));
+                }
+            }
+            synonyms.sort_by(|a, b| compare_words(&a.0, &b.0));
+        }
+
+        let dict_path = with_extensions(ifo_path, &["dict.dz", "dict"])
+                                      .ok_or_else(|| format_err!("Missing data file."))?;
+        let data = DictFile::open(&dict_path)?;
+
+        Ok(StarDict {
+            name,
+            words,
+            order,
+            synonyms,
+            same_type_sequence,
+            data,
+        })
+    }
+
+    // Converts the data of an entry into an HTML fragment.
+    fn format(&self, buf: &[u8]) -> String {
+        let mut html = String::new();
+        let mut i = 0;
+        let types: Vec<u8> = self.same_type_sequence.as_ref()
+                                 .map(|s| s.bytes().collect()).unwrap_or_default();
+        let mut index = 0;
+
+        while i < buf.len() {
+            let (kind, last) = if types.is_empty() {
+                i += 1;
+                (buf[i-1], false)
+            } else if index < types.len() {
+                index += 1;
+                (types[index-1], index == types.len())
+            } else {
+                break;
+            };
+
+            // Lower case types are textual, upper case types are binary.
+            let content = if kind.is_ascii_lowercase() {
+                let end = if last {
+                    buf.len()
+                } else {
+                    buf[i..].iter().position(|&b| b == 0).map_or(buf.len(), |p| i + p)
+                };
+                let text = String::from_utf8_lossy(&buf[i..end]).into_owned();
+                i = end + 1;
+                Some(text)
+            } else {
+                let size = if last {
+                    buf.len() - i
+                } else if i + 4 <= buf.len() {
+                    i += 4;
+                    read_be(&buf[i-4..i]) as usize
+                } else {
+                    break;
+                };
+                i += size;
+                None
+            };
+
+            if let Some(text) = content {
+                match kind {
+                    b'h' | b'g' | b'x' => html.push_str(&text),
+                    b't' => html.push_str(&format!("<p>[{}]</p>", escape_text(&text))),
+                    b'r' => (),
+                    _ => html.push_str(&format!("<p>{}</p>", escape_text(&text))),
+                }
+            }
+        }
+
+        html
+    }
+}
+
+impl Dictionary for StarDict {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn lookup(&mut self, word: &str) -> Result<Vec<Definition>, Error> {
+        let mut indices: Vec<u32> = {
+            let words = &self.words;
+            let range = equal_range(&self.order, |&index| compare_words(&words[index as usize].word, word));
+            self.order[range].to_vec()
+        };
+
+        let range = equal_range(&self.synonyms, |s| compare_words(&s.0, word));
+        for &(_, index) in &self.synonyms[range] {
+            if !indices.contains(&index) {
+                indices.push(index);
+            }
+        }
+
+        let mut definitions = Vec::with_capacity(indices.len());
+
+        for index in indices {
+            let (headword, offset, size) = {
+                let entry = &self.words[index as usize];
+                (entry.word.clone(), entry.offset, entry.size)
+            };
+            let buf = self.data.read(offset, size as usize)?;
+            definitions.push(Definition {
+                headword,
+                content: self.format(&buf),
+            });
+        }
+
+        Ok(definitions)
+    }
+}
+
+fn read_be(buf: &[u8]) -> u64 {
+    buf.iter().fold(0, |n, &b| n << 8 | u64::from(b))
+}
@@ -0,0 +1,184 @@
+//! Detection of the text columns of fixed-layout pages.
+//!
+//! The horizontal extent of the text is projected onto the x axis, either from the
+//! bounding boxes of the words, or from the ink of the rendered page when there's no
+//! text layer. The columns are the covered ranges of this profile, separated by gutters.
+
+use geom::Rectangle;
+use super::{Document, Location};
+
+// Pages with fewer words are analyzed through their pixmap.
+const MIN_WORDS_COUNT: usize = 16;
+// Pixels darker than this are considered ink.
+const INK_THRESHOLD: u8 = 160;
+// Width, in pixels, of the pixmap used to compute the ink profile.
+const PROFILE_WIDTH: f32 = 600.0;
+// Bins whose count doesn't exceed this fraction of the maximum count belong to a gutter.
+const GUTTER_DENSITY: f32 = 0.05;
+// Minimum width of a gutter, relative to the width of the page.
+const MIN_GUTTER_RATIO: f32 = 0.02;
+// Columns narrower than this fraction of the text width are merged with their neighbors.
+const MIN_COLUMN_RATIO: f32 = 0.15;
+
+// Returns the columns of the given page, in reading order.
+pub fn detect_columns(doc: &mut Document, index: usize) -> Vec<Rectangle> {
+    let (width, _) = match doc.dims(index) {
+        Some(dims) => dims,
+        None => return Vec::new(),
+    };
+
+    let rects = doc.words(Location::Exact(index as f64))
+                   .map(|(words, _)| words.into_iter()
+                                          .map(|w| w.rect)
+                                          .filter(|r| r.width() > 0 && r.height() > 0)
+                                          .collect::<Vec<Rectangle>>())
+                   .unwrap_or_default();
+
+    if rects.len() >= MIN_WORDS_COUNT {
+        columns_from_rects(&rects, width.ceil() as usize)
+    } else {
+        let scale = PROFILE_WIDTH / width;
+        doc.pixmap(Location::Exact(index as f64), scale).map(|(pixmap, _)| {
+            // Each horizontal run of ink becomes a rectangle.
+            let mut rects = Vec::new();
+            for y in 0..pixmap.height as i32 {
+                let mut start = None;
+                for x in 0..=pixmap.width as i32 {
+                    let ink = x < pixmap.width as i32 &&
+                              pixmap.data[(y * pixmap.width as i32 + x) as usize] < INK_THRESHOLD;
+                    match start {
+                        None if ink => start = Some(x),
+                        Some(s) if !ink => {
+                            rects.push(rect![s, y, x, y + 1]);
+                            start = None;
+                        },
+                        _ => (),
+                    }
+                }
+            }
+            columns_from_rects(&rects, pixmap.width as usize).into_iter().map(|r| {
+                rect![(r.min.x as f32 / scale).floor() as i32,
+                      (r.min.y as f32 / scale).floor() as i32,
+                      (r.max.x as f32 / scale).ceil() as i32,
+                      (r.max.y as f32 / scale).ceil() as i32]
+            }).collect()
+        }).unwrap_or_default()
+    }
+}
+
+fn columns_from_rects(rects: &[Rectangle], width: usize) -> Vec<Rectangle> {
+    let mut profile = vec![0u32; width];
+
+    for r in rects {
+        let x_min = r.min.x.max(0) as usize;
+        let x_max = (r.max.x.max(0) as usize).min(width);
+        for count in &mut profile[x_min.min(x_max)..x_max] {
+            *count += 1;
+        }
+    }
+
+    let min_gap = (MIN_GUTTER_RATIO * width as f32).ceil() as usize;
+    let ranges = text_ranges(&profile, min_gap);
+
+    // Each rectangle belongs to the first column it overlaps, and is clipped to it.
+    let mut columns: Vec<Option<Rectangle>> = vec![None; ranges.len()];
+
+    for r in rects {
+        if let Some(i) = ranges.iter().position(|&(start, end)| r.min.x < end as i32 && r.max.x > start as i32) {
+            let (start, end) = ranges[i];
+            let r = rect![r.min.x.max(start as i32), r.min.y,
+                          r.max.x.min(end as i32), r.max.y];
+            columns[i] = Some(columns[i].map_or(r, |c| {
+                rect![c.min.x.min(r.min.x), c.min.y.min(r.min.y),
+                      c.max.x.max(r.max.x), c.max.y.max(r.max.y)]
+            }));
+        }
+    }
+
+    columns.into_iter().filter_map(|c| c).collect()
+}
+
+// Returns the ranges of the profile that contain text, separated by gutters
+// at least `min_gap` wide.
+fn text_ranges(profile: &[u32], min_gap: usize) -> Vec<(usize, usize)> {
+    let max_count = profile.iter().cloned().max().unwrap_or(0);
+
+    if max_count == 0 {
+        return Vec::new();
+    }
+
+    let threshold = (GUTTER_DENSITY * max_count as f32) as u32;
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let mut start = None;
+    let mut gap = 0;
+
+    for (x, &count) in profile.iter().enumerate() {
+        if count > threshold {
+            if start.is_none() {
+                start = Some(x);
+            }
+            gap = 0;
+        } else if let Some(s) = start {
+            gap += 1;
+            if gap >= min_gap {
+                ranges.push((s, x + 1 - gap));
+                start = None;
+            }
+        }
+    }
+
+    if let Some(s) = start {
+        ranges.push((s, profile.len() - gap));
+    }
+
+    if ranges.len() < 2 {
+        return ranges;
+    }
+
+    let text_width = ranges[ranges.len() - 1].1 - ranges[0].0;
+    let min_width = (MIN_COLUMN_RATIO * text_width as f32) as usize;
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+
+    for (start, end) in ranges {
+        let narrow = end - start < min_width;
+        match merged.last_mut() {
+            Some(last) if narrow || last.1 - last.0 < min_width => last.1 = end,
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_columns() {
+        let mut rects = Vec::new();
+        for i in 0..30 {
+            let y = 10 * i;
+            rects.push(rect![10, y, 90, y + 8]);
+            rects.push(rect![110, y, 190, y + 8]);
+        }
+        // A title spanning both columns.
+        rects.push(rect![40, -20, 160, -10]);
+        let columns = columns_from_rects(&rects, 200);
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[0], rect![10, -20, 90, 298]);
+        assert_eq!(columns[1], rect![110, 0, 190, 298]);
+    }
+
+    #[test]
+    fn test_narrow_ranges() {
+        let mut profile = vec![0; 200];
+        for x in 2..6 {
+            profile[x] = 10;
+        }
+        for x in 20..190 {
+            profile[x] = 10;
+        }
+        assert_eq!(text_ranges(&profile, 4), vec![(2, 190)]);
+    }
+}
@@ -0,0 +1,179 @@
+//! Detection of the content of fixed-layout pages, for automatic cropping.
+//!
+//! The boundary box given by the document is used when it's meaningful. Otherwise,
+//! for scanned pages, the ink of the rendered page is scanned row by row, and column by
+//! column, optionally discarding the specks and the page numbers.
+
+use geom::Rectangle;
+use framebuffer::Pixmap;
+use super::{Document, Location};
+
+// Pixels darker than this are considered ink.
+const INK_THRESHOLD: u8 = 160;
+// Width, in pixels, of the pixmap that is scanned.
+const SCAN_WIDTH: f32 = 600.0;
+// Boundary boxes covering more than this fraction of the page are assumed to come from
+// scanned images.
+const MAX_BBOX_RATIO: f32 = 0.95;
+// Bands holding less than this fraction of the ink of the page are specks.
+const SPECK_RATIO: f32 = 0.002;
+// Gaps narrower than this fraction of the page don't separate bands.
+const MIN_GAP_RATIO: f32 = 0.01;
+// An isolated band at the top or bottom of the page, shorter and narrower than the
+// following fractions of the page, is a page number.
+const FOLIO_GAP_RATIO: f32 = 0.02;
+const FOLIO_HEIGHT_RATIO: f32 = 0.03;
+const FOLIO_WIDTH_RATIO: f32 = 0.2;
+
+// Returns the bounding box of the content of the given page, in page coordinates.
+pub fn content_frame(doc: &mut Document, index: usize, ignore_noise: bool) -> Option<Rectangle> {
+    let (width, height) = doc.dims(index)?;
+
+    if !ignore_noise {
+        if let Some(bbox) = doc.boundary_box(index) {
+            if bbox.min.x < bbox.max.x && bbox.min.y < bbox.max.y &&
+               bbox.width() as f32 * bbox.height() as f32 <= MAX_BBOX_RATIO * width * height {
+                return Some(bbox);
+            }
+        }
+    }
+
+    let scale = SCAN_WIDTH / width;
+    let (pixmap, _) = doc.pixmap(Location::Exact(index as f64), scale)?;
+
+    pixmap_frame(&pixmap, ignore_noise).map(|r| {
+        rect![(r.min.x as f32 / scale).floor() as i32,
+              (r.min.y as f32 / scale).floor() as i32,
+              (r.max.x as f32 / scale).ceil() as i32,
+              (r.max.y as f32 / scale).ceil() as i32]
+    })
+}
+
+fn pixmap_frame(pixmap: &Pixmap, ignore_noise: bool) -> Option<Rectangle> {
+    let width = pixmap.width as usize;
+    let height = pixmap.height as usize;
+    let is_ink = |x: usize, y: usize| pixmap.data[y * width + x] < INK_THRESHOLD;
+
+    let rows: Vec<u32> = (0..height).map(|y| {
+        (0..width).filter(|&x| is_ink(x, y)).count() as u32
+    }).collect();
+
+    let mut bands = if ignore_noise {
+        let min_gap = (MIN_GAP_RATIO * height as f32).ceil() as usize;
+        let mut bands = dense_bands(&rows, min_gap);
+        let is_folio = |band: (usize, usize), neighbor: (usize, usize)| {
+            let gap = if band.0 > neighbor.0 { band.0 - neighbor.1 } else { neighbor.0 - band.1 };
+            let (mut x_min, mut x_max) = (width, 0);
+            for y in band.0..band.1 {
+                for x in (0..width).filter(|&x| is_ink(x, y)) {
+                    x_min = x_min.min(x);
+                    x_max = x_max.max(x + 1);
+                }
+            }
+            gap as f32 >= FOLIO_GAP_RATIO * height as f32 &&
+            ((band.1 - band.0) as f32) < FOLIO_HEIGHT_RATIO * height as f32 &&
+            (x_max.saturating_sub(x_min) as f32) < FOLIO_WIDTH_RATIO * width as f32
+        };
+        if bands.len() > 1 && is_folio(bands[0], bands[1]) {
+            bands.remove(0);
+        }
+        let len = bands.len();
+        if len > 1 && is_folio(bands[len - 1], bands[len - 2]) {
+            bands.pop();
+        }
+        bands
+    } else {
+        dense_bands(&rows, 1)
+    };
+
+    let y_min = bands.first()?.0;
+    let y_max = bands.pop()?.1;
+
+    let columns: Vec<u32> = (0..width).map(|x| {
+        (y_min..y_max).filter(|&y| is_ink(x, y)).count() as u32
+    }).collect();
+
+    let mut bands = if ignore_noise {
+        let min_gap = (MIN_GAP_RATIO * width as f32).ceil() as usize;
+        dense_bands(&columns, min_gap)
+    } else {
+        dense_bands(&columns, 1)
+    };
+
+    let x_min = bands.first()?.0;
+    let x_max = bands.pop()?.1;
+
+    Some(rect![x_min as i32, y_min as i32, x_max as i32, y_max as i32])
+}
+
+// Returns the inked ranges of the profile, separated by gaps at least `min_gap` wide.
+// When `min_gap` is greater than one, the ranges that are specks are discarded.
+fn dense_bands(profile: &[u32], min_gap: usize) -> Vec<(usize, usize)> {
+    let mut bands: Vec<(usize, usize)> = Vec::new();
+    let mut start = None;
+    let mut gap = 0;
+
+    for (i, &count) in profile.iter().enumerate() {
+        if count > 0 {
+            if start.is_none() {
+                start = Some(i);
+            }
+            gap = 0;
+        } else if let Some(s) = start {
+            gap += 1;
+            if gap >= min_gap {
+                bands.push((s, i + 1 - gap));
+                start = None;
+            }
+        }
+    }
+
+    if let Some(s) = start {
+        bands.push((s, profile.len() - gap));
+    }
+
+    if min_gap > 1 {
+        let total: u32 = profile.iter().sum();
+        bands.retain(|&(s, e)| {
+            profile[s..e].iter().sum::<u32>() as f32 >= SPECK_RATIO * total as f32
+        });
+    }
+
+    bands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color::BLACK;
+
+    fn page() -> Pixmap {
+        let mut pixmap = Pixmap::new(200, 300);
+        let mut ink = |x: usize, y: usize| pixmap.data[y * 200 + x] = BLACK;
+        // Lines of text.
+        for y in (20..250).filter(|y| (y - 20) % 4 < 2) {
+            for x in 30..170 {
+                ink(x, y);
+            }
+        }
+        // A speck.
+        ink(5, 5);
+        // A page number.
+        for y in 280..286 {
+            for x in 95..105 {
+                ink(x, y);
+            }
+        }
+        pixmap
+    }
+
+    #[test]
+    fn test_frame() {
+        assert_eq!(pixmap_frame(&page(), false), Some(rect![5, 5, 170, 286]));
+    }
+
+    #[test]
+    fn test_frame_without_noise() {
+        assert_eq!(pixmap_frame(&page(), true), Some(rect![30, 20, 170, 250]));
+    }
+}
@@ -7,7 +7,7 @@ use std::rc::Rc;
 use std::path::Path;
 use std::ffi::{CStr, CString};
 use std::os::unix::ffi::OsStrExt;
-use super::{Document, Location, BoundedText, TocEntry};
+use super::{Document, Location, BoundedText, TocEntry, RenderLayers};
 use framebuffer::Pixmap;
 use geom::Rectangle;
 
@@ -29,6 +29,7 @@ pub struct DjvuOpener(Rc<DjvuContext>);
 pub struct DjvuDocument {
     ctx: Rc<DjvuContext>,
     doc: *mut ExoDocument,
+    render_mode: RenderMode,
 }
 
 pub struct DjvuPage<'a> {
@@ -90,6 +91,7 @@ impl DjvuOpener {
                 Some(DjvuDocument {
                     ctx: self.0.clone(),
                     doc,
+                    render_mode: DDJVU_RENDER_COLOR,
                 })
             }
         }
@@ -116,6 +118,19 @@ impl Document for DjvuDocument {
         self.page(index).and_then(|page| page.pixmap(scale)).map(|pixmap| (pixmap, index as f64))
     }
 
+    fn has_layers(&self) -> bool {
+        true
+    }
+
+    fn set_render_layers(&mut self, layers: RenderLayers) {
+        self.render_mode = match layers {
+            RenderLayers::Color => DDJVU_RENDER_COLOR,
+            RenderLayers::Mask => DDJVU_RENDER_MASKONLY,
+            RenderLayers::Foreground => DDJVU_RENDER_FOREGROUND,
+            RenderLayers::Background => DDJVU_RENDER_BACKGROUND,
+        };
+    }
+
     fn toc(&mut self) -> Option<Vec<TocEntry>> {
         unsafe {
             let mut exp = ddjvu_document_get_outline(self.doc);
@@ -374,9 +389,16 @@ impl<'a> DjvuPage<'a> {
             let len = (rect.w * rect.h) as usize;
             let mut data = vec![0xff; len];
 
-            ddjvu_page_render(self.page, DDJVU_RENDER_COLOR,
-                              &rect, &rect, fmt,
-                              rect.w as libc::c_ulong, data.as_mut_ptr());
+            // Fall back to the composite image when the page lacks the requested layer.
+            let rendered = ddjvu_page_render(self.page, self.doc.render_mode,
+                                             &rect, &rect, fmt,
+                                             rect.w as libc::c_ulong, data.as_mut_ptr());
+
+            if rendered == 0 && self.doc.render_mode != DDJVU_RENDER_COLOR {
+                ddjvu_page_render(self.page, DDJVU_RENDER_COLOR,
+                                  &rect, &rect, fmt,
+                                  rect.w as libc::c_ulong, data.as_mut_ptr());
+            }
 
             let job = ddjvu_page_job(self.page);
 
@@ -25,6 +25,11 @@ pub const DDJVU_FORMAT_RGBMASK32: FormatStyle = 3;
 pub const DDJVU_FORMAT_GREY8: FormatStyle = 4;
 
 pub const DDJVU_RENDER_COLOR: RenderMode = 0;
+pub const DDJVU_RENDER_BLACK: RenderMode = 1;
+pub const DDJVU_RENDER_COLORONLY: RenderMode = 2;
+pub const DDJVU_RENDER_MASKONLY: RenderMode = 3;
+pub const DDJVU_RENDER_BACKGROUND: RenderMode = 4;
+pub const DDJVU_RENDER_FOREGROUND: RenderMode = 5;
 
 pub const MINIEXP_NIL: *mut MiniExp = 0 as *mut MiniExp;
 pub const MINIEXP_DUMMY: *mut MiniExp = 2 as *mut MiniExp;
@@ -51,6 +51,7 @@ impl Selector {
 pub struct Declaration {
     pub name: String,
     pub value: String,
+    pub important: bool,
 }
 
 impl Default for Declaration {
@@ -58,6 +59,7 @@ impl Default for Declaration {
         Declaration {
             name: String::default(),
             value: String::default(),
+            important: false,
         }
     }
 }
@@ -206,7 +208,6 @@ impl<'a> CssParser<'a> {
                     self.advance(1);
                     let offset = self.offset;
                     while !self.eof() {
-                        // TODO: Skip !important.
                         self.advance_while(|&c| c != '"' && c != ';' && c != '}');
                         if let Some('"') = self.next() {
                             self.advance(1);
@@ -216,7 +217,14 @@ impl<'a> CssParser<'a> {
                             break;
                         }
                     }
-                    d.value = self.input[offset..self.offset].trim().to_string();
+                    let mut value = self.input[offset..self.offset].trim();
+                    if let Some(index) = value.rfind('!') {
+                        if value[index+1..].trim().eq_ignore_ascii_case("important") {
+                            d.important = true;
+                            value = value[..index].trim_end();
+                        }
+                    }
+                    d.value = value.to_string();
                 },
                 Some(';') => {
                     self.advance(1);
@@ -320,4 +328,17 @@ mod tests {
         let (css, _) = CssParser::new(text).parse(RuleKind::User);
         println!("{:?}", css);
     }
+
+    #[test]
+    fn important_css() {
+        let text = "a { b: c !important; d: \"!\" ! IMPORTANT; e: f }";
+        let (css, _) = CssParser::new(text).parse(RuleKind::User);
+        let declarations = &css[0].declarations;
+        assert_eq!(declarations[0].value, "c");
+        assert!(declarations[0].important);
+        assert_eq!(declarations[1].value, "\"!\"");
+        assert!(declarations[1].important);
+        assert_eq!(declarations[2].value, "f");
+        assert!(!declarations[2].important);
+    }
 }
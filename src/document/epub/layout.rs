@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 use fnv::FnvHashMap;
 use geom::{Point, Rectangle, Edge};
-use font::{FontFamily, Font, RenderPlan};
+use font::{FontFamily, Font, FallbackFonts, RenderPlan};
 use super::dom::Node;
 use hyphenation::Language;
 use color::BLACK;
@@ -204,6 +204,14 @@ pub struct Fonts {
 }
 
 impl Fonts {
+    pub fn set_fallbacks(&mut self, fallbacks: &FallbackFonts) {
+        self.serif.set_fallbacks(fallbacks);
+        self.sans_serif.set_fallbacks(fallbacks);
+        self.monospace.set_fallbacks(fallbacks);
+        self.cursive.set_fallbacks(fallbacks);
+        self.fantasy.set_fallbacks(fallbacks);
+    }
+
     pub fn get_mut(&mut self, font_kind: FontKind, font_style: FontStyle, font_weight: FontWeight) -> &mut Font {
         match font_kind {
             FontKind::Serif => {
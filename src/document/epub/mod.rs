@@ -11,6 +11,8 @@ use std::io::Read;
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 use std::borrow::Cow;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering as AtomicOrdering;
 use fnv::FnvHashMap;
 use zip::ZipArchive;
 use hyphenation::{Standard, Load, Hyphenator, Iter};
@@ -18,8 +20,9 @@ use failure::Error;
 use either::Either;
 use framebuffer::{Framebuffer, Pixmap};
 use helpers::Normalize;
-use font::{FontOpener, FontFamily};
+use font::{FontOpener, FontFamily, FallbackFonts, open_fallback_fonts};
 use document::{Document, Location, TocEntry, BoundedText};
+use document::TextAlign as ForcedTextAlign;
 use document::pdf::PdfOpener;
 use paragraph_breaker::{Item as ParagraphItem, Breakpoint, INFINITE_PENALTY};
 use paragraph_breaker::{total_fit, standard_fit};
@@ -37,7 +40,7 @@ use self::layout::{TextAlign, ParagraphElement, TextElement, ImageElement, Displ
 use self::layout::{hyph_lang, DEFAULT_HYPH_LANG};
 use self::layout::{EM_SPACE_RATIOS, WORD_SPACE_RATIOS, FONT_SPACES};
 use self::layout::{collapse_margins, SpecialSplitter, SPECIAL_CHARS};
-use self::style::{Stylesheet, specified_values};
+use self::style::{Stylesheet, specified_values, bound_font_size};
 use self::css::{CssParser, RuleKind};
 use self::xml::{XmlParser, decode_entities};
 
@@ -54,7 +57,6 @@ const USER_STYLESHEET: &str = "user.css";
 type Page = Vec<DrawCommand>;
 type UriCache = FnvHashMap<String, f64>;
 
-// TODO: Add min_font_size.
 pub struct EpubDocument {
     archive: ZipArchive<File>,
     content: Node,
@@ -62,10 +64,20 @@ pub struct EpubDocument {
     spine: Vec<Chunk>,
     cache: FnvHashMap<usize, Vec<Page>>,
     fonts: Option<Fonts>,
+    fallback_fonts: Option<FallbackFonts>,
     ignore_document_css: bool,
+    // Rules applied after the ones of the user's stylesheet.
+    user_stylesheet: String,
+    // The alignment that replaces the left and justified alignments.
+    text_align: Option<TextAlign>,
+    hyphenation: bool,
     margin: Edge,
     // Font size in points.
     font_size: f32,
+    // Smallest rendered font size in points.
+    min_font_size: f32,
+    // Largest allowed ratio between the font size of an element and the body font size.
+    max_font_size_ratio: Option<f32>,
     // Line height in ems.
     line_height: f32,
     // Page dimensions in pixels.
@@ -160,9 +172,15 @@ impl EpubDocument {
             spine,
             cache: FnvHashMap::default(),
             fonts: None,
+            fallback_fonts: None,
             ignore_document_css: false,
+            user_stylesheet: String::new(),
+            text_align: None,
+            hyphenation: true,
             margin,
             font_size: DEFAULT_FONT_SIZE,
+            min_font_size: 0.0,
+            max_font_size_ratio: None,
             line_height,
             dims: (DEFAULT_WIDTH, DEFAULT_HEIGHT),
             dpi: DEFAULT_DPI,
@@ -177,6 +195,35 @@ impl EpubDocument {
         self.offset(self.spine.len())
     }
 
+    // The text of each block of the book, with the location of its first character.
+    // Returns `None` if *running* is cleared before the end of the book.
+    pub fn paragraphs(&mut self, running: &AtomicBool) -> Option<Vec<(String, f64)>> {
+        let mut paragraphs = Vec::new();
+
+        for index in 0..self.spine.len() {
+            if !running.load(AtomicOrdering::Relaxed) {
+                return None;
+            }
+
+            let mut text = String::new();
+
+            if let Ok(mut zf) = self.archive.by_name(&self.spine[index].path) {
+                zf.read_to_string(&mut text).ok();
+            }
+
+            let start_offset = self.offset(index);
+            let root = XmlParser::new(&text).parse();
+
+            if let Some(body) = root.find("body") {
+                let mut paragraph = (String::new(), 0.0);
+                collect_paragraphs(body, start_offset, &mut paragraph, &mut paragraphs);
+                flush_paragraph(&mut paragraph, &mut paragraphs);
+            }
+        }
+
+        Some(paragraphs)
+    }
+
     fn vertebra_coordinates_with<F>(&self, test: F) -> (usize, usize) where F: Fn(usize, usize) -> bool {
         let mut start_offset = 0;
         let mut end_offset = start_offset;
@@ -221,13 +268,6 @@ impl EpubDocument {
         self.cache.clear();
     }
 
-    fn set_ignore_document_css(&mut self, value: bool) {
-        if self.ignore_document_css != value {
-            self.ignore_document_css = value;
-            self.cache.clear();
-        }
-    }
-
     #[inline]
     fn rect(&self) -> Rectangle {
         let (width, height) = self.dims;
@@ -384,6 +424,11 @@ impl EpubDocument {
             stylesheet.append(&mut css);
         }
 
+        if !self.user_stylesheet.is_empty() {
+            let (mut css, _) = CssParser::new(&self.user_stylesheet).parse(RuleKind::User);
+            stylesheet.append(&mut css);
+        }
+
         if !self.ignore_document_css {
             if let Some(head) = root.find("head") {
                 if let Some(children) = head.children() {
@@ -477,6 +522,8 @@ impl EpubDocument {
 
         style.font_size = props.get("font-size")
                                .and_then(|value| parse_font_size(value, parent_style.font_size, self.font_size))
+                               .map(|font_size| bound_font_size(font_size, self.font_size,
+                                                                self.min_font_size, self.max_font_size_ratio))
                                .unwrap_or(parent_style.font_size);
 
         style.line_height = props.get("line-height")
@@ -516,6 +563,8 @@ impl EpubDocument {
                                 .and_then(|value| parse_text_align(value))
                                 .unwrap_or(parent_style.text_align);
 
+        style.text_align = forced_text_align(style.text_align, self.text_align);
+
         style.font_features = props.get("font-feature-settings")
                                    .map(|value| parse_font_features(value))
                                    .or_else(|| parent_style.font_features.clone());
@@ -687,6 +736,8 @@ impl EpubDocument {
 
                 style.font_size = props.get("font-size")
                                        .and_then(|value| parse_font_size(value, parent_style.font_size, self.font_size))
+                                       .map(|font_size| bound_font_size(font_size, self.font_size,
+                                                                        self.min_font_size, self.max_font_size_ratio))
                                        .unwrap_or(parent_style.font_size);
 
                 style.width = props.get("width")
@@ -1146,7 +1197,7 @@ impl EpubDocument {
         let mut hyph_indices = Vec::new();
         let mut glue_drifts = Vec::new();
 
-        if bps.is_empty() && style.text_align == TextAlign::Justify {
+        if bps.is_empty() && style.text_align == TextAlign::Justify && self.hyphenation {
             // Hyphenate.
             if let Some(dictionary) = hyph_lang(style.language.as_ref()
                                                      .map_or(DEFAULT_HYPH_LANG, String::as_str))
@@ -1663,7 +1714,7 @@ impl Document for EpubDocument {
 
     fn resolve_location(&mut self, loc: Location) -> Option<f64> {
         if self.fonts.is_none() {
-            self.fonts = default_fonts().ok();
+            self.fonts = default_fonts(self.fallback_fonts.as_ref()).ok();
         }
 
         match loc {
@@ -1808,9 +1859,12 @@ impl Document for EpubDocument {
     }
 
     fn set_font_family(&mut self, family_name: &str, search_path: &str) {
-        if let Ok(serif_family) = FontFamily::from_name(family_name, search_path) {
+        if let Ok(mut serif_family) = FontFamily::from_name(family_name, search_path) {
             if self.fonts.is_none() {
-                self.fonts = default_fonts().ok();
+                self.fonts = default_fonts(self.fallback_fonts.as_ref()).ok();
+            }
+            if let Some(fallbacks) = self.fallback_fonts.as_ref() {
+                serif_family.set_fallbacks(fallbacks);
             }
             if let Some(fonts) = self.fonts.as_mut() {
                 fonts.serif = serif_family;
@@ -1831,6 +1885,58 @@ impl Document for EpubDocument {
         self.cache.clear();
     }
 
+    fn set_text_align(&mut self, text_align: Option<ForcedTextAlign>) {
+        self.text_align = text_align.map(|t| match t {
+            ForcedTextAlign::Justify => TextAlign::Justify,
+            ForcedTextAlign::Left => TextAlign::Left,
+        });
+        self.cache.clear();
+    }
+
+    fn set_hyphenation(&mut self, enable: bool) {
+        self.hyphenation = enable;
+        self.cache.clear();
+    }
+
+    fn set_ignore_document_css(&mut self, ignore: bool) {
+        if self.ignore_document_css != ignore {
+            self.ignore_document_css = ignore;
+            self.cache.clear();
+        }
+    }
+
+    fn set_min_font_size(&mut self, min_font_size: f32) {
+        self.min_font_size = min_font_size;
+        self.cache.clear();
+    }
+
+    fn set_max_font_size_ratio(&mut self, ratio: Option<f32>) {
+        // A ratio below one would make the lower bound exceed the upper bound.
+        self.max_font_size_ratio = ratio.map(|r| r.max(1.0));
+        self.cache.clear();
+    }
+
+    fn set_fallback_fonts(&mut self, paths: &[PathBuf]) {
+        if paths.is_empty() {
+            return;
+        }
+
+        if let Ok(fallbacks) = open_fallback_fonts(paths) {
+            if let Some(fonts) = self.fonts.as_mut() {
+                fonts.set_fallbacks(&fallbacks);
+            }
+            self.fallback_fonts = Some(fallbacks);
+            self.cache.clear();
+        }
+    }
+
+    fn set_user_stylesheet(&mut self, css: &str) {
+        if self.user_stylesheet != css {
+            self.user_stylesheet = css.to_string();
+            self.cache.clear();
+        }
+    }
+
     fn title(&self) -> Option<String> {
         self.metadata("dc:title")
     }
@@ -1856,7 +1962,7 @@ impl Document for EpubDocument {
     }
 }
 
-fn default_fonts() -> Result<Fonts, Error> {
+fn default_fonts(fallbacks: Option<&FallbackFonts>) -> Result<Fonts, Error> {
     let opener = FontOpener::new()?;
     let mut fonts = Fonts {
         serif: FontFamily {
@@ -1882,5 +1988,75 @@ fn default_fonts() -> Result<Fonts, Error> {
     };
     fonts.monospace.bold.set_variations(&["wght=600"]);
     fonts.monospace.bold_italic.set_variations(&["wght=600"]);
+    if let Some(fallbacks) = fallbacks {
+        fonts.set_fallbacks(fallbacks);
+    }
     Ok(fonts)
 }
+
+fn collect_paragraphs(node: &Node, start_offset: usize, paragraph: &mut (String, f64), paragraphs: &mut Vec<(String, f64)>) {
+    match *node {
+        Node::Text(TextData { offset, ref text }) |
+        Node::Whitespace(TextData { offset, ref text }) => {
+            if paragraph.0.trim().is_empty() {
+                if node.is_whitespace() {
+                    return;
+                }
+                paragraph.1 = location_from_offset(start_offset + offset);
+            }
+            paragraph.0.push_str(&decode_entities(text));
+        },
+        Node::Element(ElementData { ref name, ref children, .. }) => {
+            if name == "script" || name == "style" {
+                return;
+            }
+
+            let is_block = node.is_block();
+
+            if is_block {
+                flush_paragraph(paragraph, paragraphs);
+            }
+
+            for child in children {
+                collect_paragraphs(child, start_offset, paragraph, paragraphs);
+            }
+
+            if is_block {
+                flush_paragraph(paragraph, paragraphs);
+            } else if name == "br" {
+                paragraph.0.push(' ');
+            }
+        },
+    }
+}
+
+// The forced alignment only overrides the body text alignments:
+// centered and right aligned blocks are left untouched.
+fn forced_text_align(text_align: TextAlign, forced: Option<TextAlign>) -> TextAlign {
+    match forced {
+        Some(forced) if text_align == TextAlign::Left || text_align == TextAlign::Justify => forced,
+        _ => text_align,
+    }
+}
+
+fn flush_paragraph(paragraph: &mut (String, f64), paragraphs: &mut Vec<(String, f64)>) {
+    let text = paragraph.0.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if !text.is_empty() {
+        paragraphs.push((text, paragraph.1));
+    }
+    paragraph.0.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forced_text_align() {
+        assert_eq!(forced_text_align(TextAlign::Justify, None), TextAlign::Justify);
+        assert_eq!(forced_text_align(TextAlign::Justify, Some(TextAlign::Left)), TextAlign::Left);
+        assert_eq!(forced_text_align(TextAlign::Left, Some(TextAlign::Justify)), TextAlign::Justify);
+        assert_eq!(forced_text_align(TextAlign::Center, Some(TextAlign::Left)), TextAlign::Center);
+        assert_eq!(forced_text_align(TextAlign::Right, Some(TextAlign::Justify)), TextAlign::Right);
+    }
+}
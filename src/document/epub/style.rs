@@ -1,6 +1,6 @@
 use fnv::FnvHashMap;
 use super::dom::Node;
-use super::css::{CssParser, Rule, Selector, SimpleSelector, Specificity};
+use super::css::{CssParser, Rule, RuleKind, Declaration, Selector, SimpleSelector, Specificity};
 
 pub type PropertyMap = FnvHashMap<String, String>;
 pub type Stylesheet = [Rule];
@@ -8,7 +8,7 @@ type MatchedRule<'a> = (Specificity, &'a Rule);
 
 #[cfg(test)]
 mod tests {
-    use super::specified_values;
+    use super::{specified_values, bound_font_size};
     use super::super::css::{CssParser, RuleKind};
     use super::super::xml::XmlParser;
 
@@ -25,6 +25,39 @@ mod tests {
                                                     ("c".to_string(), "7".to_string())].iter().cloned().collect());
         assert_eq!(specified_values(&xml2, None, None, &css1), [("b".to_string(), "2".to_string())].iter().cloned().collect());
     }
+
+    #[test]
+    fn important_style() {
+        let xml = XmlParser::new("<p class='x' style='a: 3; b: 3 !important'/>").parse();
+        let (mut css1, _) = CssParser::new("p { a: 1 !important; b: 1 !important; c: 1 }").parse(RuleKind::User);
+        let (mut css2, _) = CssParser::new(".x { a: 2; b: 2 !important; c: 2 }").parse(RuleKind::Document);
+        css1.append(&mut css2);
+        assert_eq!(specified_values(&xml, None, None, &css1), [("a".to_string(), "1".to_string()),
+                                                   ("b".to_string(), "1".to_string()),
+                                                   ("c".to_string(), "2".to_string())].iter().cloned().collect());
+    }
+
+    #[test]
+    fn bounded_font_size() {
+        assert_eq!(bound_font_size(30.0, 12.0, 6.0, None), 30.0);
+        assert_eq!(bound_font_size(4.0, 12.0, 6.0, None), 6.0);
+        assert_eq!(bound_font_size(30.0, 12.0, 6.0, Some(1.5)), 18.0);
+        assert_eq!(bound_font_size(4.0, 12.0, 6.0, Some(1.5)), 8.0);
+        assert_eq!(bound_font_size(14.0, 12.0, 6.0, Some(1.5)), 14.0);
+        assert_eq!(bound_font_size(4.0, 6.0, 5.0, Some(2.0)), 5.0);
+    }
+}
+
+// Keeps the given font size above the minimum font size and, if requested,
+// within the given ratio of the body font size.
+pub fn bound_font_size(font_size: f32, body_font_size: f32, min_font_size: f32, max_ratio: Option<f32>) -> f32 {
+    let font_size = if let Some(ratio) = max_ratio {
+        font_size.max(body_font_size / ratio)
+                 .min(body_font_size * ratio)
+    } else {
+        font_size
+    };
+    font_size.max(min_font_size)
 }
 
 pub fn specified_values(node: &Node, parent: Option<&Node>, sibling: Option<&Node>, stylesheet: &Stylesheet) -> PropertyMap {
@@ -39,23 +72,38 @@ pub fn specified_values(node: &Node, parent: Option<&Node>, sibling: Option<&Nod
         }
     });
 
-    for (_, rule) in rules {
-        for declaration in &rule.declarations {
-            expand_and_insert(&declaration.name, &declaration.value, &mut props);
-        }
+    for &(_, rule) in &rules {
+        insert_declarations(&rule.declarations, false, &mut props);
     }
 
     let local_declarations = node.attr("style").map(|text| {
         CssParser::new(text).parse_declarations()
     }).unwrap_or_default();
 
-    for declaration in &local_declarations {
-        expand_and_insert(&declaration.name, &declaration.value, &mut props);
+    insert_declarations(&local_declarations, false, &mut props);
+
+    // The important declarations of the user and of the viewer override the document's.
+    for &(_, rule) in rules.iter().filter(|&&(_, rule)| rule.kind == RuleKind::Document) {
+        insert_declarations(&rule.declarations, true, &mut props);
+    }
+
+    insert_declarations(&local_declarations, true, &mut props);
+
+    for kind in &[RuleKind::User, RuleKind::Viewer] {
+        for &(_, rule) in rules.iter().filter(|&&(_, rule)| rule.kind == *kind) {
+            insert_declarations(&rule.declarations, true, &mut props);
+        }
     }
 
     props
 }
 
+fn insert_declarations(declarations: &[Declaration], important: bool, props: &mut PropertyMap) {
+    for declaration in declarations.iter().filter(|d| d.important == important) {
+        expand_and_insert(&declaration.name, &declaration.value, props);
+    }
+}
+
 fn matching_rules<'a>(node: &Node, parent: Option<&Node>, sibling: Option<&Node>, stylesheet: &'a Stylesheet) -> Vec<MatchedRule<'a>> {
     stylesheet.iter().filter_map(|rule| match_rule(node, parent, sibling, rule)).collect()
 }
@@ -0,0 +1,197 @@
+//! Synthesis of a table of contents from the headings of documents without an outline.
+//!
+//! The words of each page are grouped into lines. The short lines set in a noticeably
+//! larger type than the body text, or starting a page with words such as *Chapter*,
+//! are headings. The largest headings become the top-level entries, the other ones
+//! are nested within them.
+
+use fnv::FnvHashMap;
+use regex::Regex;
+use geom::Rectangle;
+use super::{BoundedText, TocEntry};
+
+// Lines at least this much taller than the body text are headings.
+const HEADING_RATIO: f32 = 1.3;
+// Headings whose heights differ by less than this ratio have the same level.
+const LEVEL_RATIO: f32 = 1.15;
+// Headings don't have more words than this.
+const MAX_HEADING_WORDS: usize = 12;
+// Lines that appear on at least this many pages are running heads.
+const MIN_RUNNING_HEAD_PAGES: usize = 3;
+// Lines starting with a keyword are only headings among the first lines of a page.
+const MAX_KEYWORD_RANK: usize = 2;
+
+#[derive(Debug, Clone)]
+pub struct TextLine {
+    pub text: String,
+    // The location of the page that holds the line.
+    pub location: f64,
+    // The index of the line within its page.
+    pub rank: usize,
+    pub words_count: usize,
+    height: i32,
+}
+
+// Groups the words of a page into lines.
+pub fn text_lines(words: &[BoundedText], location: f64) -> Vec<TextLine> {
+    let mut lines: Vec<(Vec<&BoundedText>, Rectangle)> = Vec::new();
+
+    for word in words.iter().filter(|w| !w.text.trim().is_empty() && w.rect.height() > 0) {
+        let center = (word.rect.min.y + word.rect.max.y) / 2;
+        match lines.last_mut() {
+            Some(&mut (ref mut line, ref mut rect)) if center >= rect.min.y && center < rect.max.y => {
+                line.push(word);
+                rect.absorb(&word.rect);
+            },
+            _ => lines.push((vec![word], word.rect)),
+        }
+    }
+
+    lines.into_iter().enumerate().map(|(rank, (line, _))| {
+        let mut heights: Vec<i32> = line.iter().map(|w| w.rect.height() as i32).collect();
+        heights.sort();
+        TextLine {
+            text: line.iter().map(|w| w.text.trim()).collect::<Vec<&str>>().join(" "),
+            location,
+            rank,
+            words_count: line.len(),
+            height: heights[heights.len() / 2],
+        }
+    }).collect()
+}
+
+// Returns the table of contents built from the headings found among the given lines,
+// which are expected to be in reading order.
+pub fn synthesize_toc(lines: &[TextLine]) -> Vec<TocEntry> {
+    let body_height = match body_height(lines) {
+        Some(height) => height,
+        None => return Vec::new(),
+    };
+
+    let keyword = Regex::new(r"(?i)^(chapter|part|book|section|appendix|prologue|epilogue|preface|introduction|conclusion)\b").unwrap();
+
+    // The pages on which each line appears.
+    let mut pages: FnvHashMap<String, Vec<f64>> = FnvHashMap::default();
+    for line in lines {
+        let entry = pages.entry(line.text.to_lowercase()).or_insert_with(Vec::new);
+        if entry.last() != Some(&line.location) {
+            entry.push(line.location);
+        }
+    }
+
+    let is_heading = |line: &TextLine| {
+        if line.words_count > MAX_HEADING_WORDS || !line.text.chars().any(char::is_alphabetic) ||
+           pages[&line.text.to_lowercase()].len() >= MIN_RUNNING_HEAD_PAGES {
+            return false;
+        }
+        let is_keyword = line.rank <= MAX_KEYWORD_RANK && keyword.is_match(&line.text);
+        let is_large = line.height as f32 >= HEADING_RATIO * body_height;
+        is_keyword || is_large
+    };
+
+    // Consecutive heading lines are merged into a single heading.
+    let mut headings: Vec<(String, f64, i32)> = Vec::new();
+    let mut previous: Option<&TextLine> = None;
+
+    for line in lines {
+        if !is_heading(line) {
+            previous = None;
+            continue;
+        }
+        let height = (line.height as f32).max(HEADING_RATIO * body_height) as i32;
+        match (previous, headings.last_mut()) {
+            (Some(p), Some(h)) if p.location == line.location && p.rank + 1 == line.rank => {
+                h.0.push(' ');
+                h.0.push_str(&line.text);
+                h.2 = h.2.max(height);
+            },
+            _ => headings.push((line.text.clone(), line.location, height)),
+        }
+        previous = Some(line);
+    }
+
+    // The headings of the largest size are the top-level entries.
+    let top_height = headings.iter().map(|h| h.2).max().unwrap_or(0);
+    let mut toc: Vec<TocEntry> = Vec::new();
+
+    for (title, location, height) in headings {
+        let entry = TocEntry { title, location, children: Vec::new() };
+        if height as f32 * LEVEL_RATIO >= top_height as f32 {
+            toc.push(entry);
+        } else if let Some(parent) = toc.last_mut() {
+            parent.children.push(entry);
+        } else {
+            toc.push(entry);
+        }
+    }
+
+    toc
+}
+
+// Returns the median height of the lines, weighted by their number of words.
+fn body_height(lines: &[TextLine]) -> Option<f32> {
+    let mut heights: Vec<(i32, usize)> = lines.iter().map(|l| (l.height, l.words_count)).collect();
+    heights.sort();
+    let total: usize = heights.iter().map(|h| h.1).sum();
+    let mut sum = 0;
+    for (height, count) in heights {
+        sum += count;
+        if 2 * sum >= total {
+            return Some(height as f32);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(location: f64, lines: &[(&str, i32)]) -> Vec<TextLine> {
+        let mut words = Vec::new();
+        let mut y = 0;
+        for &(text, height) in lines {
+            let mut x = 0;
+            for word in text.split_whitespace() {
+                words.push(BoundedText { text: word.to_string(),
+                                         rect: rect![x, y, x + 10, y + height] });
+                x += 12;
+            }
+            y += height + 4;
+        }
+        text_lines(&words, location)
+    }
+
+    #[test]
+    fn test_lines() {
+        let lines = page(0.0, &[("Lorem ipsum dolor", 10), ("sit amet", 10)]);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].text, "Lorem ipsum dolor");
+        assert_eq!(lines[1].rank, 1);
+    }
+
+    #[test]
+    fn test_headings() {
+        let body = "lorem ipsum dolor sit amet consectetur adipiscing elit";
+        let mut lines = Vec::new();
+        for i in 0..6 {
+            let mut content = vec![("Book Title", 10)];
+            match i {
+                1 => content.extend_from_slice(&[("Chapter 1", 20), ("The Beginning", 20)]),
+                2 => content.push(("A Section", 14)),
+                4 => content.push(("Chapter 2", 20)),
+                _ => (),
+            }
+            for _ in 0..10 {
+                content.push((body, 10));
+            }
+            lines.extend(page(i as f64, &content));
+        }
+        let toc = synthesize_toc(&lines);
+        assert_eq!(toc.len(), 2);
+        assert_eq!(toc[0].title, "Chapter 1 The Beginning");
+        assert_eq!(toc[0].children.len(), 1);
+        assert_eq!(toc[0].children[0].location, 2.0);
+        assert_eq!(toc[1].location, 4.0);
+    }
+}
@@ -1,12 +1,17 @@
 pub mod djvu;
 pub mod pdf;
 pub mod epub;
+pub mod reflow;
+pub mod columns;
+pub mod crop;
+pub mod page_labels;
+pub mod headings;
 
 mod djvulibre_sys;
 mod mupdf_sys;
 
 use std::ptr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use fnv::FnvHashSet;
 use isbn::Isbn;
@@ -16,6 +21,7 @@ use geom::{Rectangle, CycleDir};
 use document::djvu::DjvuOpener;
 use document::pdf::PdfOpener;
 use document::epub::EpubDocument;
+use document::page_labels::PageLabels;
 use settings::EpubEngine;
 use framebuffer::Pixmap;
 
@@ -33,13 +39,78 @@ pub struct BoundedText {
     pub rect: Rectangle,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TocEntry {
     pub title: String,
     pub location: f64,
     pub children: Vec<TocEntry>,
 }
 
+// The layers of the pages that are rendered, for documents made of several layers.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum RenderLayers {
+    Color,
+    Mask,
+    Foreground,
+    Background,
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        RenderLayers::Color
+    }
+}
+
+// The alignment forced on the body text of reflowable documents.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextAlign {
+    Justify,
+    Left,
+}
+
+// Stylesheet snippets that can be toggled for each book.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StyleTweak {
+    RemoveParagraphIndents,
+    SpaceParagraphs,
+    ForceSerif,
+    ForceSansSerif,
+    BlackText,
+}
+
+pub const STYLE_TWEAKS: [StyleTweak; 5] = [StyleTweak::RemoveParagraphIndents,
+                                           StyleTweak::SpaceParagraphs,
+                                           StyleTweak::ForceSerif,
+                                           StyleTweak::ForceSansSerif,
+                                           StyleTweak::BlackText];
+
+const TEXT_ELEMENTS: &str = "body, div, p, span, a, em, i, strong, b, small, li, dt, dd, blockquote, \
+                             h1, h2, h3, h4, h5, h6, td, th";
+
+impl StyleTweak {
+    pub fn label(&self) -> &str {
+        match *self {
+            StyleTweak::RemoveParagraphIndents => "Remove Paragraph Indents",
+            StyleTweak::SpaceParagraphs => "Space Paragraphs",
+            StyleTweak::ForceSerif => "Force Serif",
+            StyleTweak::ForceSansSerif => "Force Sans Serif",
+            StyleTweak::BlackText => "Black Text",
+        }
+    }
+
+    pub fn css(&self) -> String {
+        match *self {
+            StyleTweak::RemoveParagraphIndents => "p { text-indent: 0 !important }".to_string(),
+            StyleTweak::SpaceParagraphs => "p { margin-top: 0.5em !important; margin-bottom: 0.5em !important }".to_string(),
+            StyleTweak::ForceSerif => format!("{} {{ font-family: serif !important }}", TEXT_ELEMENTS),
+            StyleTweak::ForceSansSerif => format!("{} {{ font-family: sans-serif !important }}", TEXT_ELEMENTS),
+            StyleTweak::BlackText => format!("{} {{ color: black !important }}", TEXT_ELEMENTS),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Neighbors {
     pub previous_page: Option<f64>,
@@ -149,8 +220,52 @@ pub trait Document: Send+Sync {
         false
     }
 
-    fn has_toc(&mut self) -> bool {
-        self.toc().map_or(false, |entries| !entries.is_empty())
+    // Returns the bounding box of the marks made on the given page.
+    fn boundary_box(&mut self, _index: usize) -> Option<Rectangle> {
+        None
+    }
+
+    fn page_labels(&mut self) -> Option<PageLabels> {
+        None
+    }
+
+    fn is_protected(&self) -> bool {
+        false
+    }
+
+    // Returns whether the given password unlocks the document.
+    fn authenticate(&mut self, _password: &str) -> bool {
+        true
+    }
+
+    fn has_layers(&self) -> bool {
+        false
+    }
+
+    fn set_render_layers(&mut self, _layers: RenderLayers) {
+    }
+
+    fn set_text_align(&mut self, _text_align: Option<TextAlign>) {
+    }
+
+    fn set_hyphenation(&mut self, _enable: bool) {
+    }
+
+    fn set_ignore_document_css(&mut self, _ignore: bool) {
+    }
+
+    fn set_min_font_size(&mut self, _min_font_size: f32) {
+    }
+
+    fn set_max_font_size_ratio(&mut self, _ratio: Option<f32>) {
+    }
+
+    // Sets the fonts that provide the glyphs missing from the fonts of the document.
+    fn set_fallback_fonts(&mut self, _paths: &[PathBuf]) {
+    }
+
+    // Sets the stylesheet applied with the priority of the user's stylesheet.
+    fn set_user_stylesheet(&mut self, _css: &str) {
     }
 
     fn isbn(&mut self) -> Option<String> {
@@ -33,6 +33,8 @@ pub enum FzCookie {}
 pub enum FzStoreDropFn {}
 pub enum FzSeparations {}
 pub enum FzImage {}
+pub enum PdfDoc {}
+pub enum PdfObj {}
 
 #[link(name="mupdf")]
 #[link(name="mupdfwrapper")]
@@ -50,6 +52,7 @@ extern {
     pub fn mp_count_pages(ctx: *mut FzContext, doc: *mut FzDocument) -> libc::c_int;
     pub fn fz_lookup_metadata(ctx: *mut FzContext, doc: *mut FzDocument, key: *const libc::c_char, buf: *mut libc::c_char, size: libc::c_int) -> libc::c_int;
     pub fn fz_needs_password(ctx: *mut FzContext, doc: *mut FzDocument) -> libc::c_int;
+    pub fn fz_authenticate_password(ctx: *mut FzContext, doc: *mut FzDocument, password: *const libc::c_char) -> libc::c_int;
     pub fn fz_is_document_reflowable(ctx: *mut FzContext, doc: *mut FzDocument) -> libc::c_int;
     pub fn fz_layout_document(ctx: *mut FzContext, doc: *mut FzDocument, w: libc::c_float, h: libc::c_float, em: libc::c_float);
     pub fn mp_load_outline(ctx: *mut FzContext, doc: *mut FzDocument) -> *mut FzOutline;
@@ -73,6 +76,17 @@ extern {
     pub fn fz_drop_device(ctx: *mut FzContext, dev: *mut FzDevice);
     pub fn fz_new_pixmap(ctx: *mut FzContext, cs: *mut FzColorspace, width: libc::c_int, height: libc::c_int, alpha: libc::c_int) -> *mut FzPixmap;
     pub fn fz_union_rect(a: *mut FzRect, b: *const FzRect);
+    pub fn pdf_specifics(ctx: *mut FzContext, doc: *mut FzDocument) -> *mut PdfDoc;
+    pub fn pdf_trailer(ctx: *mut FzContext, doc: *mut PdfDoc) -> *mut PdfObj;
+    pub fn pdf_dict_gets(ctx: *mut FzContext, dict: *mut PdfObj, key: *const libc::c_char) -> *mut PdfObj;
+    pub fn pdf_array_len(ctx: *mut FzContext, array: *mut PdfObj) -> libc::c_int;
+    pub fn pdf_array_get(ctx: *mut FzContext, array: *mut PdfObj, index: libc::c_int) -> *mut PdfObj;
+    pub fn pdf_is_int(ctx: *mut FzContext, obj: *mut PdfObj) -> libc::c_int;
+    pub fn pdf_is_string(ctx: *mut FzContext, obj: *mut PdfObj) -> libc::c_int;
+    pub fn pdf_to_int(ctx: *mut FzContext, obj: *mut PdfObj) -> libc::c_int;
+    pub fn pdf_to_name(ctx: *mut FzContext, obj: *mut PdfObj) -> *const libc::c_char;
+    pub fn pdf_to_str_buf(ctx: *mut FzContext, obj: *mut PdfObj) -> *mut libc::c_char;
+    pub fn pdf_to_str_len(ctx: *mut FzContext, obj: *mut PdfObj) -> libc::c_int;
     pub fn fz_runetochar(buf: *mut u8, rune: libc::c_int) -> libc::c_int;
     pub static fz_identity: FzMatrix;
 }
@@ -0,0 +1,137 @@
+//! Page labels, as defined by the *PageLabels* number tree of PDF documents.
+//!
+//! The pages are split into ranges. Each range defines a prefix, a numbering style and
+//! the number of its first page, from which the labels of its pages are computed.
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LabelStyle {
+    Decimal,
+    UpperRoman,
+    LowerRoman,
+    UpperAlpha,
+    LowerAlpha,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LabelRange {
+    // Index of the first page of the range.
+    pub start: usize,
+    pub style: Option<LabelStyle>,
+    pub prefix: String,
+    // Number of the first page of the range.
+    pub first: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct PageLabels {
+    ranges: Vec<LabelRange>,
+    pages_count: usize,
+}
+
+impl PageLabels {
+    pub fn new(mut ranges: Vec<LabelRange>, pages_count: usize) -> Option<PageLabels> {
+        ranges.sort_by_key(|r| r.start);
+        ranges.dedup_by_key(|r| r.start);
+        if ranges.is_empty() {
+            None
+        } else {
+            Some(PageLabels { ranges, pages_count })
+        }
+    }
+
+    // Returns the label of the page at the given index.
+    pub fn label(&self, index: usize) -> Option<String> {
+        if index >= self.pages_count {
+            return None;
+        }
+
+        let range = self.ranges.iter().rev().find(|r| r.start <= index)?;
+        let number = range.first + index - range.start;
+        let mut label = range.prefix.clone();
+
+        match range.style {
+            Some(LabelStyle::Decimal) => label.push_str(&number.to_string()),
+            Some(LabelStyle::UpperRoman) => label.push_str(&roman(number)),
+            Some(LabelStyle::LowerRoman) => label.push_str(&roman(number).to_lowercase()),
+            Some(LabelStyle::UpperAlpha) => label.push_str(&alpha(number)),
+            Some(LabelStyle::LowerAlpha) => label.push_str(&alpha(number).to_lowercase()),
+            None => (),
+        }
+
+        if label.is_empty() {
+            None
+        } else {
+            Some(label)
+        }
+    }
+
+    // Returns the index of the page with the given label. The exact matches are preferred
+    // to the case insensitive ones.
+    pub fn index(&self, label: &str) -> Option<usize> {
+        let label = label.trim();
+        let labels: Vec<Option<String>> = (0..self.pages_count).map(|i| self.label(i)).collect();
+        labels.iter().position(|l| l.as_ref().map(String::as_str) == Some(label))
+              .or_else(|| {
+                  let label = label.to_lowercase();
+                  labels.iter().position(|l| l.as_ref().map(|l| l.to_lowercase()) == Some(label.clone()))
+              })
+    }
+}
+
+fn roman(mut n: usize) -> String {
+    const NUMERALS: [(usize, &str); 13] = [(1000, "M"), (900, "CM"), (500, "D"), (400, "CD"),
+                                           (100, "C"), (90, "XC"), (50, "L"), (40, "XL"),
+                                           (10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I")];
+    let mut s = String::new();
+    for &(value, numeral) in NUMERALS.iter() {
+        while n >= value {
+            s.push_str(numeral);
+            n -= value;
+        }
+    }
+    s
+}
+
+// A to Z, then AA to ZZ, AAA to ZZZ, etc.
+fn alpha(n: usize) -> String {
+    if n == 0 {
+        return String::new();
+    }
+    let letter = (b'A' + ((n - 1) % 26) as u8) as char;
+    (0..=(n - 1) / 26).map(|_| letter).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels() -> PageLabels {
+        PageLabels::new(vec![LabelRange { start: 0, style: Some(LabelStyle::LowerRoman),
+                                          prefix: String::new(), first: 1 },
+                             LabelRange { start: 20, style: Some(LabelStyle::Decimal),
+                                          prefix: String::new(), first: 1 },
+                             LabelRange { start: 300, style: Some(LabelStyle::Decimal),
+                                          prefix: "A-".to_string(), first: 1 }],
+                        320).unwrap()
+    }
+
+    #[test]
+    fn test_label() {
+        let labels = labels();
+        assert_eq!(labels.label(13), Some("xiv".to_string()));
+        assert_eq!(labels.label(20), Some("1".to_string()));
+        assert_eq!(labels.label(302), Some("A-3".to_string()));
+        assert_eq!(labels.label(320), None);
+        assert_eq!(alpha(28), "BB");
+    }
+
+    #[test]
+    fn test_index() {
+        let labels = labels();
+        assert_eq!(labels.index("xiv"), Some(13));
+        assert_eq!(labels.index("XIV"), Some(13));
+        assert_eq!(labels.index("A-3"), Some(302));
+        assert_eq!(labels.index("7"), Some(26));
+        assert_eq!(labels.index("B-1"), None);
+    }
+}
@@ -13,10 +13,14 @@ use std::ffi::{CString, CStr};
 use std::os::unix::ffi::OsStrExt;
 use failure::Error;
 use super::{Document, Location, BoundedText, TocEntry};
+use super::page_labels::{PageLabels, LabelRange, LabelStyle};
 use unit::pt_to_px;
 use framebuffer::Pixmap;
 use geom::Rectangle;
 
+// Guards against cycles in malformed number trees.
+const MAX_NUMBER_TREE_DEPTH: usize = 32;
+
 impl Into<FzRect> for Rectangle {
     fn into(self) -> FzRect {
         FzRect {
@@ -162,8 +166,75 @@ impl PdfDocument {
         }
     }
 
-    pub fn is_protected(&self) -> bool {
-        unsafe { fz_needs_password(self.ctx.0, self.doc) == 1 }
+    // Collects the ranges of the given node of the page labels number tree.
+    fn walk_page_labels(&self, node: *mut PdfObj, ranges: &mut Vec<LabelRange>, depth: usize) {
+        if node.is_null() || depth > MAX_NUMBER_TREE_DEPTH {
+            return;
+        }
+
+        unsafe {
+            let ctx = self.ctx.0;
+            let kids = pdf_dict_gets(ctx, node, b"Kids\0".as_ptr() as *const libc::c_char);
+            for i in 0..pdf_array_len(ctx, kids) {
+                self.walk_page_labels(pdf_array_get(ctx, kids, i), ranges, depth + 1);
+            }
+
+            let nums = pdf_dict_gets(ctx, node, b"Nums\0".as_ptr() as *const libc::c_char);
+            for i in 0..pdf_array_len(ctx, nums) / 2 {
+                let key = pdf_array_get(ctx, nums, 2 * i);
+                let value = pdf_array_get(ctx, nums, 2 * i + 1);
+                if pdf_is_int(ctx, key) == 0 || pdf_to_int(ctx, key) < 0 {
+                    continue;
+                }
+
+                let style_name = pdf_dict_gets(ctx, value, b"S\0".as_ptr() as *const libc::c_char);
+                let style = match CStr::from_ptr(pdf_to_name(ctx, style_name)).to_bytes() {
+                    b"D" => Some(LabelStyle::Decimal),
+                    b"R" => Some(LabelStyle::UpperRoman),
+                    b"r" => Some(LabelStyle::LowerRoman),
+                    b"A" => Some(LabelStyle::UpperAlpha),
+                    b"a" => Some(LabelStyle::LowerAlpha),
+                    _ => None,
+                };
+
+                let prefix_obj = pdf_dict_gets(ctx, value, b"P\0".as_ptr() as *const libc::c_char);
+                let prefix = if pdf_is_string(ctx, prefix_obj) != 0 {
+                    let len = pdf_to_str_len(ctx, prefix_obj).max(0) as usize;
+                    let buf = slice::from_raw_parts(pdf_to_str_buf(ctx, prefix_obj) as *const u8, len);
+                    decode_text_string(buf)
+                } else {
+                    String::new()
+                };
+
+                let first_obj = pdf_dict_gets(ctx, value, b"St\0".as_ptr() as *const libc::c_char);
+                let first = if pdf_is_int(ctx, first_obj) != 0 {
+                    pdf_to_int(ctx, first_obj).max(1) as usize
+                } else {
+                    1
+                };
+
+                ranges.push(LabelRange {
+                    start: pdf_to_int(ctx, key) as usize,
+                    style,
+                    prefix,
+                    first,
+                });
+            }
+        }
+    }
+}
+
+// Text strings are either encoded in UTF-16BE, with a byte order mark, or in
+// PDFDocEncoding, which is approximated by Latin-1.
+fn decode_text_string(buf: &[u8]) -> String {
+    if buf.starts_with(&[0xfe, 0xff]) {
+        let units: Vec<u16> = buf[2..].chunks(2)
+                                      .filter(|c| c.len() == 2)
+                                      .map(|c| (c[0] as u16) << 8 | c[1] as u16)
+                                      .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        buf.iter().map(|&b| b as char).collect()
     }
 }
 
@@ -176,6 +247,40 @@ impl Document for PdfDocument {
         unsafe { mp_count_pages(self.ctx.0, self.doc) as f64 }
     }
 
+    fn boundary_box(&mut self, index: usize) -> Option<Rectangle> {
+        self.page(index).and_then(|page| page.boundary_box())
+    }
+
+    fn page_labels(&mut self) -> Option<PageLabels> {
+        unsafe {
+            let ctx = self.ctx.0;
+            let pdf = pdf_specifics(ctx, self.doc);
+            if pdf.is_null() {
+                return None;
+            }
+            let root = pdf_dict_gets(ctx, pdf_trailer(ctx, pdf), b"Root\0".as_ptr() as *const libc::c_char);
+            let tree = pdf_dict_gets(ctx, root, b"PageLabels\0".as_ptr() as *const libc::c_char);
+            if tree.is_null() {
+                return None;
+            }
+            let mut ranges = Vec::new();
+            self.walk_page_labels(tree, &mut ranges, 0);
+            PageLabels::new(ranges, mp_count_pages(ctx, self.doc).max(0) as usize)
+        }
+    }
+
+    fn is_protected(&self) -> bool {
+        unsafe { fz_needs_password(self.ctx.0, self.doc) == 1 }
+    }
+
+    fn authenticate(&mut self, password: &str) -> bool {
+        let c_password = match CString::new(password) {
+            Ok(s) => s,
+            Err(_) => return false,
+        };
+        unsafe { fz_authenticate_password(self.ctx.0, self.doc, c_password.as_ptr()) != 0 }
+    }
+
     fn pixmap(&mut self, loc: Location, scale: f32) -> Option<(Pixmap, f64)> {
         let index = self.resolve_location(loc)? as usize;
         self.page(index).and_then(|page| page.pixmap(scale)).map(|pixmap| (pixmap, index as f64))
@@ -0,0 +1,622 @@
+//! Reflow of fixed-layout documents.
+//!
+//! Each page of the wrapped document is rendered and segmented into lines and words.
+//! The images of the words are then laid out anew into screens that fit the display.
+//! The screens are virtual pages: the location of the screen `k` of the page `p` is
+//! `p + k / n`, where `n` is the number of screens of `p`.
+
+use fnv::FnvHashMap;
+use device::CURRENT_DEVICE;
+use framebuffer::{Framebuffer, Pixmap};
+use geom::Rectangle;
+use unit::mm_to_px;
+use super::{Document, Location, BoundedText, TocEntry, RenderLayers};
+use super::page_labels::PageLabels;
+
+pub const DEFAULT_REFLOW_ZOOM: f32 = 1.5;
+// Pixels darker than this are considered ink.
+const INK_THRESHOLD: u8 = 160;
+// Components that aren't larger than this, in both directions, are considered noise.
+const SPECK_SIZE: u32 = 1;
+// Horizontal gap between two components, relative to the height of their line,
+// above which they belong to different words.
+const WORD_GAP_RATIO: f32 = 0.2;
+const MARGIN_WIDTH_MM: f32 = 3.0;
+// Number of reflowed pages kept in memory.
+const CACHE_SIZE: usize = 3;
+const SCREEN_EPSILON: f64 = 1e-3;
+
+#[derive(Debug, Clone)]
+struct Line {
+    rect: Rectangle,
+    baseline: i32,
+    words: Vec<Rectangle>,
+}
+
+#[derive(Debug, Clone)]
+struct Word {
+    rect: Rectangle,
+    baseline: i32,
+}
+
+#[derive(Debug, Clone)]
+struct Paragraph {
+    indent: bool,
+    words: Vec<Word>,
+}
+
+// Where a word of the page is drawn on a screen.
+#[derive(Debug, Clone)]
+struct Placement {
+    source: Rectangle,
+    target: Rectangle,
+}
+
+struct ReflowedPage {
+    index: usize,
+    scale: f32,
+    pixmap: Pixmap,
+    screens: Vec<Vec<Placement>>,
+}
+
+pub struct ReflowDocument {
+    doc: Box<Document>,
+    zoom: f32,
+    width: u32,
+    height: u32,
+    dpi: u16,
+    cache: Vec<ReflowedPage>,
+    // Number of screens of each page reflowed so far.
+    counts: FnvHashMap<usize, usize>,
+}
+
+impl ReflowDocument {
+    pub fn new(doc: Box<Document>, zoom: f32) -> ReflowDocument {
+        let (width, height) = CURRENT_DEVICE.dims;
+        ReflowDocument {
+            doc,
+            zoom,
+            width,
+            height,
+            dpi: CURRENT_DEVICE.dpi,
+            cache: Vec::new(),
+            counts: FnvHashMap::default(),
+        }
+    }
+
+    fn reflow(&mut self, index: usize) -> Option<ReflowedPage> {
+        let (page_width, _) = self.doc.dims(index)?;
+        let scale = self.zoom * self.width as f32 / page_width;
+        let (pixmap, _) = self.doc.pixmap(Location::Exact(index as f64), scale)?;
+        let bounds = pixmap.rect();
+        let words: Vec<Rectangle> = self.doc.words(Location::Exact(index as f64))
+                                        .map(|(words, _)| words.iter()
+                                                               .filter_map(|w| scale_rect(&w.rect, scale).intersection(&bounds))
+                                                               .collect())
+                                        .unwrap_or_default();
+        let lines = segment(&pixmap, &words);
+        let margin = mm_to_px(MARGIN_WIDTH_MM, self.dpi) as i32;
+        let screens = flow(&paragraphs(&lines), median_height(&lines), self.width, self.height, margin);
+        Some(ReflowedPage { index, scale, pixmap, screens })
+    }
+
+    fn page(&mut self, index: usize) -> Option<&ReflowedPage> {
+        if let Some(position) = self.cache.iter().position(|p| p.index == index) {
+            let page = self.cache.remove(position);
+            self.cache.push(page);
+        } else {
+            let page = self.reflow(index)?;
+            self.counts.insert(index, page.screens.len());
+            if self.cache.len() >= CACHE_SIZE {
+                self.cache.remove(0);
+            }
+            self.cache.push(page);
+        }
+        self.cache.last()
+    }
+
+    fn screens_count(&mut self, index: usize) -> usize {
+        if let Some(&count) = self.counts.get(&index) {
+            return count;
+        }
+        self.page(index).map_or(1, |p| p.screens.len())
+    }
+
+    // Returns the index of the page, the index of the screen and the number of screens.
+    fn position(&mut self, location: f64) -> Option<(usize, usize, usize)> {
+        let pages_count = self.doc.pages_count() as usize;
+        if pages_count == 0 {
+            return None;
+        }
+        let index = (location.max(0.0) as usize).min(pages_count - 1);
+        let count = self.screens_count(index);
+        let screen = ((location - index as f64) * count as f64 + SCREEN_EPSILON).max(0.0) as usize;
+        Some((index, screen.min(count - 1), count))
+    }
+
+    // Maps the rectangles of the given page, in page units, to the given screen.
+    fn map_texts(&mut self, texts: Vec<BoundedText>, index: usize, screen: usize) -> Option<Vec<BoundedText>> {
+        let page = self.page(index)?;
+        let placements = &page.screens[screen];
+        Some(texts.into_iter().filter_map(|t| {
+            map_rect(&scale_rect(&t.rect, page.scale), placements)
+                    .map(|rect| BoundedText { text: t.text, rect })
+        }).collect())
+    }
+}
+
+fn screen_location(index: usize, screen: usize, count: usize) -> f64 {
+    index as f64 + screen as f64 / count as f64
+}
+
+fn scale_rect(rect: &Rectangle, scale: f32) -> Rectangle {
+    rect![(rect.min.x as f32 * scale).floor() as i32,
+          (rect.min.y as f32 * scale).floor() as i32,
+          (rect.max.x as f32 * scale).ceil() as i32,
+          (rect.max.y as f32 * scale).ceil() as i32]
+}
+
+fn map_rect(rect: &Rectangle, placements: &[Placement]) -> Option<Rectangle> {
+    let center = rect.center();
+    placements.iter().find(|p| p.source.includes(center)).map(|p| {
+        let sx = p.target.width() as f32 / p.source.width() as f32;
+        let sy = p.target.height() as f32 / p.source.height() as f32;
+        rect![p.target.min.x + ((rect.min.x - p.source.min.x).max(0) as f32 * sx) as i32,
+              p.target.min.y + ((rect.min.y - p.source.min.y).max(0) as f32 * sy) as i32,
+              p.target.max.x - ((p.source.max.x - rect.max.x).max(0) as f32 * sx) as i32,
+              p.target.max.y - ((p.source.max.y - rect.max.y).max(0) as f32 * sy) as i32]
+    })
+}
+
+fn connected_components(pixmap: &Pixmap) -> Vec<Rectangle> {
+    let width = pixmap.width as i32;
+    let height = pixmap.height as i32;
+    let mut visited = vec![false; pixmap.data.len()];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            let addr = (y * width + x) as usize;
+            if visited[addr] || pixmap.data[addr] >= INK_THRESHOLD {
+                continue;
+            }
+
+            visited[addr] = true;
+            stack.push(pt!(x, y));
+            let mut rect = Rectangle::from_point(pt!(x, y));
+
+            while let Some(pt) = stack.pop() {
+                rect.merge(pt);
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let (nx, ny) = (pt.x + dx, pt.y + dy);
+                        if nx < 0 || ny < 0 || nx >= width || ny >= height {
+                            continue;
+                        }
+                        let addr = (ny * width + nx) as usize;
+                        if !visited[addr] && pixmap.data[addr] < INK_THRESHOLD {
+                            visited[addr] = true;
+                            stack.push(pt!(nx, ny));
+                        }
+                    }
+                }
+            }
+
+            // The components touching the edges are usually scanning artifacts.
+            let touches_edges = rect.min.x == 0 || rect.min.y == 0 ||
+                                rect.max.x == width || rect.max.y == height;
+
+            if !touches_edges && (rect.width() > SPECK_SIZE || rect.height() > SPECK_SIZE) {
+                components.push(rect);
+            }
+        }
+    }
+
+    components
+}
+
+// Estimates the baseline of a line: the lowest row that is dense with ink.
+fn baseline(pixmap: &Pixmap, rect: &Rectangle) -> i32 {
+    let width = pixmap.width as i32;
+    let counts: Vec<usize> = (rect.min.y..rect.max.y).map(|y| {
+        (rect.min.x..rect.max.x).filter(|&x| pixmap.data[(y * width + x) as usize] < INK_THRESHOLD)
+                                .count()
+    }).collect();
+    let max_count = counts.iter().cloned().max().unwrap_or(0);
+    let index = counts.iter().rposition(|&c| 3 * c >= max_count).unwrap_or(counts.len());
+    rect.min.y + (index as i32 + 1).min(rect.height() as i32)
+}
+
+// Splits the page into lines of words. The words boxes given by the document are used
+// when available, the components that aren't covered by them are kept as is.
+fn segment(pixmap: &Pixmap, words: &[Rectangle]) -> Vec<Line> {
+    let gap_ratio = if words.is_empty() { WORD_GAP_RATIO } else { 0.0 };
+    let mut boxes = words.to_vec();
+    boxes.extend(connected_components(pixmap).into_iter()
+                                             .filter(|c| !words.iter().any(|w| w.overlaps(c))));
+
+    let mut covered = vec![false; pixmap.height as usize];
+    for b in &boxes {
+        for y in b.min.y..b.max.y {
+            covered[y as usize] = true;
+        }
+    }
+
+    let mut bands = Vec::new();
+    let mut y = 0;
+    while y < covered.len() {
+        if !covered[y] {
+            y += 1;
+            continue;
+        }
+        let start = y;
+        while y < covered.len() && covered[y] {
+            y += 1;
+        }
+        bands.push((start as i32, y as i32));
+    }
+
+    let mut members = vec![Vec::new(); bands.len()];
+    for b in boxes {
+        if let Some(index) = bands.iter().position(|&(start, end)| b.min.y >= start && b.min.y < end) {
+            members[index].push(b);
+        }
+    }
+
+    bands.into_iter().zip(members.into_iter()).filter_map(|((start, end), mut boxes)| {
+        boxes.sort_by_key(|b| b.min.x);
+        let threshold = (gap_ratio * (end - start) as f32) as i32;
+        let mut words: Vec<Rectangle> = Vec::new();
+
+        for b in boxes {
+            if let Some(last) = words.last_mut() {
+                if b.min.x - last.max.x <= threshold {
+                    last.absorb(&b);
+                    continue;
+                }
+            }
+            words.push(b);
+        }
+
+        let mut rect = *words.first()?;
+        for w in &mut words {
+            w.min.y = start;
+            w.max.y = end;
+            rect.absorb(w);
+        }
+
+        let baseline = baseline(pixmap, &rect);
+        Some(Line { rect, baseline, words })
+    }).collect()
+}
+
+fn median_height(lines: &[Line]) -> i32 {
+    let mut heights: Vec<i32> = lines.iter().map(|l| l.rect.height() as i32).collect();
+    heights.sort();
+    heights.get(heights.len() / 2).cloned().unwrap_or(0)
+}
+
+// Groups the lines into paragraphs: a paragraph starts after a short line, an indented
+// line, a large vertical gap or a tall block.
+fn paragraphs(lines: &[Line]) -> Vec<Paragraph> {
+    if lines.is_empty() {
+        return Vec::new();
+    }
+
+    let left = lines.iter().map(|l| l.rect.min.x).min().unwrap();
+    let right = lines.iter().map(|l| l.rect.max.x).max().unwrap();
+    let em = median_height(lines);
+    let mut pitches: Vec<i32> = lines.windows(2).map(|w| w[1].rect.min.y - w[0].rect.min.y).collect();
+    pitches.sort();
+    let pitch = pitches.get(pitches.len() / 2).cloned().unwrap_or(em);
+
+    let mut paragraphs: Vec<Paragraph> = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let indent = line.rect.min.x - left > em / 2;
+        let is_start = i == 0 || indent || {
+            let prev = &lines[i - 1];
+            prev.rect.max.x < right - 2 * em ||
+            2 * (line.rect.min.y - prev.rect.min.y) > 3 * pitch ||
+            line.rect.height() as i32 > 2 * em ||
+            prev.rect.height() as i32 > 2 * em
+        };
+        if is_start {
+            paragraphs.push(Paragraph { indent, words: Vec::new() });
+        }
+        if let Some(paragraph) = paragraphs.last_mut() {
+            paragraph.words.extend(line.words.iter().map(|&rect| Word { rect, baseline: line.baseline }));
+        }
+    }
+
+    paragraphs
+}
+
+// Lays out the words of the paragraphs into screens of the given dimensions.
+// The words that don't fit within the margins are scaled down.
+fn flow(paragraphs: &[Paragraph], em: i32, width: u32, height: u32, margin: i32) -> Vec<Vec<Placement>> {
+    let frame = rect![margin, margin, width as i32 - margin, height as i32 - margin];
+    let max_width = frame.width() as f32;
+    let max_height = frame.height() as f32;
+    let space = em / 3;
+    let leading = em / 4;
+    let mut screens = vec![Vec::new()];
+    let mut y = frame.min.y;
+
+    for paragraph in paragraphs {
+        let words = &paragraph.words;
+        let mut start = 0;
+
+        while start < words.len() {
+            let indent = if start == 0 && paragraph.indent { em } else { 0 };
+            let mut line_width = indent;
+            let mut items = Vec::new();
+            let mut end = start;
+
+            while end < words.len() {
+                let word = &words[end];
+                let scale = (max_width / word.rect.width() as f32)
+                                .min(max_height / word.rect.height() as f32)
+                                .min(1.0);
+                let word_width = (word.rect.width() as f32 * scale).round().max(1.0) as i32;
+                let advance = if items.is_empty() { word_width } else { space + word_width };
+                if !items.is_empty() && line_width + advance > frame.width() as i32 {
+                    break;
+                }
+                line_width += advance;
+                items.push((word, scale, word_width));
+                end += 1;
+            }
+
+            let ascent = items.iter().map(|&(w, s, _)| ((w.baseline - w.rect.min.y) as f32 * s).ceil() as i32)
+                              .max().unwrap_or(0);
+            let descent = items.iter().map(|&(w, s, _)| ((w.rect.max.y - w.baseline) as f32 * s).ceil() as i32)
+                               .max().unwrap_or(0);
+
+            if y + ascent + descent > frame.max.y && !screens.last().map_or(true, Vec::is_empty) {
+                screens.push(Vec::new());
+                y = frame.min.y;
+            }
+
+            // Justify every line but the last one of the paragraph.
+            let extra = if end < words.len() && items.len() > 1 {
+                let extra = (frame.width() as i32 - line_width) / (items.len() - 1) as i32;
+                if extra <= em { extra } else { 0 }
+            } else {
+                0
+            };
+
+            let baseline = y + ascent;
+            let mut x = frame.min.x + indent;
+
+            for (word, scale, word_width) in items {
+                let top = baseline - ((word.baseline - word.rect.min.y) as f32 * scale).round() as i32;
+                let word_height = (word.rect.height() as f32 * scale).round().max(1.0) as i32;
+                if let Some(screen) = screens.last_mut() {
+                    screen.push(Placement {
+                        source: word.rect,
+                        target: rect![x, top, x + word_width, top + word_height],
+                    });
+                }
+                x += word_width + space + extra;
+            }
+
+            y = baseline + descent + leading;
+            start = end;
+        }
+
+        y += em / 2;
+    }
+
+    screens
+}
+
+fn compose(source: &Pixmap, placements: &[Placement], width: u32, height: u32) -> Pixmap {
+    let mut pixmap = Pixmap::new(width, height);
+
+    for p in placements {
+        let sx = p.source.width() as f32 / p.target.width() as f32;
+        let sy = p.source.height() as f32 / p.target.height() as f32;
+        for y in p.target.min.y.max(0)..p.target.max.y.min(height as i32) {
+            let src_y = (p.source.min.y + ((y - p.target.min.y) as f32 * sy) as i32).min(p.source.max.y - 1);
+            for x in p.target.min.x.max(0)..p.target.max.x.min(width as i32) {
+                let src_x = (p.source.min.x + ((x - p.target.min.x) as f32 * sx) as i32).min(p.source.max.x - 1);
+                let color = source.data[(src_y * source.width as i32 + src_x) as usize];
+                pixmap.data[(y * width as i32 + x) as usize] = color;
+            }
+        }
+    }
+
+    pixmap
+}
+
+impl Document for ReflowDocument {
+    fn dims(&self, index: usize) -> Option<(f32, f32)> {
+        if (index as f64) < self.doc.pages_count() {
+            Some((self.width as f32, self.height as f32))
+        } else {
+            None
+        }
+    }
+
+    fn pages_count(&self) -> f64 {
+        self.doc.pages_count()
+    }
+
+    fn toc(&mut self) -> Option<Vec<TocEntry>> {
+        self.doc.toc()
+    }
+
+    fn resolve_location(&mut self, loc: Location) -> Option<f64> {
+        match loc {
+            Location::Exact(l) => {
+                let (index, screen, count) = self.position(l)?;
+                Some(screen_location(index, screen, count))
+            },
+            Location::Previous(l) => {
+                let (index, screen, count) = self.position(l)?;
+                if screen > 0 {
+                    Some(screen_location(index, screen - 1, count))
+                } else if index > 0 {
+                    let count = self.screens_count(index - 1);
+                    Some(screen_location(index - 1, count - 1, count))
+                } else {
+                    None
+                }
+            },
+            Location::Next(l) => {
+                let (index, screen, count) = self.position(l)?;
+                if screen + 1 < count {
+                    Some(screen_location(index, screen + 1, count))
+                } else if ((index + 1) as f64) < self.doc.pages_count() {
+                    Some((index + 1) as f64)
+                } else {
+                    None
+                }
+            },
+            Location::Uri(..) => None,
+        }
+    }
+
+    fn words(&mut self, loc: Location) -> Option<(Vec<BoundedText>, f64)> {
+        let location = self.resolve_location(loc)?;
+        let (index, screen, _) = self.position(location)?;
+        let (words, _) = self.doc.words(Location::Exact(index as f64))?;
+        self.map_texts(words, index, screen).map(|words| (words, location))
+    }
+
+    fn links(&mut self, loc: Location) -> Option<(Vec<BoundedText>, f64)> {
+        let location = self.resolve_location(loc)?;
+        let (index, screen, _) = self.position(location)?;
+        let (links, _) = self.doc.links(Location::Exact(index as f64))?;
+        self.map_texts(links, index, screen).map(|links| (links, location))
+    }
+
+    fn pixmap(&mut self, loc: Location, _scale: f32) -> Option<(Pixmap, f64)> {
+        let location = self.resolve_location(loc)?;
+        let (index, screen, _) = self.position(location)?;
+        let (width, height) = (self.width, self.height);
+        let page = self.page(index)?;
+        Some((compose(&page.pixmap, &page.screens[screen], width, height), location))
+    }
+
+    fn layout(&mut self, width: u32, height: u32, font_size: f32, dpi: u16) {
+        self.width = width;
+        self.height = height;
+        self.dpi = dpi;
+        self.cache.clear();
+        self.counts.clear();
+        self.doc.layout(width, height, font_size, dpi);
+    }
+
+    fn set_font_family(&mut self, family_name: &str, search_path: &str) {
+        self.doc.set_font_family(family_name, search_path);
+    }
+
+    // The rectangle covered by the words of the first screen of the page, the one
+    // rendered for its index.
+    fn boundary_box(&mut self, index: usize) -> Option<Rectangle> {
+        self.doc.boundary_box(index)?;
+        let page = self.page(index)?;
+        let mut targets = page.screens.first()?.iter().map(|p| p.target);
+        let first = targets.next()?;
+        Some(targets.fold(first, |mut rect, target| {
+            rect.absorb(&target);
+            rect
+        }))
+    }
+
+    // The labels of the source pages: the integer part of a location is the index of its page.
+    fn page_labels(&mut self) -> Option<PageLabels> {
+        self.doc.page_labels()
+    }
+
+    fn has_layers(&self) -> bool {
+        self.doc.has_layers()
+    }
+
+    fn set_render_layers(&mut self, layers: RenderLayers) {
+        self.cache.clear();
+        self.doc.set_render_layers(layers);
+    }
+
+    fn set_margin_width(&mut self, width: i32) {
+        self.doc.set_margin_width(width);
+    }
+
+    fn set_line_height(&mut self, line_height: f32) {
+        self.doc.set_line_height(line_height);
+    }
+
+    fn title(&self) -> Option<String> {
+        self.doc.title()
+    }
+
+    fn author(&self) -> Option<String> {
+        self.doc.author()
+    }
+
+    fn metadata(&self, key: &str) -> Option<String> {
+        self.doc.metadata(key)
+    }
+
+    fn is_reflowable(&self) -> bool {
+        false
+    }
+
+    fn isbn(&mut self) -> Option<String> {
+        self.doc.isbn()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color::BLACK;
+
+    fn page() -> Pixmap {
+        let mut pixmap = Pixmap::new(200, 100);
+        // Two lines of three words made of two letters each.
+        for &(x_min, y_min) in &[(20, 20), (80, 20), (140, 20), (20, 60), (80, 60), (140, 60)] {
+            for &dx in &[0, 12] {
+                for y in y_min..y_min + 20 {
+                    for x in x_min + dx..x_min + dx + 10 {
+                        pixmap.set_pixel(x as u32, y as u32, BLACK);
+                    }
+                }
+            }
+        }
+        pixmap
+    }
+
+    #[test]
+    fn test_segment_components() {
+        let lines = segment(&page(), &[]);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].words.len(), 3);
+        assert_eq!(lines[0].words[0], rect![20, 20, 42, 40]);
+        assert_eq!(lines[1].baseline, 80);
+    }
+
+    #[test]
+    fn test_segment_words() {
+        let words = vec![rect![18, 18, 44, 42]];
+        let lines = segment(&page(), &words);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].words[0], rect![18, 18, 44, 42]);
+        assert_eq!(lines[0].words.len(), 5);
+    }
+
+    #[test]
+    fn test_flow() {
+        let lines = segment(&page(), &[]);
+        let paragraphs = paragraphs(&lines);
+        assert_eq!(paragraphs.len(), 1);
+        let screens = flow(&paragraphs, median_height(&lines), 100, 40, 5);
+        assert_eq!(screens.len(), 2);
+        assert_eq!(screens[0].len() + screens[1].len(), 6);
+        let location = screen_location(3, 1, 2);
+        assert_eq!(location, 3.5);
+    }
+}
@@ -22,6 +22,7 @@ extern crate fnv;
 extern crate png;
 extern crate isbn;
 extern crate titlecase;
+extern crate flate2;
 
 #[macro_use] mod geom;
 mod unit;
@@ -35,12 +36,16 @@ mod device;
 mod font;
 mod helpers;
 mod document;
+mod dictionary;
+mod fulltext;
 mod metadata;
 mod settings;
 mod frontlight;
 mod lightsensor;
 mod symbolic_path;
 mod trash;
+mod keystore;
+mod statistics;
 mod app;
 
 use std::process;
@@ -61,7 +66,7 @@ use sdl2::pixels::{Color as SdlColor, PixelFormatEnum};
 use sdl2::rect::Point as SdlPoint;
 use sdl2::rect::Rect as SdlRect;
 use framebuffer::{Framebuffer, UpdateMode};
-use input::{DeviceEvent, FingerStatus};
+use input::{DeviceEvent, FingerStatus, ButtonCode, ButtonStatus};
 use view::{View, Event, ViewId, EntryId, EntryKind};
 use view::{render, render_no_wait, handle_event, fill_crack};
 use view::home::Home;
@@ -75,13 +80,15 @@ use helpers::{load_json, save_json, load_toml, save_toml};
 use metadata::{Metadata, METADATA_FILENAME};
 use settings::{Settings, SETTINGS_PATH};
 use geom::Rectangle;
-use gesture::gesture_events;
+use gesture::{GestureEvent, gesture_events};
 use device::CURRENT_DEVICE;
 use battery::{Battery, FakeBattery};
 use frontlight::{Frontlight, LightLevels};
 use lightsensor::LightSensor;
-use font::Fonts;
-use app::Context;
+use font::{Fonts, open_fallback_fonts};
+use fulltext::Indexer;
+use dictionary::{DICTIONARIES_DIRNAME, spawn_loader};
+use app::{Context, Display, rotate};
 
 pub const APP_NAME: &str = "Plato";
 
@@ -94,7 +101,9 @@ pub fn build_context() -> Result<Context, Error> {
     let battery = Box::new(FakeBattery::new()) as Box<Battery>;
     let frontlight = Box::new(LightLevels::default()) as Box<Frontlight>;
     let lightsensor = Box::new(0u16) as Box<LightSensor>;
-    let fonts = Fonts::load()?;
+    let mut fonts = Fonts::load()?;
+    let fallbacks = open_fallback_fonts(&settings.fallback_fonts)?;
+    fonts.set_fallbacks(&fallbacks);
     Ok(Context::new(settings, metadata, PathBuf::from(METADATA_FILENAME),
                     fonts, battery, frontlight, lightsensor))
 }
@@ -178,6 +187,13 @@ impl Framebuffer for WindowCanvas {
 
     fn toggle_monochrome(&mut self) {}
 
+    fn set_rotation(&mut self, n: i8) -> Result<(u32, u32), Error> {
+        let (width, height) = CURRENT_DEVICE.dims;
+        let (width, height) = if n % 2 == 0 { (width, height) } else { (height, width) };
+        self.window_mut().set_size(width, height).context("Can't resize the window.")?;
+        Ok((width, height))
+    }
+
     fn dims(&self) -> (u32, u32) {
         self.window().size()
     }
@@ -216,11 +232,23 @@ pub fn run() -> Result<(), Error> {
         }
     });
 
-    let fb_rect = fb.rect();
+    if context.settings.rotation != 0 {
+        match fb.set_rotation(context.settings.rotation) {
+            Ok(dims) => context.display = Display { dims, rotation: context.settings.rotation },
+            Err(e) => eprintln!("Can't rotate the display: {}", e),
+        }
+    }
+
+    let mut fb_rect = fb.rect();
 
     let mut history: Vec<Box<View>> = Vec::new();
     let mut view: Box<View> = Box::new(Home::new(fb_rect, &tx, &mut context)?);
 
+    context.indexer = Some(Indexer::new(&context.settings.library_path,
+                                        &context.metadata,
+                                        &context.settings.reader));
+    context.dictionaries_loader = Some(spawn_loader(DICTIONARIES_DIRNAME));
+
     let mut updating = FnvHashMap::default();
 
     if context.settings.frontlight {
@@ -280,6 +308,17 @@ pub fn run() -> Result<(), Error> {
                             ty.send(DeviceEvent::Finger { status: FingerStatus::Down, position, id: 0, time: 0.0}).unwrap();
                             ty.send(DeviceEvent::Finger { status: FingerStatus::Up, position, id: 0, time: 0.0}).unwrap();
                         }
+                    } else {
+                        // Simulate the page turn buttons.
+                        let code = match scancode {
+                            Scancode::PageUp => Some(ButtonCode::Backward),
+                            Scancode::PageDown => Some(ButtonCode::Forward),
+                            _ => None,
+                        };
+                        if let Some(code) = code {
+                            ty.send(DeviceEvent::Button { code, status: ButtonStatus::Pressed, time: 0.0 }).unwrap();
+                            ty.send(DeviceEvent::Button { code, status: ButtonStatus::Released, time: 0.0 }).unwrap();
+                        }
                     }
                 },
                 _ => {
@@ -310,13 +349,35 @@ pub fn run() -> Result<(), Error> {
                         updating.insert(tok, rect);
                     }
                 },
-                Event::Open(info) => {
+                // The reader maps the gestures to actions itself.
+                Event::Gesture(GestureEvent::Rotate { quarter_turns, .. }) if quarter_turns != 0 &&
+                                                                             view.downcast_ref::<Reader>().is_none() => {
+                    let n = (context.display.rotation + quarter_turns).rem_euclid(4);
+                    tx.send(Event::Select(EntryId::Rotate(n))).unwrap();
+                },
+                Event::Open(info, location) => {
                     let info2 = info.clone();
-                    if let Some(r) = Reader::new(fb_rect, *info, &tx, &mut context) {
+                    let n = info.reader.as_ref().and_then(|r| r.rotation)
+                                .unwrap_or(context.settings.rotation);
+                    let rect = if (n - context.display.rotation) % 2 == 0 {
+                        fb_rect
+                    } else {
+                        rect![0, 0, fb_rect.height() as i32, fb_rect.width() as i32]
+                    };
+                    if let Some(mut r) = Reader::new(rect, *info, location, &tx, &mut context) {
+                        if n != context.display.rotation {
+                            match rotate(n, &mut fb, &mut view, &mut history, &tx, &mut context) {
+                                Ok(rect) => fb_rect = rect,
+                                Err(e) => {
+                                    eprintln!("Can't rotate the display: {}", e);
+                                    r.resize(fb_rect, &tx, &mut context);
+                                },
+                            }
+                        }
                         history.push(view as Box<View>);
                         view = Box::new(r) as Box<View>;
                     } else {
-                        handle_event(view.as_mut(), &Event::Invalid(info2), &tx, &mut bus, &mut context);
+                        handle_event(view.as_mut(), &Event::Invalid(info2, location), &tx, &mut bus, &mut context);
                     }
                 },
                 Event::OpenToc(ref toc, current_page) => {
@@ -324,9 +385,25 @@ pub fn run() -> Result<(), Error> {
                     history.push(view as Box<View>);
                     view = Box::new(r) as Box<View>;
                 },
+                Event::OpenStatistics(ref html) => {
+                    let r = Reader::from_html(fb_rect, html, "Statistics", "css/statistics.css", None, &tx, &mut context);
+                    history.push(view as Box<View>);
+                    view = Box::new(r) as Box<View>;
+                },
+                Event::SaveSettings => {
+                    let path = Path::new(SETTINGS_PATH);
+                    save_toml(&context.settings, path).map_err(|e| eprintln!("Can't save settings: {}", e)).ok();
+                },
                 Event::Back => {
                     if let Some(v) = history.pop() {
                         view = v;
+                        let n = context.settings.rotation;
+                        if view.is::<Home>() && n != context.display.rotation {
+                            if let Ok(rect) = rotate(n, &mut fb, &mut view, &mut history, &tx, &mut context)
+                                                    .map_err(|e| eprintln!("Can't rotate the display: {}", e)) {
+                                fb_rect = rect;
+                            }
+                        }
                         view.handle_event(&Event::Reseed, &tx, &mut bus, &mut context);
                     }
                 },
@@ -339,6 +416,7 @@ pub fn run() -> Result<(), Error> {
                         let preset_menu = Menu::new(rect, ViewId::PresetMenu, MenuKind::Contextual,
                                                     vec![EntryKind::Command("Remove".to_string(),
                                                                             EntryId::RemovePreset(index))],
+                                                    context.display.dims,
                                                     &mut context.fonts);
                         tx.send(Event::Render(*preset_menu.rect(), UpdateMode::Gui)).unwrap();
                         view.children_mut().push(Box::new(preset_menu) as Box<View>);
@@ -376,6 +454,22 @@ pub fn run() -> Result<(), Error> {
                     context.monochrome = !context.monochrome;
                     tx.send(Event::Render(fb_rect, UpdateMode::Gui)).unwrap();
                 },
+                Event::Select(EntryId::Rotate(n)) if n != context.display.rotation => {
+                    let previous = context.settings.rotation;
+                    context.settings.rotation = n;
+                    if let Ok(rect) = rotate(n, &mut fb, &mut view, &mut history, &tx, &mut context)
+                                            .map_err(|e| eprintln!("Can't rotate the display: {}", e)) {
+                        fb_rect = rect;
+                    } else {
+                        context.settings.rotation = previous;
+                    }
+                },
+                Event::Rotate(n) if n != context.display.rotation => {
+                    if let Ok(rect) = rotate(n, &mut fb, &mut view, &mut history, &tx, &mut context)
+                                            .map_err(|e| eprintln!("Can't rotate the display: {}", e)) {
+                        fb_rect = rect;
+                    }
+                },
                 Event::Select(EntryId::TakeScreenshot) => {
                     let name = Local::now().format("screenshot-%Y%m%d_%H%M%S.png");
                     let msg = match fb.save(&name.to_string()) {
@@ -385,6 +479,7 @@ pub fn run() -> Result<(), Error> {
                     let notif = Notification::new(ViewId::TakeScreenshotNotif,
                                                   msg,
                                                   &mut context.notification_index,
+                                                  context.display.dims,
                                                   &mut context.fonts,
                                                   &tx);
                     view.children_mut().push(Box::new(notif) as Box<View>);
@@ -14,6 +14,7 @@ use std::os::unix::ffi::OsStrExt;
 use std::path::Path;
 use std::collections::BTreeSet;
 use std::rc::Rc;
+use std::cell::RefCell;
 use fnv::FnvHashMap;
 use failure::Error;
 use glob::glob;
@@ -94,6 +95,40 @@ pub struct FontFamily {
     pub bold_italic: Font,
 }
 
+// Fonts that provide the glyphs missing from other fonts, tried in order.
+// Like the font library, they're never used by two threads at the same time:
+// the fonts of a document are only accessed while its mutex is locked, and the
+// fonts of the interface are only accessed by the main thread.
+pub type FallbackFonts = Rc<RefCell<Vec<Font>>>;
+
+pub fn open_fallback_fonts<P: AsRef<Path>>(paths: &[P]) -> Result<FallbackFonts, Error> {
+    let opener = FontOpener::new()?;
+    let fonts = paths.iter().filter_map(|path| {
+        opener.open(path)
+              .map_err(|e| eprintln!("Can't open fallback font {}: {}", path.as_ref().display(), e))
+              .ok()
+    }).collect();
+    Ok(Rc::new(RefCell::new(fonts)))
+}
+
+// Splits the given text into runs, each run starts at the given byte offset and is
+// rendered by the given fallback font, or by the main font when the index is `None`.
+// The coverage function returns the font that covers the given character, if any.
+fn fallback_runs<F>(txt: &str, coverage: F) -> Vec<(usize, Option<usize>)>
+                    where F: Fn(char) -> Option<Option<usize>> {
+    let mut runs: Vec<(usize, Option<usize>)> = Vec::new();
+
+    for (offset, c) in txt.char_indices() {
+        // The characters that no font covers stay in the current run.
+        let index = coverage(c).unwrap_or_else(|| runs.last().and_then(|r| r.1));
+        if runs.last().map_or(true, |r| r.1 != index) {
+            runs.push((offset, index));
+        }
+    }
+
+    runs
+}
+
 pub fn family_names<P: AsRef<Path>>(search_path: P) -> Result<BTreeSet<String>, Error> {
     let opener = FontOpener::new()?;
     let end_path = Path::new("**").join("*.[ot]tf");
@@ -153,6 +188,13 @@ impl FontFamily {
             bold_italic: opener.open(bold_italic_path)?,
         })
     }
+
+    pub fn set_fallbacks(&mut self, fallbacks: &FallbackFonts) {
+        self.regular.set_fallbacks(fallbacks);
+        self.italic.set_fallbacks(fallbacks);
+        self.bold.set_fallbacks(fallbacks);
+        self.bold_italic.set_fallbacks(fallbacks);
+    }
 }
 
 pub struct Fonts {
@@ -182,6 +224,13 @@ impl Fonts {
             display: opener.open("fonts/Cormorant-Regular.ttf")?,
         })
     }
+
+    pub fn set_fallbacks(&mut self, fallbacks: &FallbackFonts) {
+        self.sans_serif.set_fallbacks(fallbacks);
+        self.serif.set_fallbacks(fallbacks);
+        self.keyboard.set_fallbacks(fallbacks);
+        self.display.set_fallbacks(fallbacks);
+    }
 }
 
 bitflags! {
@@ -250,6 +299,7 @@ pub struct Font {
     // lowercase and uppercase x heights
     pub x_heights: (u32, u32),
     space_codepoint: u32,
+    fallbacks: Option<FallbackFonts>,
 }
 
 impl RenderPlan {
@@ -294,7 +344,7 @@ impl FontOpener {
             let x_heights = (0, 0);
             let space_codepoint = FT_Get_Char_Index(face, ' ' as libc::c_ulong);
             Ok(Font { lib: self.0.clone(), face, font,
-                      size: 0, dpi: 0, ellipsis, x_heights, space_codepoint })
+                      size: 0, dpi: 0, ellipsis, x_heights, space_codepoint, fallbacks: None })
         }
     }
 
@@ -310,7 +360,7 @@ impl FontOpener {
             let x_heights = (0, 0);
             let space_codepoint = FT_Get_Char_Index(face, ' ' as libc::c_ulong);
             Ok(Font { lib: self.0.clone(), face, font,
-                      size: 0, dpi: 0, ellipsis, x_heights, space_codepoint })
+                      size: 0, dpi: 0, ellipsis, x_heights, space_codepoint, fallbacks: None })
         }
     }
 }
@@ -336,6 +386,37 @@ impl Font {
         }
     }
 
+    pub fn set_fallbacks(&mut self, fallbacks: &FallbackFonts) {
+        self.fallbacks = if fallbacks.borrow().is_empty() {
+            None
+        } else {
+            Some(fallbacks.clone())
+        };
+    }
+
+    #[inline]
+    fn has_glyph(&self, c: char) -> bool {
+        unsafe { FT_Get_Char_Index(self.face, c as libc::c_ulong) != 0 }
+    }
+
+    // Returns `None` when this font renders the whole text.
+    fn fallback_runs(&self, txt: &str) -> Option<Vec<(usize, Option<usize>)>> {
+        let fallbacks = self.fallbacks.as_ref()?.borrow();
+        let runs = fallback_runs(txt, |c| {
+            if self.has_glyph(c) {
+                Some(None)
+            } else {
+                fallbacks.iter().position(|font| font.has_glyph(c)).map(Some)
+            }
+        });
+
+        if runs.iter().all(|r| r.1.is_none()) {
+            None
+        } else {
+            Some(runs)
+        }
+    }
+
     pub fn set_size(&mut self, size: u32, dpi: u16) {
         if !self.font.is_null() && self.size == size && self.dpi == dpi {
             return;
@@ -463,6 +544,40 @@ impl Font {
     }
 
     pub fn plan(&mut self, txt: &str, max_width: Option<u32>, features: Option<&[String]>) -> RenderPlan {
+        let mut render_plan = match self.fallback_runs(txt) {
+            Some(runs) => {
+                let mut render_plan = RenderPlan::default();
+                let fallbacks = self.fallbacks.clone().unwrap();
+                for (i, &(start, index)) in runs.iter().enumerate() {
+                    let end = runs.get(i+1).map_or(txt.len(), |r| r.0);
+                    let mut plan = if let Some(index) = index {
+                        let mut fonts = fallbacks.borrow_mut();
+                        let font = &mut fonts[index];
+                        font.set_size(self.size, self.dpi);
+                        let mut plan = font.shape(&txt[start..end], features);
+                        for glyph in &mut plan.glyphs {
+                            glyph.fallback = Some(index);
+                        }
+                        plan
+                    } else {
+                        self.shape(&txt[start..end], features)
+                    };
+                    render_plan.width += plan.width;
+                    render_plan.glyphs.append(&mut plan.glyphs);
+                }
+                render_plan
+            },
+            None => self.shape(txt, features),
+        };
+
+        if let Some(mw) = max_width {
+            self.crop_right(&mut render_plan, mw);
+        }
+
+        render_plan
+    }
+
+    fn shape(&self, txt: &str, features: Option<&[String]>) -> RenderPlan {
         unsafe {
             let buf = hb_buffer_create();
             hb_buffer_add_utf8(buf,
@@ -502,14 +617,11 @@ impl Font {
                     codepoint: info_i.codepoint,
                     advance: pt!(pos_i.x_advance >> 6, pos_i.y_advance >> 6),
                     offset: pt!(pos_i.x_offset >> 6, -pos_i.y_offset >> 6),
+                    fallback: None,
                 };
                 render_plan.glyphs.push(glyph);
             }
 
-            if let Some(mw) = max_width {
-                self.crop_right(&mut render_plan, mw);
-            }
-
             hb_buffer_destroy(buf);
             render_plan
         }
@@ -537,6 +649,7 @@ impl Font {
         }
 
         let len = render_plan.glyphs.len();
+        let index = index.min(len);
         let mut width = 0;
         let mut polarity = 0;
         let mut upper_index = index;
@@ -625,18 +738,28 @@ impl Font {
 
     pub fn render(&mut self, fb: &mut Framebuffer, color: u8, render_plan: &RenderPlan, origin: Point) {
         unsafe {
+            let mut fallbacks = self.fallbacks.as_ref().map(|f| f.borrow_mut());
             let mut pos = origin;
             for glyph in &render_plan.glyphs {
-                FT_Load_Glyph(self.face, glyph.codepoint, FT_LOAD_RENDER | FT_LOAD_NO_HINTING);
-                let glyph_slot = (*self.face).glyph;
-                let top_left = pos + glyph.offset + pt!((*glyph_slot).bitmap_left, -(*glyph_slot).bitmap_top);
-                let bitmap = &(*glyph_slot).bitmap;
-                for y in 0..bitmap.rows {
-                    for x in 0..bitmap.width {
-                        let blackness = *bitmap.buffer.offset((bitmap.pitch * y + x) as isize);
-                        let alpha = blackness as f32 / 255.0;
-                        let pt = top_left + pt!(x, y);
-                        fb.set_blended_pixel(pt.x as u32, pt.y as u32, color, alpha);
+                let face = match glyph.fallback {
+                    Some(index) => fallbacks.as_mut().and_then(|f| f.get_mut(index)).map(|font| {
+                        font.set_size(self.size, self.dpi);
+                        font.face
+                    }),
+                    None => Some(self.face),
+                };
+                if let Some(face) = face {
+                    FT_Load_Glyph(face, glyph.codepoint, FT_LOAD_RENDER | FT_LOAD_NO_HINTING);
+                    let glyph_slot = (*face).glyph;
+                    let top_left = pos + glyph.offset + pt!((*glyph_slot).bitmap_left, -(*glyph_slot).bitmap_top);
+                    let bitmap = &(*glyph_slot).bitmap;
+                    for y in 0..bitmap.rows {
+                        for x in 0..bitmap.width {
+                            let blackness = *bitmap.buffer.offset((bitmap.pitch * y + x) as isize);
+                            let alpha = blackness as f32 / 255.0;
+                            let pt = top_left + pt!(x, y);
+                            fb.set_blended_pixel(pt.x as u32, pt.y as u32, color, alpha);
+                        }
                     }
                 }
                 pos += glyph.advance;
@@ -682,6 +805,8 @@ pub struct GlyphPlan {
     codepoint: u32,
     offset: Point,
     advance: Point,
+    // Index of the fallback font that renders this glyph.
+    fallback: Option<usize>,
 }
 
 #[derive(Debug, Clone)]
@@ -1121,3 +1246,27 @@ impl From<FtError> for FreetypeError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::fallback_runs;
+
+    fn coverage(c: char) -> Option<Option<usize>> {
+        match c {
+            c if c.is_ascii_lowercase() || c == ' ' => Some(None),
+            '→' => Some(Some(0)),
+            '中' | '文' => Some(Some(1)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_fallback_runs() {
+        assert_eq!(fallback_runs("", coverage), vec![]);
+        assert_eq!(fallback_runs("abc", coverage), vec![(0, None)]);
+        assert_eq!(fallback_runs("a → 中文 b", coverage),
+                   vec![(0, None), (2, Some(0)), (5, None), (6, Some(1)), (12, None)]);
+        assert_eq!(fallback_runs("中\u{FFFF}文", coverage), vec![(0, Some(1))]);
+        assert_eq!(fallback_runs("\u{FFFF}a", coverage), vec![(0, None)]);
+    }
+}
@@ -0,0 +1,144 @@
+//! Image processing of rendered pages.
+
+use super::Pixmap;
+use color::{BLACK, WHITE};
+
+// Fraction of the darkest and lightest pixels clipped by a full contrast stretch.
+const MAX_STRETCH_CLIP: f32 = 0.1;
+// Dynamic range of the standard deviation, used by Sauvola's method.
+const SAUVOLA_RANGE: f32 = 128.0;
+// Minimum side of the window, in pixels, used by Sauvola's method.
+const SAUVOLA_MIN_WINDOW: usize = 15;
+
+// Raises the normalized intensities to the given power: values greater than one darken the image.
+pub fn gamma(pixmap: &mut Pixmap, value: f32) {
+    let table: Vec<u8> = (0..256).map(|i| {
+        (255.0 * (i as f32 / 255.0).powf(value)).round() as u8
+    }).collect();
+    apply_table(pixmap, &table);
+}
+
+// Stretches the histogram so that the given amount, between zero and one, of the darkest
+// and lightest pixels become black and white.
+pub fn stretch_contrast(pixmap: &mut Pixmap, amount: f32) {
+    let mut histogram = [0usize; 256];
+    for &v in &pixmap.data {
+        histogram[v as usize] += 1;
+    }
+
+    let clip = (amount * MAX_STRETCH_CLIP * pixmap.data.len() as f32) as usize;
+    let percentile = |clip: usize, range: &mut Iterator<Item=usize>| {
+        let mut sum = 0;
+        for i in range {
+            sum += histogram[i];
+            if sum > clip {
+                return i;
+            }
+        }
+        0
+    };
+
+    let low = percentile(clip, &mut (0..256));
+    let high = percentile(clip, &mut (0..256).rev());
+
+    if high <= low {
+        return;
+    }
+
+    let table: Vec<u8> = (0..256).map(|i| {
+        let v = (i as f32 - low as f32) / (high - low) as f32;
+        (255.0 * v.max(0.0).min(1.0)).round() as u8
+    }).collect();
+    apply_table(pixmap, &table);
+}
+
+// Whitens the light background tints: the larger the amount, between zero and one,
+// the darker the tints that are removed.
+pub fn remove_tint(pixmap: &mut Pixmap, amount: f32) {
+    let level = 255.0 * (1.0 - amount / 2.0);
+    let table: Vec<u8> = (0..256).map(|i| {
+        (255.0 * (i as f32 / level).min(1.0)).round() as u8
+    }).collect();
+    apply_table(pixmap, &table);
+}
+
+// Binarizes the image with Sauvola's method, using the given sensitivity.
+pub fn binarize(pixmap: &mut Pixmap, k: f32) {
+    let width = pixmap.width as usize;
+    let height = pixmap.height as usize;
+
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    // Integral images of the intensities and of their squares.
+    let mut sums = vec![0f64; (width + 1) * (height + 1)];
+    let mut squares = vec![0f64; (width + 1) * (height + 1)];
+
+    for y in 0..height {
+        let mut row_sum = 0.0;
+        let mut row_square = 0.0;
+        for x in 0..width {
+            let v = pixmap.data[y * width + x] as f64;
+            row_sum += v;
+            row_square += v * v;
+            let addr = (y + 1) * (width + 1) + x + 1;
+            sums[addr] = sums[addr - width - 1] + row_sum;
+            squares[addr] = squares[addr - width - 1] + row_square;
+        }
+    }
+
+    let radius = SAUVOLA_MIN_WINDOW.max(width.min(height) / 50) / 2;
+    let area = |table: &[f64], x0: usize, y0: usize, x1: usize, y1: usize| {
+        table[y1 * (width + 1) + x1] - table[y0 * (width + 1) + x1] -
+        table[y1 * (width + 1) + x0] + table[y0 * (width + 1) + x0]
+    };
+
+    for y in 0..height {
+        let y0 = y.saturating_sub(radius);
+        let y1 = (y + radius + 1).min(height);
+        for x in 0..width {
+            let x0 = x.saturating_sub(radius);
+            let x1 = (x + radius + 1).min(width);
+            let count = ((x1 - x0) * (y1 - y0)) as f64;
+            let mean = area(&sums, x0, y0, x1, y1) / count;
+            let variance = (area(&squares, x0, y0, x1, y1) / count - mean * mean).max(0.0);
+            let threshold = mean * (1.0 + k as f64 * (variance.sqrt() / SAUVOLA_RANGE as f64 - 1.0));
+            let addr = y * width + x;
+            pixmap.data[addr] = if (pixmap.data[addr] as f64) < threshold { BLACK } else { WHITE };
+        }
+    }
+}
+
+fn apply_table(pixmap: &mut Pixmap, table: &[u8]) {
+    for v in &mut pixmap.data {
+        *v = table[*v as usize];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stretch_contrast() {
+        let mut pixmap = Pixmap::new(4, 1);
+        pixmap.data = vec![64, 96, 160, 192];
+        stretch_contrast(&mut pixmap, 0.0);
+        assert_eq!(pixmap.data, vec![0, 64, 191, 255]);
+    }
+
+    #[test]
+    fn test_binarize() {
+        let mut pixmap = Pixmap::new(40, 40);
+        for v in &mut pixmap.data {
+            *v = 200;
+        }
+        for x in 10..30 {
+            pixmap.data[20 * 40 + x] = 90;
+        }
+        binarize(&mut pixmap, 0.3);
+        assert_eq!(pixmap.data[20 * 40 + 15], BLACK);
+        assert_eq!(pixmap.data[5 * 40 + 5], WHITE);
+    }
+}
@@ -81,6 +81,10 @@ impl Framebuffer for Pixmap {
     fn toggle_monochrome(&mut self) {
     }
 
+    fn set_rotation(&mut self, _n: i8) -> Result<(u32, u32), Error> {
+        Err(format_err!("Can't rotate a pixmap."))
+    }
+
     fn dims(&self) -> (u32, u32) {
         (self.width, self.height)
     }
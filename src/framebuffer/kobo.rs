@@ -37,6 +37,7 @@ pub struct KoboFramebuffer {
     frame_size: libc::size_t, 
     token: u32,
     flags: u32,
+    initial_rotation: u32,
     set_pixel_rgb: SetPixelRgb,
     get_pixel_rgb: GetPixelRgb,
     as_rgb: AsRgb,
@@ -155,6 +156,36 @@ impl Framebuffer for KoboFramebuffer {
         self.flags ^= EPDC_FLAG_FORCE_MONOCHROME;
     }
 
+    // The line length and the resolution change with the orientation,
+    // hence the frame needs to be mapped again.
+    fn set_rotation(&mut self, n: i8) -> Result<(u32, u32), Error> {
+        let mut info = self.var_info.clone();
+        info.rotate = (self.initial_rotation + n as u32) % 4;
+
+        let result = unsafe {
+            libc::ioctl(self.device.as_raw_fd(), FBIOPUT_VSCREENINFO, &mut info)
+        };
+
+        if result == -1 {
+            return Err(Error::from(io::Error::last_os_error()).context("Can't set variable screen info.").into());
+        }
+
+        let var_info = var_screen_info(&self.device)?;
+        let fix_info = fix_screen_info(&self.device)?;
+        let (frame, frame_size) = map_frame(&self.device, &var_info, &fix_info)?;
+
+        unsafe {
+            libc::munmap(self.frame, self.frame_size);
+        }
+
+        self.frame = frame;
+        self.frame_size = frame_size;
+        self.var_info = var_info;
+        self.fix_info = fix_info;
+
+        Ok(self.dims())
+    }
+
     fn width(&self) -> u32 {
         self.var_info.xres
     }
@@ -177,46 +208,30 @@ impl KoboFramebuffer {
         assert_eq!(var_info.bits_per_pixel % 8, 0);
 
         let bytes_per_pixel = var_info.bits_per_pixel / 8;
+        let (frame, frame_size) = map_frame(&device, &var_info, &fix_info)?;
 
-        let mut frame_size = (var_info.xres_virtual *
-                              var_info.yres_virtual * bytes_per_pixel) as libc::size_t;
-
-        if frame_size > fix_info.smem_len as usize {
-            frame_size = fix_info.smem_len as usize;
-        }
-
-        assert!(frame_size as u32 >= var_info.yres * fix_info.line_length);
-
-        let frame = unsafe {
-            libc::mmap(ptr::null_mut(), frame_size,
-                       libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED,
-                       device.as_raw_fd(), 0)
+        let (set_pixel_rgb, get_pixel_rgb, as_rgb): (SetPixelRgb, GetPixelRgb, AsRgb) = if var_info.bits_per_pixel > 16 {
+            (set_pixel_rgb_32, get_pixel_rgb_32, as_rgb_32)
+        } else {
+            (set_pixel_rgb_16, get_pixel_rgb_16, as_rgb_16)
         };
 
-        if frame == libc::MAP_FAILED {
-            Err(Error::from(io::Error::last_os_error()).context("Can't map memory.").into())
-        } else {
-            let (set_pixel_rgb, get_pixel_rgb, as_rgb): (SetPixelRgb, GetPixelRgb, AsRgb) = if var_info.bits_per_pixel > 16 {
-                (set_pixel_rgb_32, get_pixel_rgb_32, as_rgb_32)
-            } else {
-                (set_pixel_rgb_16, get_pixel_rgb_16, as_rgb_16)
-            };
-            Ok(KoboFramebuffer {
-                   device,
-                   frame,
-                   frame_size,
-                   token: 1,
-                   flags: 0,
-                   set_pixel_rgb,
-                   get_pixel_rgb,
-                   as_rgb,
-                   bytes_per_pixel: bytes_per_pixel as u8,
-                   var_info,
-                   fix_info,
-               })
-        }
+        Ok(KoboFramebuffer {
+               device,
+               frame,
+               frame_size,
+               token: 1,
+               flags: 0,
+               initial_rotation: var_info.rotate,
+               set_pixel_rgb,
+               get_pixel_rgb,
+               as_rgb,
+               bytes_per_pixel: bytes_per_pixel as u8,
+               var_info,
+               fix_info,
+           })
     }
-    
+
     fn as_bytes(&self) -> &[u8] {
         unsafe { slice::from_raw_parts(self.frame as *const u8, self.frame_size) }
     }
@@ -312,6 +327,30 @@ fn as_rgb_32(fb: &KoboFramebuffer) -> Vec<u8> {
     rgb888
 }
 
+fn map_frame(device: &File, var_info: &VarScreenInfo, fix_info: &FixScreenInfo) -> Result<(*mut libc::c_void, libc::size_t), Error> {
+    let bytes_per_pixel = var_info.bits_per_pixel / 8;
+    let mut frame_size = (var_info.xres_virtual *
+                          var_info.yres_virtual * bytes_per_pixel) as libc::size_t;
+
+    if frame_size > fix_info.smem_len as usize {
+        frame_size = fix_info.smem_len as usize;
+    }
+
+    assert!(frame_size as u32 >= var_info.yres * fix_info.line_length);
+
+    let frame = unsafe {
+        libc::mmap(ptr::null_mut(), frame_size,
+                   libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED,
+                   device.as_raw_fd(), 0)
+    };
+
+    if frame == libc::MAP_FAILED {
+        Err(Error::from(io::Error::last_os_error()).context("Can't map memory.").into())
+    } else {
+        Ok((frame, frame_size))
+    }
+}
+
 pub fn fix_screen_info(device: &File) -> Result<FixScreenInfo, Error> {
     let mut info: FixScreenInfo = Default::default();
     let result = unsafe { ioctl(device.as_raw_fd(), FBIOGET_FSCREENINFO, &mut info) };
@@ -1,6 +1,7 @@
 mod mxcfb_sys;
 mod kobo;
 mod image;
+pub mod filters;
 
 use failure::Error;
 use geom::{Point, Rectangle, surface_area, nearest_segment_point, lerp};
@@ -28,6 +29,9 @@ pub trait Framebuffer {
     fn save(&self, path: &str) -> Result<(), Error>;
     fn toggle_inverted(&mut self);
     fn toggle_monochrome(&mut self);
+    // Rotate the display by `n` quarter turns clockwise from its initial
+    // orientation, and return the new dimensions.
+    fn set_rotation(&mut self, n: i8) -> Result<(u32, u32), Error>;
 
     fn width(&self) -> u32 {
         let (width, _) = self.dims();
@@ -5,6 +5,7 @@ extern crate libc;
 use std::mem;
 
 pub const FBIOGET_VSCREENINFO: libc::c_ulong = 0x4600;
+pub const FBIOPUT_VSCREENINFO: libc::c_ulong = 0x4601;
 pub const FBIOGET_FSCREENINFO: libc::c_ulong = 0x4602;
 
 // Platform dependent
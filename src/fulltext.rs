@@ -0,0 +1,304 @@
+//! Library-wide full-text index.
+//!
+//! The text of each book is extracted in the background and stored, compressed, in the
+//! `.fulltext` directory of the library. The catalog records the size of each indexed file,
+//! so that modified files get indexed again.
+
+use std::thread;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering as AtomicOrdering;
+use std::sync::mpsc::Sender;
+use std::thread::JoinHandle;
+use fnv::{FnvHasher, FnvHashSet};
+use regex::Regex;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use failure::{Error, ResultExt};
+use serde_json;
+use libc;
+use device::CURRENT_DEVICE;
+use document::{Document, DocumentOpener, Location, file_kind};
+use document::epub::EpubDocument;
+use helpers::{load_json, save_json};
+use keystore::stored_password;
+use metadata::Info;
+use settings::{EpubEngine, ReaderSettings};
+use view::Event;
+use view::search_results::Hit;
+
+pub const FULLTEXT_DIRNAME: &str = ".fulltext";
+// Queries starting with this prefix are full-text queries.
+pub const FULLTEXT_PREFIX: char = '/';
+const CATALOG_FILENAME: &str = "catalog.json";
+// Number of characters kept on each side of a match.
+const SNIPPET_RADIUS: usize = 64;
+const MAX_HITS_PER_BOOK: usize = 32;
+// Number of books indexed between two saves of the catalog.
+const CATALOG_SAVE_INTERVAL: usize = 8;
+
+// The text of a book: each block is associated with the location of its beginning.
+// The pages of reflowable documents depend on the layout, so their locations are
+// recorded as fractions of the pages count.
+type Paragraphs = Vec<(String, f64)>;
+// Maps the path of each indexed book to its size.
+type Catalog = BTreeMap<PathBuf, u64>;
+
+enum Extraction {
+    Text(Paragraphs),
+    // The book is protected by a password that isn't in the keystore.
+    Locked,
+    Failed,
+}
+
+pub struct Indexer {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Indexer {
+    pub fn new(library_path: &Path, metadata: &[Info], settings: &ReaderSettings) -> Indexer {
+        let running = Arc::new(AtomicBool::new(true));
+        let running2 = Arc::clone(&running);
+        let library_path = library_path.to_path_buf();
+        let files: Vec<(PathBuf, u64)> = metadata.iter()
+                                                 .map(|info| (info.file.path.clone(), info.file.size))
+                                                 .collect();
+        let settings = settings.clone();
+
+        let handle = thread::spawn(move || {
+            // Indexing should never slow down the interface.
+            unsafe { libc::setpriority(libc::PRIO_PROCESS as _, 0, 19) };
+            update_index(&library_path, &files, &settings, &running2)
+                        .map_err(|e| eprintln!("Can't update the full-text index: {}", e)).ok();
+        });
+
+        Indexer { running, handle: Some(handle) }
+    }
+
+    // Stops the indexing thread and waits for it to finish: the text is extracted one page,
+    // or one chapter, at a time, so the wait is short.
+    pub fn stop(mut self) {
+        self.halt();
+    }
+
+    fn halt(&mut self) {
+        self.running.store(false, AtomicOrdering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+    }
+}
+
+// Two indexers must never write the catalog at the same time.
+impl Drop for Indexer {
+    fn drop(&mut self) {
+        self.halt();
+    }
+}
+
+fn update_index(library_path: &Path, files: &[(PathBuf, u64)], settings: &ReaderSettings, running: &AtomicBool) -> Result<(), Error> {
+    let dir = library_path.join(FULLTEXT_DIRNAME);
+    fs::create_dir_all(&dir).context("Can't create the index directory.")?;
+
+    let catalog_path = dir.join(CATALOG_FILENAME);
+    let mut catalog: Catalog = load_json(&catalog_path).unwrap_or_default();
+
+    // Forget the books that were removed from the library.
+    let paths: FnvHashSet<&PathBuf> = files.iter().map(|&(ref path, _)| path).collect();
+    let removed: Vec<PathBuf> = catalog.keys().filter(|p| !paths.contains(p)).cloned().collect();
+    for path in &removed {
+        catalog.remove(path);
+        fs::remove_file(text_path(&dir, path)).ok();
+    }
+
+    let mut pending = removed.len();
+
+    for &(ref path, size) in files {
+        if !running.load(AtomicOrdering::Relaxed) {
+            break;
+        }
+
+        if catalog.get(path) == Some(&size) {
+            continue;
+        }
+
+        let extraction = extract_text(library_path, path, settings, running);
+
+        if !running.load(AtomicOrdering::Relaxed) {
+            break;
+        }
+
+        match extraction {
+            Extraction::Text(paragraphs) => {
+                save_text(&paragraphs, &text_path(&dir, path))
+                         .map_err(|e| eprintln!("Can't save the text of {}: {}", path.display(), e)).ok();
+            },
+            // Locked books aren't recorded, they'll be indexed once their password is known.
+            Extraction::Locked => continue,
+            // Books whose text can't be extracted are still recorded, so that we don't try again.
+            Extraction::Failed => (),
+        }
+
+        catalog.insert(path.clone(), size);
+        pending += 1;
+
+        if pending >= CATALOG_SAVE_INTERVAL {
+            save_json(&catalog, &catalog_path)?;
+            pending = 0;
+        }
+    }
+
+    if pending > 0 {
+        save_json(&catalog, &catalog_path)?;
+    }
+
+    Ok(())
+}
+
+fn extract_text(library_path: &Path, path: &Path, settings: &ReaderSettings, running: &AtomicBool) -> Extraction {
+    let full_path = library_path.join(path);
+
+    if let EpubEngine::BuiltIn = settings.epub_engine {
+        if file_kind(&full_path).as_ref().map(String::as_str) == Some("epub") {
+            return EpubDocument::new(&full_path).ok()
+                               .and_then(|mut doc| doc.paragraphs(running))
+                               .map_or(Extraction::Failed, Extraction::Text);
+        }
+    }
+
+    let mut doc = match DocumentOpener::new(settings.epub_engine).open(&full_path) {
+        Some(doc) => doc,
+        None => return Extraction::Failed,
+    };
+
+    if doc.is_protected() {
+        let unlocked = stored_password(library_path, path).map_or(false, |password| doc.authenticate(&password));
+        if !unlocked {
+            return Extraction::Locked;
+        }
+    }
+
+    let (width, height) = CURRENT_DEVICE.dims;
+    doc.layout(width, height, settings.font_size, CURRENT_DEVICE.dpi);
+    let pages_count = if doc.is_reflowable() { doc.pages_count() } else { 1.0 };
+
+    let mut paragraphs = Vec::new();
+    let mut loc = Location::Exact(0.0);
+
+    while let Some((words, location)) = doc.words(loc) {
+        if !running.load(AtomicOrdering::Relaxed) {
+            return Extraction::Failed;
+        }
+        let text = words.iter().map(|w| w.text.as_str()).collect::<Vec<&str>>().join(" ");
+        if !text.is_empty() {
+            paragraphs.push((text, location / pages_count));
+        }
+        loc = Location::Next(location);
+    }
+
+    Extraction::Text(paragraphs)
+}
+
+// Converts a location of the index into a location of the given document, as laid out.
+pub fn document_location(doc: &mut Document, location: f64) -> Option<f64> {
+    let location = if doc.is_reflowable() && !doc.has_synthetic_page_numbers() {
+        (location * doc.pages_count()).floor()
+    } else {
+        location
+    };
+    doc.resolve_location(Location::Exact(location))
+}
+
+fn text_path(dir: &Path, path: &Path) -> PathBuf {
+    let mut hasher = FnvHasher::default();
+    path.hash(&mut hasher);
+    dir.join(format!("{:016x}.json.gz", hasher.finish()))
+}
+
+fn save_text(paragraphs: &Paragraphs, path: &Path) -> Result<(), Error> {
+    // Write to a temporary file first, a search might be reading the current one.
+    let tmp_path = path.with_extension("tmp");
+    {
+        let file = File::create(&tmp_path).context("Can't create text file.")?;
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        serde_json::to_writer(&mut encoder, paragraphs).context("Can't serialize text.")?;
+        encoder.finish().context("Can't compress text.")?;
+    }
+    fs::rename(&tmp_path, path).context("Can't rename text file.")?;
+    Ok(())
+}
+
+fn load_text(path: &Path) -> Result<Paragraphs, Error> {
+    let file = File::open(path).context("Can't open text file.")?;
+    serde_json::from_reader(GzDecoder::new(file)).context("Can't parse text file.").map_err(Into::into)
+}
+
+// Searches the indexed text of the given books and sends the hits through the hub.
+pub fn search(library_path: &Path, books: &[(PathBuf, String)], query: &Regex, running: &AtomicBool, hub: &Sender<Event>) {
+    let dir = library_path.join(FULLTEXT_DIRNAME);
+
+    for &(ref path, ref title) in books {
+        if !running.load(AtomicOrdering::Relaxed) {
+            break;
+        }
+
+        let paragraphs = match load_text(&text_path(&dir, path)) {
+            Ok(paragraphs) => paragraphs,
+            Err(_) => continue,
+        };
+
+        let mut count = 0;
+
+        'paragraphs: for (text, location) in paragraphs {
+            for m in query.find_iter(&text) {
+                let (snippet, index) = snippet(&text, m.start(), m.end());
+                let hit = Hit {
+                    heading: title.clone(),
+                    snippet,
+                    index,
+                    path: path.clone(),
+                    location,
+                };
+                hub.send(Event::SearchHit(Box::new(hit))).unwrap();
+                count += 1;
+                if count >= MAX_HITS_PER_BOOK {
+                    break 'paragraphs;
+                }
+            }
+        }
+    }
+}
+
+// Returns the text surrounding the given byte range and the character index of the range's
+// start within it.
+pub fn snippet(text: &str, start: usize, end: usize) -> (String, usize) {
+    let lower = text[..start].char_indices().rev()
+                             .nth(SNIPPET_RADIUS - 1)
+                             .map_or(0, |(i, _)| i);
+    let upper = text[end..].char_indices()
+                           .nth(SNIPPET_RADIUS)
+                           .map_or(text.len(), |(i, _)| end + i);
+    (text[lower..upper].to_string(), text[lower..start].chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::snippet;
+
+    #[test]
+    fn test_snippet() {
+        let text = "Call me Ishmael.";
+        assert_eq!(snippet(text, 8, 15), (text.to_string(), 8));
+        let text = format!("{}Ishmael{}", "é".repeat(100), "ô".repeat(100));
+        let (snippet, index) = snippet(&text, 200, 207);
+        assert_eq!(index, 64);
+        assert_eq!(snippet.chars().count(), 64 + 7 + 64);
+        assert!(snippet[128..].starts_with("Ishmael"));
+    }
+}
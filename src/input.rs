@@ -4,12 +4,17 @@ use std::mem;
 use std::slice;
 use std::thread;
 use std::io::Read;
-use std::fs::File;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::AtomicI8;
+use std::sync::atomic::Ordering as AtomicOrdering;
 use std::sync::mpsc::{self, Sender, Receiver};
-use std::os::unix::io::AsRawFd;
+use std::os::unix::io::{AsRawFd, FromRawFd};
 use std::ffi::CString;
 use fnv::{FnvHashMap, FnvHashSet};
 use device::CURRENT_DEVICE;
+use settings::KeySettings;
 use geom::Point;
 use failure::{Error, ResultExt};
 
@@ -33,8 +38,25 @@ pub const SYN_REPORT: u16 = 0;
 pub const KEY_POWER: u16 = 116;
 pub const KEY_HOME: u16 = 102;
 pub const KEY_LIGHT: u16 = 90;
+pub const KEY_BACKWARD: u16 = 193;
+pub const KEY_FORWARD: u16 = 194;
 pub const SLEEP_COVER: u16 = 59;
 
+pub const INPUT_DEVICES_DIR: &str = "/dev/input";
+
+// Inotify events that signal a new event node: its creation, and the change of its
+// permissions that makes it readable.
+const IN_ATTRIB: u32 = 0x0000_0004;
+const IN_CREATE: u32 = 0x0000_0100;
+
+// _IOC(_IOC_READ, 'E', 0x20, 4): the bits of the event types supported by a device.
+const EVIOCGBIT_TYPES: libc::c_ulong = 0x8004_4520;
+
+extern "C" {
+    fn inotify_init1(flags: libc::c_int) -> libc::c_int;
+    fn inotify_add_watch(fd: libc::c_int, path: *const libc::c_char, mask: u32) -> libc::c_int;
+}
+
 pub const SINGLE_TOUCH_CODES: TouchCodes = TouchCodes {
     pressure: ABS_PRESSURE,
     x: ABS_X,
@@ -93,6 +115,8 @@ pub enum ButtonCode {
     Power,
     Home,
     Light,
+    Backward,
+    Forward,
     Raw(u16),
 }
 
@@ -104,10 +128,25 @@ impl ButtonCode {
             ButtonCode::Home
         } else if code == KEY_LIGHT {
             ButtonCode::Light
+        } else if code == KEY_BACKWARD {
+            ButtonCode::Backward
+        } else if code == KEY_FORWARD {
+            ButtonCode::Forward
         } else {
             ButtonCode::Raw(code)
         }
     }
+
+    pub fn to_raw(self) -> u16 {
+        match self {
+            ButtonCode::Power => KEY_POWER,
+            ButtonCode::Home => KEY_HOME,
+            ButtonCode::Light => KEY_LIGHT,
+            ButtonCode::Backward => KEY_BACKWARD,
+            ButtonCode::Forward => KEY_FORWARD,
+            ButtonCode::Raw(code) => code,
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -140,45 +179,158 @@ pub fn seconds(time: libc::timeval) -> f64 {
     time.tv_sec as f64 + time.tv_usec as f64 / 1e6
 }
 
-pub fn raw_events(paths: Vec<String>) -> Receiver<InputEvent> {
+pub fn raw_events(paths: Vec<String>, keys: KeySettings) -> Receiver<InputEvent> {
     let (tx, rx) = mpsc::channel();
-    thread::spawn(move || parse_raw_events(&paths, &tx));
+    thread::spawn(move || parse_raw_events(&paths, &keys, &tx));
     rx
 }
 
-pub fn parse_raw_events(paths: &[String], tx: &Sender<InputEvent>) -> Result<(), Error> {
-    let mut files = Vec::new();
-    let mut pfds = Vec::new();
+// Maps a point of the initial orientation to the display rotated by `n` quarter turns clockwise.
+fn rotate_point(pt: Point, n: i8, dims: (u32, u32)) -> Point {
+    let (width, height) = (dims.0 as i32, dims.1 as i32);
+    match n {
+        1 => pt!(pt.y, width - 1 - pt.x),
+        2 => pt!(width - 1 - pt.x, height - 1 - pt.y),
+        3 => pt!(height - 1 - pt.y, pt.x),
+        _ => pt,
+    }
+}
+
+// The external devices are the ones found while scanning the input devices directory,
+// e.g. USB or Bluetooth page turners.
+struct InputDevice {
+    file: File,
+    path: PathBuf,
+    external: bool,
+}
+
+// Returns a non-blocking inotify file that becomes readable when event nodes appear
+// in the input devices directory.
+fn watch_input_devices() -> Option<File> {
+    let path = CString::new(INPUT_DEVICES_DIR).unwrap();
+    unsafe {
+        let fd = inotify_init1(libc::O_NONBLOCK | libc::O_CLOEXEC);
+        if fd < 0 {
+            return None;
+        }
+        let file = File::from_raw_fd(fd);
+        if inotify_add_watch(fd, path.as_ptr(), IN_CREATE | IN_ATTRIB) < 0 {
+            return None;
+        }
+        Some(file)
+    }
+}
+
+// Only the devices that have keys, and aren't pointing devices, are used: the touch
+// screens report their contacts with keys too.
+fn is_keyboard(file: &File) -> bool {
+    let mut bits = [0u8; 4];
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), EVIOCGBIT_TYPES, bits.as_mut_ptr()) };
+    let supports = |kind: u16| bits[kind as usize / 8] & (1 << (kind % 8)) != 0;
+    ret >= 0 && supports(EV_KEY) && !supports(EV_ABS)
+}
+
+// Opens the keyboards of the input devices directory that aren't already opened.
+// The internal devices are reopened when they come back.
+fn scan_input_devices(devices: &mut Vec<InputDevice>, internal: &[PathBuf]) {
+    let entries = match fs::read_dir(INPUT_DEVICES_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let is_event = path.file_name().and_then(|n| n.to_str())
+                           .map_or(false, |n| n.starts_with("event"));
+        if !is_event || devices.iter().any(|d| d.path == path) {
+            continue;
+        }
+        // The node might not be readable yet: it will be retried when its permissions change.
+        if let Ok(file) = File::open(&path) {
+            if internal.contains(&path) {
+                devices.push(InputDevice { file, path, external: false });
+            } else if is_keyboard(&file) {
+                devices.push(InputDevice { file, path, external: true });
+            }
+        }
+    }
+}
+
+// Only the bound keys of the external devices are forwarded: the buttons of the device,
+// and the keys that would interfere with the power management, are never forwarded.
+fn is_external_key(evt: &InputEvent, keys: &KeySettings) -> bool {
+    evt.kind == EV_KEY && keys.action(evt.code).is_some() &&
+    ![KEY_POWER, KEY_HOME, KEY_LIGHT, SLEEP_COVER].contains(&evt.code)
+}
+
+pub fn parse_raw_events(paths: &[String], keys: &KeySettings, tx: &Sender<InputEvent>) -> Result<(), Error> {
+    let mut devices = Vec::new();
+    let internal: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
 
-    for path in paths.iter() {
+    for path in &internal {
         let file = File::open(path).context("Can't open input file.")?;
-        let fd = file.as_raw_fd();
-        files.push(file);
-        pfds.push(libc::pollfd {
-            fd,
-            events: libc::POLLIN,
-            revents: 0,
-        });
+        devices.push(InputDevice { file, path: path.clone(), external: false });
     }
 
+    let mut watch = watch_input_devices();
+    scan_input_devices(&mut devices, &internal);
+
     loop {
+        let mut pfds: Vec<libc::pollfd> = devices.iter().map(|d| d.file.as_raw_fd())
+                                                 .chain(watch.iter().map(|w| w.as_raw_fd()))
+                                                 .map(|fd| {
+            libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            }
+        }).collect();
+
+        let count = devices.len();
         let ret = unsafe { libc::poll(pfds.as_mut_ptr(), pfds.len() as libc::nfds_t, -1) };
         if ret < 0 {
             break;
         }
-        for (pfd, mut file) in pfds.iter().zip(&files) {
+
+        let mut lost = Vec::new();
+
+        for (index, (pfd, device)) in pfds.iter().zip(devices.iter_mut()).enumerate() {
             if pfd.revents & libc::POLLIN != 0 {
                 let mut input_event: InputEvent = unsafe { mem::uninitialized() };
                 unsafe {
                     let event_slice = slice::from_raw_parts_mut(&mut input_event as *mut InputEvent as *mut u8,
                                                                 mem::size_of::<InputEvent>());
-                    if file.read_exact(event_slice).is_err() {
-                        break;
+                    if device.file.read_exact(event_slice).is_err() {
+                        lost.push(index);
+                        continue;
                     }
                 }
-                tx.send(input_event).unwrap();
+                if !device.external || is_external_key(&input_event, keys) {
+                    tx.send(input_event).unwrap();
+                }
+            } else if pfd.revents & (libc::POLLHUP | libc::POLLERR | libc::POLLNVAL) != 0 {
+                lost.push(index);
             }
         }
+
+        // The files that can't be read anymore would be reported by every poll.
+        // Unplugged devices are reopened when they come back.
+        for index in lost.into_iter().rev() {
+            devices.remove(index);
+        }
+
+        // The inotify file, if any, comes after the devices.
+        let watch_events = pfds.get(count).map_or(0, |pfd| pfd.revents);
+
+        if watch_events & libc::POLLIN != 0 {
+            let mut buf = [0u8; 1024];
+            if let Some(ref mut file) = watch {
+                while file.read(&mut buf).map_or(false, |n| n > 0) {}
+            }
+            scan_input_devices(&mut devices, &internal);
+        } else if watch_events & (libc::POLLHUP | libc::POLLERR | libc::POLLNVAL) != 0 {
+            watch = None;
+        }
     }
 
     Ok(())
@@ -238,13 +390,14 @@ fn parse_usb_events(tx: &Sender<DeviceEvent>) {
     }
 }
 
-pub fn device_events(rx: Receiver<InputEvent>, dims: (u32, u32)) -> Receiver<DeviceEvent> {
+// The rotation is the number of quarter turns of the display, it's updated when the display is rotated.
+pub fn device_events(rx: Receiver<InputEvent>, dims: (u32, u32), rotation: Arc<AtomicI8>) -> Receiver<DeviceEvent> {
     let (ty, ry) = mpsc::channel();
-    thread::spawn(move || parse_device_events(&rx, &ty, dims));
+    thread::spawn(move || parse_device_events(&rx, &ty, dims, rotation));
     ry
 }
 
-pub fn parse_device_events(rx: &Receiver<InputEvent>, ty: &Sender<DeviceEvent>, dims: (u32, u32)) {
+pub fn parse_device_events(rx: &Receiver<InputEvent>, ty: &Sender<DeviceEvent>, dims: (u32, u32), rotation: Arc<AtomicI8>) {
     let mut id = 0;
     let mut position = Point::default();
     let mut pressure = 0;
@@ -280,6 +433,7 @@ pub fn parse_device_events(rx: &Receiver<InputEvent>, ty: &Sender<DeviceEvent>,
             }
         } else if evt.kind == EV_SYN {
             if evt.code == SYN_MT_REPORT || (proto == TouchProto::Single && evt.code == SYN_REPORT) {
+                let position = rotate_point(position, rotation.load(AtomicOrdering::Relaxed), dims);
                 if let Some(&p) = fingers.get(&id) {
                     if pressure > 0 {
                         if p != position {
@@ -328,7 +482,8 @@ pub fn parse_device_events(rx: &Receiver<InputEvent>, ty: &Sender<DeviceEvent>,
                 } else {
                     ty.send(DeviceEvent::CoverOff).unwrap();
                 }
-            } else {
+            } else if evt.value != 2 {
+                // A value of 2 denotes an autorepeat.
                 ty.send(DeviceEvent::Button {
                     time: seconds(evt.time),
                     code: ButtonCode::from_raw(evt.code),
@@ -339,3 +494,37 @@ pub fn parse_device_events(rx: &Receiver<InputEvent>, ty: &Sender<DeviceEvent>,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_point() {
+        let dims = (600, 800);
+        let pt = pt!(10, 20);
+        assert_eq!(rotate_point(pt, 0, dims), pt);
+        assert_eq!(rotate_point(pt, 1, dims), pt!(20, 589));
+        assert_eq!(rotate_point(pt, 2, dims), pt!(589, 779));
+        assert_eq!(rotate_point(pt, 3, dims), pt!(779, 10));
+        // The corners of the initial orientation stay corners.
+        assert_eq!(rotate_point(pt!(599, 799), 1, dims), pt!(799, 0));
+        assert_eq!(rotate_point(pt!(0, 0), 3, dims), pt!(799, 0));
+    }
+
+    #[test]
+    fn test_external_keys() {
+        let key = |code| InputEvent {
+            time: libc::timeval { tv_sec: 0, tv_usec: 0 },
+            kind: EV_KEY,
+            code,
+            value: 1,
+        };
+        let keys = KeySettings::default();
+        assert!(is_external_key(&key(KEY_FORWARD), &keys));
+        assert!(is_external_key(&key(KEY_BACKWARD), &keys));
+        assert!(!is_external_key(&key(30), &keys));
+        assert!(!is_external_key(&key(KEY_HOME), &keys));
+        assert!(!is_external_key(&key(KEY_POWER), &keys));
+    }
+}
@@ -0,0 +1,74 @@
+use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use failure::Error;
+use helpers::{load_json, save_json};
+use app::Context;
+
+// Maps the paths of the protected documents to their obfuscated passwords.
+type Keystore = BTreeMap<PathBuf, String>;
+
+const KEYSTORE_NAME: &str = ".keystore.json";
+// The passwords are obfuscated, not encrypted: this only prevents them from being read
+// at a glance.
+const OBFUSCATION_KEY: &[u8] = b"\x9b\x3e\xd1\x52\x07\xc4\x6a\xf8\x21\xbd\x45\x90\x1c\xe3\x77\x0e";
+
+pub fn load_password(path: &Path, context: &Context) -> Option<String> {
+    stored_password(&context.settings.library_path, path)
+}
+
+pub fn stored_password(library_path: &Path, path: &Path) -> Option<String> {
+    let keystore_path = library_path.join(KEYSTORE_NAME);
+    let keystore = load_json::<Keystore, _>(&keystore_path).ok()?;
+    keystore.get(path).and_then(|hex| reveal(hex))
+}
+
+pub fn save_password(path: &Path, password: &str, context: &Context) -> Result<(), Error> {
+    let keystore_path = context.settings.library_path.join(KEYSTORE_NAME);
+    let mut keystore = load_json::<Keystore, _>(&keystore_path).unwrap_or_default();
+    keystore.insert(path.to_path_buf(), obfuscate(password));
+    save_json(&keystore, &keystore_path)
+}
+
+// Returns whether a password was removed.
+pub fn forget_password(path: &Path, context: &Context) -> Result<bool, Error> {
+    let keystore_path = context.settings.library_path.join(KEYSTORE_NAME);
+    if !keystore_path.exists() {
+        return Ok(false);
+    }
+    let mut keystore = load_json::<Keystore, _>(&keystore_path)?;
+    if keystore.remove(path).is_none() {
+        return Ok(false);
+    }
+    save_json(&keystore, &keystore_path)?;
+    Ok(true)
+}
+
+fn obfuscate(password: &str) -> String {
+    password.bytes().zip(OBFUSCATION_KEY.iter().cycle())
+            .map(|(b, k)| format!("{:02x}", b ^ k))
+            .collect()
+}
+
+fn reveal(hex: &str) -> Option<String> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+    let bytes = (0..hex.len()).step_by(2).zip(OBFUSCATION_KEY.iter().cycle())
+                              .map(|(i, k)| u8::from_str_radix(&hex[i..i+2], 16).ok().map(|b| b ^ k))
+                              .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_obfuscation() {
+        let password = "pâté, 1234";
+        let hex = obfuscate(password);
+        assert!(!hex.contains("1234"));
+        assert_eq!(reveal(&hex).as_ref().map(String::as_str), Some(password));
+        assert_eq!(reveal("0"), None);
+    }
+}
@@ -22,6 +22,7 @@ extern crate zip;
 extern crate png;
 extern crate isbn;
 extern crate titlecase;
+extern crate flate2;
 
 #[macro_use] mod geom;
 mod unit;
@@ -35,10 +36,14 @@ mod input;
 mod gesture;
 mod helpers;
 mod document;
+mod dictionary;
+mod fulltext;
 mod metadata;
 mod symbolic_path;
 mod settings;
 mod trash;
+mod keystore;
+mod statistics;
 mod view;
 mod font;
 mod app;
@@ -6,9 +6,10 @@ use std::collections::BTreeSet;
 use std::cmp::Ordering;
 use fnv::{FnvHashMap, FnvHashSet};
 use chrono::{Local, DateTime};
-use document::DocumentOpener;
+use document::{DocumentOpener, RenderLayers, StyleTweak, TextAlign, TocEntry};
 use settings::EpubEngine;
 use helpers::simple_date_format;
+use serde::{Deserialize, Deserializer};
 use regex::Regex;
 use document::file_kind;
 use symbolic_path;
@@ -100,6 +101,24 @@ impl Default for Margin {
 pub enum PageScheme {
     Any,
     EvenOdd,
+    Auto,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ZoomMode {
+    FitToPage,
+    FitToWidth,
+    // Each text column, in reading order, fit to the width.
+    Columns,
+    // Percentage of the fit to page scale.
+    Custom(u16),
+}
+
+impl Default for ZoomMode {
+    fn default() -> Self {
+        ZoomMode::FitToPage
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -107,6 +126,11 @@ pub enum PageScheme {
 pub enum CroppingMargins {
     Any(Margin),
     EvenOdd([Margin; 2]),
+    // The margins are computed from the content of each page.
+    Auto {
+        #[serde(rename = "ignoreNoise")]
+        ignore_noise: bool,
+    },
 }
 
 impl CroppingMargins {
@@ -114,6 +138,7 @@ impl CroppingMargins {
         match *self {
             CroppingMargins::Any(ref margin) => margin,
             CroppingMargins::EvenOdd(ref pair) => &pair[index % 2],
+            CroppingMargins::Auto { .. } => &Margin { top: 0.0, right: 0.0, bottom: 0.0, left: 0.0 },
         }
     }
 
@@ -121,6 +146,10 @@ impl CroppingMargins {
         match *self {
             CroppingMargins::Any(ref mut margin) => margin,
             CroppingMargins::EvenOdd(ref mut pair) => &mut pair[index % 2],
+            CroppingMargins::Auto { .. } => {
+                *self = CroppingMargins::Any(Margin::default());
+                self.margin_mut(index)
+            },
         }
     }
 
@@ -130,13 +159,74 @@ impl CroppingMargins {
         match scheme {
             PageScheme::Any => *self = CroppingMargins::Any(margin),
             PageScheme::EvenOdd => *self = CroppingMargins::EvenOdd([margin.clone(), margin]),
+            PageScheme::Auto => if !self.is_auto() {
+                *self = CroppingMargins::Auto { ignore_noise: false };
+            },
         }
     }
 
     pub fn is_split(&self) -> bool {
         match *self {
-            CroppingMargins::Any(..) => false,
-            _ => true,
+            CroppingMargins::EvenOdd(..) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_auto(&self) -> bool {
+        match *self {
+            CroppingMargins::Auto { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+// The processing applied to the rendered pages of fixed layout documents.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ImageAdjustments {
+    pub gamma: f32,
+    // The following amounts range from zero, which disables the corresponding
+    // processing, to one.
+    pub contrast: f32,
+    pub tint_removal: f32,
+    pub binarization: f32,
+}
+
+impl Default for ImageAdjustments {
+    fn default() -> Self {
+        ImageAdjustments {
+            gamma: 1.0,
+            contrast: 0.0,
+            tint_removal: 0.0,
+            binarization: 0.0,
+        }
+    }
+}
+
+impl ImageAdjustments {
+    pub fn is_identity(&self) -> bool {
+        *self == ImageAdjustments::default()
+    }
+}
+
+// The totals of the reading sessions of a book.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ReadingStatistics {
+    // Time spent reading, in seconds.
+    pub time: u64,
+    pub sessions: usize,
+    pub pages_turned: usize,
+    // Distance, in locations, covered by turning pages forward.
+    pub covered: f64,
+}
+
+impl ReadingStatistics {
+    pub fn seconds_per_location(&self) -> Option<f64> {
+        if self.time > 0 && self.covered > 0.0 {
+            Some(self.time as f64 / self.covered)
+        } else {
+            None
         }
     }
 }
@@ -159,16 +249,95 @@ pub struct ReaderInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub line_height: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub first_page: Option<usize>,
+    pub text_align: Option<TextAlign>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hyphenation: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore_document_css: Option<bool>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub bookmarks: Vec<f64>,
+    pub style_tweaks: Vec<StyleTweak>,
+    // Stylesheet rules specific to this book.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_css: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_page: Option<usize>,
+    // The zoom factor of the reflow mode, if enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reflow: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zoom_mode: Option<ZoomMode>,
+    // The rotation of the display chosen while reading the book.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<i8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_adjustments: Option<ImageAdjustments>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub render_layers: Option<RenderLayers>,
+    // The table of contents built from the headings, for documents without an outline.
+    // It's empty when no headings were found.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub synthetic_toc: Option<Vec<TocEntry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub statistics: Option<ReadingStatistics>,
+    #[serde(skip_serializing_if = "Vec::is_empty", deserialize_with = "deserialize_bookmarks")]
+    pub bookmarks: Vec<Bookmark>,
     pub finished: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bookmark {
+    pub location: f64,
+    #[serde(with = "simple_date_format")]
+    pub created: DateTime<Local>,
+    // The name or note given by the user.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    // The title of the chapter and the beginning of the text of the page, at creation.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub chapter: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub excerpt: String,
+}
+
+impl Bookmark {
+    pub fn new(location: f64) -> Bookmark {
+        Bookmark {
+            location,
+            created: Local::now(),
+            name: String::new(),
+            chapter: String::new(),
+            excerpt: String::new(),
+        }
+    }
+}
+
+// The bookmarks used to be bare locations.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnyBookmark {
+    Location(f64),
+    Record(Bookmark),
+}
+
+fn deserialize_bookmarks<'de, D>(deserializer: D) -> Result<Vec<Bookmark>, D::Error> where D: Deserializer<'de> {
+    let bookmarks = Vec::<AnyBookmark>::deserialize(deserializer)?;
+    Ok(bookmarks.into_iter().map(|b| match b {
+        AnyBookmark::Location(location) => Bookmark::new(location),
+        AnyBookmark::Record(bookmark) => bookmark,
+    }).collect())
+}
+
 impl ReaderInfo {
     pub fn progress(&self) -> f32 {
         (self.current_page / self.pages_count) as f32
     }
+
+    // Returns the index of the bookmark at the given location, or the index where it would be inserted.
+    pub fn find_bookmark(&self, location: f64) -> Result<usize, usize> {
+        self.bookmarks.binary_search_by(|b| b.location.partial_cmp(&location)
+                                                      .unwrap_or(Ordering::Equal))
+    }
 }
 
 impl Default for ReaderInfo {
@@ -181,7 +350,19 @@ impl Default for ReaderInfo {
             font_size: None,
             margin_width: None,
             line_height: None,
+            text_align: None,
+            hyphenation: None,
+            ignore_document_css: None,
+            style_tweaks: Vec::new(),
+            user_css: None,
             first_page: None,
+            reflow: None,
+            zoom_mode: None,
+            rotation: None,
+            image_adjustments: None,
+            render_layers: None,
+            synthetic_toc: None,
+            statistics: None,
             cropping_margins: None,
             bookmarks: Vec::new(),
             finished: false,
@@ -312,6 +493,10 @@ impl Info {
 }
 
 pub fn make_query(text: &str) -> Option<Regex> {
+    make_search_query(text, false, false)
+}
+
+pub fn make_search_query(text: &str, case_sensitive: bool, whole_word: bool) -> Option<Regex> {
     let any = Regex::new(r"^\.*$").unwrap();
 
     if any.is_match(text) {
@@ -326,7 +511,13 @@ pub fn make_query(text: &str) -> Option<Regex> {
                    .replace('c', "[cç]")
                    .replace("ae", "(ae|æ)")
                    .replace("oe", "(oe|œ)");
-    Regex::new(&format!("(?i){}", text))
+    let text = if whole_word {
+        format!(r"\b(?:{})\b", text)
+    } else {
+        text
+    };
+    let flags = if case_sensitive { "" } else { "(?i)" };
+    Regex::new(&format!("{}{}", flags, text))
           .map_err(|e| eprintln!("{}", e))
           .ok()
 }
@@ -538,3 +729,19 @@ fn find_files(root: &Path, dir: &Path) -> Result<Vec<FileInfo>, Error> {
 
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmarks_migration() {
+        let text = r#"{"bookmarks": [3, 12.5, {"location": 20.0, "created": "2018-10-02 09:30:00", "name": "Notes"}]}"#;
+        let reader: ReaderInfo = serde_json::from_str(text).unwrap();
+        let locations: Vec<f64> = reader.bookmarks.iter().map(|b| b.location).collect();
+        assert_eq!(locations, vec![3.0, 12.5, 20.0]);
+        assert_eq!(reader.bookmarks[2].name, "Notes");
+        assert_eq!(reader.find_bookmark(12.5), Ok(1));
+        assert_eq!(reader.find_bookmark(15.0), Err(2));
+    }
+}
@@ -0,0 +1,313 @@
+use geom::{Point, Rectangle, Dir};
+use gesture::GestureEvent;
+
+// Width of the left and right strips of the default layout, relative to the width of the screen.
+pub const DEFAULT_STRIP_WIDTH: f32 = 1.0 / 3.0;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GestureKind {
+    Tap,
+    HoldFinger,
+    SwipeNorth,
+    SwipeEast,
+    SwipeSouth,
+    SwipeWest,
+    MultiTap,
+    MultiSwipeNorth,
+    MultiSwipeEast,
+    MultiSwipeSouth,
+    MultiSwipeWest,
+    Pinch,
+    Spread,
+    Rotate,
+}
+
+impl GestureKind {
+    // Returns the kind of the given gesture, and the point where it happened.
+    pub fn from_event(ge: &GestureEvent) -> Option<(GestureKind, Point)> {
+        match *ge {
+            GestureEvent::Tap(pt) => Some((GestureKind::Tap, pt)),
+            GestureEvent::HoldFinger(pt) => Some((GestureKind::HoldFinger, pt)),
+            GestureEvent::Swipe { dir, start, .. } => {
+                let kind = match dir {
+                    Dir::North => GestureKind::SwipeNorth,
+                    Dir::East => GestureKind::SwipeEast,
+                    Dir::South => GestureKind::SwipeSouth,
+                    Dir::West => GestureKind::SwipeWest,
+                };
+                Some((kind, start))
+            },
+            GestureEvent::MultiTap(pts) => Some((GestureKind::MultiTap, (pts[0] + pts[1]) / 2)),
+            GestureEvent::MultiSwipe { dir, starts, .. } => {
+                let kind = match dir {
+                    Dir::North => GestureKind::MultiSwipeNorth,
+                    Dir::East => GestureKind::MultiSwipeEast,
+                    Dir::South => GestureKind::MultiSwipeSouth,
+                    Dir::West => GestureKind::MultiSwipeWest,
+                };
+                Some((kind, (starts[0] + starts[1]) / 2))
+            },
+            GestureEvent::Pinch { starts, .. } => Some((GestureKind::Pinch, (starts[0] + starts[1]) / 2)),
+            GestureEvent::Spread { starts, .. } => Some((GestureKind::Spread, (starts[0] + starts[1]) / 2)),
+            GestureEvent::Rotate { center, .. } => Some((GestureKind::Rotate, center)),
+            GestureEvent::HoldButton(..) => None,
+        }
+    }
+}
+
+// The zones of the default layout: two strips on the left and right sides of the screen,
+// whose corners are cut diagonally, and the band in between.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Zone {
+    Anywhere,
+    LeftEar,
+    RightEar,
+    MiddleBand,
+    TopLeftCorner,
+    TopRightCorner,
+    BottomLeftCorner,
+    BottomRightCorner,
+}
+
+impl Zone {
+    pub fn includes(self, pt: Point, rect: &Rectangle, strip_width: f32) -> bool {
+        let width = (rect.width() as f32 * strip_width) as i32;
+        let x1 = rect.min.x + width;
+        let x2 = rect.max.x - width;
+
+        // The distance to the inner edge of the strip sets the height of the corners.
+        let (dx, is_left) = if pt.x < x1 {
+            (x1 - pt.x, true)
+        } else if pt.x > x2 {
+            (pt.x - x2, false)
+        } else {
+            return self == Zone::Anywhere || self == Zone::MiddleBand;
+        };
+
+        let is_top = pt.y < rect.min.y + dx;
+        let is_bottom = !is_top && pt.y > rect.max.y - dx;
+
+        match self {
+            Zone::Anywhere => true,
+            Zone::LeftEar => is_left && !is_top && !is_bottom,
+            Zone::RightEar => !is_left && !is_top && !is_bottom,
+            Zone::TopLeftCorner => is_left && is_top,
+            Zone::TopRightCorner => !is_left && is_top,
+            Zone::BottomLeftCorner => is_left && is_bottom,
+            Zone::BottomRightCorner => !is_left && is_bottom,
+            Zone::MiddleBand => false,
+        }
+    }
+
+    fn mirrored(self) -> Zone {
+        match self {
+            Zone::LeftEar => Zone::RightEar,
+            Zone::RightEar => Zone::LeftEar,
+            Zone::TopLeftCorner => Zone::TopRightCorner,
+            Zone::TopRightCorner => Zone::TopLeftCorner,
+            Zone::BottomLeftCorner => Zone::BottomRightCorner,
+            Zone::BottomRightCorner => Zone::BottomLeftCorner,
+            _ => self,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Region {
+    Zone(Zone),
+    // The left, top, right and bottom edges, relative to the dimensions of the screen.
+    Fractions([f32; 4]),
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Region::Zone(Zone::Anywhere)
+    }
+}
+
+impl Region {
+    pub fn includes(&self, pt: Point, rect: &Rectangle, strip_width: f32) -> bool {
+        match *self {
+            Region::Zone(zone) => zone.includes(pt, rect, strip_width),
+            Region::Fractions([left, top, right, bottom]) => {
+                let (width, height) = (rect.width() as f32, rect.height() as f32);
+                rect![rect.min.x + (left * width) as i32,
+                      rect.min.y + (top * height) as i32,
+                      rect.min.x + (right * width) as i32,
+                      rect.min.y + (bottom * height) as i32].includes(pt)
+            },
+        }
+    }
+
+    fn mirrored(&self) -> Region {
+        match *self {
+            Region::Zone(zone) => Region::Zone(zone.mirrored()),
+            Region::Fractions([left, top, right, bottom]) => Region::Fractions([1.0 - right, top, 1.0 - left, bottom]),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReaderAction {
+    Ignore,
+    // In search mode, the page actions go through the pages with results.
+    NextPage,
+    PreviousPage,
+    // In search mode, the chapter actions go to the last and first pages with results.
+    NextChapter,
+    PreviousChapter,
+    NextBookmark,
+    PreviousBookmark,
+    ToggleBookmark,
+    Bookmarks,
+    // Goes back to the previous location.
+    PreviousLocation,
+    // Goes to the previous page in search mode, and back from ephemeral documents.
+    TableOfContents,
+    // Goes to the next page in search mode.
+    GoToPage,
+    ToggleBars,
+    Search,
+    // Guesses the frontlight levels if there's more than one preset, toggles the frontlight otherwise.
+    Frontlight,
+    ToggleMonochrome,
+    // Looks up the word under the finger if there's one, refreshes the screen otherwise.
+    Dictionary,
+    FullRefresh,
+    Zoom,
+    Rotate,
+    Back,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GestureBinding {
+    pub gesture: GestureKind,
+    #[serde(default)]
+    pub region: Region,
+    pub action: ReaderAction,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GestureProfile {
+    Default,
+    // The default layout, mirrored horizontally.
+    LeftHanded,
+    // Only the user defined bindings.
+    Empty,
+}
+
+impl GestureProfile {
+    pub fn bindings(self) -> Vec<GestureBinding> {
+        let default = default_bindings();
+        match self {
+            GestureProfile::Default => default,
+            GestureProfile::LeftHanded => default.into_iter().map(|b| {
+                let gesture = match b.gesture {
+                    GestureKind::SwipeEast => GestureKind::SwipeWest,
+                    GestureKind::SwipeWest => GestureKind::SwipeEast,
+                    kind => kind,
+                };
+                GestureBinding { gesture, region: b.region.mirrored(), action: b.action }
+            }).collect(),
+            GestureProfile::Empty => Vec::new(),
+        }
+    }
+}
+
+fn default_bindings() -> Vec<GestureBinding> {
+    use self::GestureKind::*;
+    use self::Zone::*;
+    use self::ReaderAction as A;
+
+    [(Tap, TopLeftCorner, A::PreviousLocation),
+     (Tap, BottomLeftCorner, A::TableOfContents),
+     (Tap, LeftEar, A::PreviousPage),
+     (Tap, TopRightCorner, A::ToggleBookmark),
+     (Tap, BottomRightCorner, A::GoToPage),
+     (Tap, RightEar, A::NextPage),
+     (Tap, MiddleBand, A::ToggleBars),
+     (HoldFinger, TopLeftCorner, A::PreviousBookmark),
+     (HoldFinger, BottomLeftCorner, A::Frontlight),
+     (HoldFinger, LeftEar, A::PreviousChapter),
+     (HoldFinger, TopRightCorner, A::NextBookmark),
+     (HoldFinger, BottomRightCorner, A::ToggleMonochrome),
+     (HoldFinger, RightEar, A::NextChapter),
+     (HoldFinger, MiddleBand, A::Dictionary),
+     (SwipeWest, Anywhere, A::NextPage),
+     (SwipeEast, Anywhere, A::PreviousPage),
+     (Pinch, Anywhere, A::Zoom),
+     (Spread, Anywhere, A::Zoom),
+     (Rotate, Anywhere, A::Rotate)].iter().map(|&(gesture, zone, action)| {
+        GestureBinding { gesture, region: Region::Zone(zone), action }
+    }).collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct GestureSettings {
+    pub profile: GestureProfile,
+    // Width of the left and right strips, relative to the width of the screen.
+    pub strip_width: f32,
+    // These bindings take precedence over the ones of the profile.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bindings: Vec<GestureBinding>,
+}
+
+impl Default for GestureSettings {
+    fn default() -> Self {
+        GestureSettings {
+            profile: GestureProfile::Default,
+            strip_width: DEFAULT_STRIP_WIDTH,
+            bindings: Vec::new(),
+        }
+    }
+}
+
+impl GestureSettings {
+    // Returns the action bound to the given gesture, happening at the given point of the given rectangle.
+    pub fn action(&self, kind: GestureKind, pt: Point, rect: &Rectangle) -> Option<ReaderAction> {
+        self.bindings.iter().cloned()
+            .chain(self.profile.bindings())
+            .find(|b| b.gesture == kind && b.region.includes(pt, rect, self.strip_width))
+            .map(|b| b.action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_layout() {
+        let settings = GestureSettings::default();
+        let rect = rect![0, 0, 600, 800];
+        assert_eq!(settings.action(GestureKind::Tap, pt!(10, 10), &rect), Some(ReaderAction::PreviousLocation));
+        assert_eq!(settings.action(GestureKind::Tap, pt!(10, 400), &rect), Some(ReaderAction::PreviousPage));
+        assert_eq!(settings.action(GestureKind::Tap, pt!(590, 790), &rect), Some(ReaderAction::GoToPage));
+        assert_eq!(settings.action(GestureKind::Tap, pt!(300, 10), &rect), Some(ReaderAction::ToggleBars));
+        assert_eq!(settings.action(GestureKind::HoldFinger, pt!(590, 400), &rect), Some(ReaderAction::NextChapter));
+        assert_eq!(settings.action(GestureKind::MultiTap, pt!(300, 400), &rect), None);
+    }
+
+    #[test]
+    fn test_bindings() {
+        let settings = GestureSettings {
+            profile: GestureProfile::LeftHanded,
+            strip_width: DEFAULT_STRIP_WIDTH,
+            bindings: vec![GestureBinding { gesture: GestureKind::Tap,
+                                            region: Region::Fractions([0.0, 0.5, 1.0, 1.0]),
+                                            action: ReaderAction::NextPage }],
+        };
+        let rect = rect![0, 0, 600, 800];
+        assert_eq!(settings.action(GestureKind::Tap, pt!(300, 600), &rect), Some(ReaderAction::NextPage));
+        assert_eq!(settings.action(GestureKind::Tap, pt!(10, 300), &rect), Some(ReaderAction::NextPage));
+        assert_eq!(settings.action(GestureKind::Tap, pt!(590, 300), &rect), Some(ReaderAction::PreviousPage));
+        assert_eq!(settings.action(GestureKind::SwipeEast, pt!(300, 300), &rect), Some(ReaderAction::NextPage));
+    }
+}
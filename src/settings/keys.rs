@@ -0,0 +1,71 @@
+use input::{KEY_BACKWARD, KEY_FORWARD};
+use super::ReaderAction;
+
+// Key codes sent by common page turners and keyboards.
+pub const KEY_UP: u16 = 103;
+pub const KEY_PAGEUP: u16 = 104;
+pub const KEY_LEFT: u16 = 105;
+pub const KEY_RIGHT: u16 = 106;
+pub const KEY_DOWN: u16 = 108;
+pub const KEY_PAGEDOWN: u16 = 109;
+pub const KEY_VOLUMEDOWN: u16 = 114;
+pub const KEY_VOLUMEUP: u16 = 115;
+pub const KEY_NEXTSONG: u16 = 163;
+pub const KEY_PREVIOUSSONG: u16 = 165;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct KeyBinding {
+    pub code: u16,
+    pub action: ReaderAction,
+}
+
+fn default_bindings() -> Vec<KeyBinding> {
+    vec![KeyBinding { code: KEY_BACKWARD, action: ReaderAction::PreviousPage },
+         KeyBinding { code: KEY_FORWARD, action: ReaderAction::NextPage },
+         KeyBinding { code: KEY_PAGEUP, action: ReaderAction::PreviousPage },
+         KeyBinding { code: KEY_PAGEDOWN, action: ReaderAction::NextPage },
+         KeyBinding { code: KEY_LEFT, action: ReaderAction::PreviousPage },
+         KeyBinding { code: KEY_RIGHT, action: ReaderAction::NextPage },
+         KeyBinding { code: KEY_UP, action: ReaderAction::PreviousPage },
+         KeyBinding { code: KEY_DOWN, action: ReaderAction::NextPage },
+         KeyBinding { code: KEY_PREVIOUSSONG, action: ReaderAction::PreviousPage },
+         KeyBinding { code: KEY_NEXTSONG, action: ReaderAction::NextPage },
+         KeyBinding { code: KEY_VOLUMEDOWN, action: ReaderAction::PreviousPage },
+         KeyBinding { code: KEY_VOLUMEUP, action: ReaderAction::NextPage }]
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct KeySettings {
+    // These bindings take precedence over the default ones.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bindings: Vec<KeyBinding>,
+}
+
+impl KeySettings {
+    // Returns the action bound to the given key code.
+    pub fn action(&self, code: u16) -> Option<ReaderAction> {
+        self.bindings.iter().cloned()
+            .chain(default_bindings())
+            .find(|b| b.code == code)
+            .map(|b| b.action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_bindings() {
+        let mut settings = KeySettings::default();
+        assert_eq!(settings.action(KEY_FORWARD), Some(ReaderAction::NextPage));
+        assert_eq!(settings.action(KEY_VOLUMEDOWN), Some(ReaderAction::PreviousPage));
+        assert_eq!(settings.action(30), None);
+        settings.bindings.push(KeyBinding { code: KEY_VOLUMEDOWN, action: ReaderAction::Ignore });
+        settings.bindings.push(KeyBinding { code: 30, action: ReaderAction::ToggleBookmark });
+        assert_eq!(settings.action(KEY_VOLUMEDOWN), Some(ReaderAction::Ignore));
+        assert_eq!(settings.action(30), Some(ReaderAction::ToggleBookmark));
+    }
+}
@@ -1,10 +1,16 @@
 mod preset;
+mod gestures;
+mod keys;
 
 use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet};
 use fnv::FnvHashSet;
 use frontlight::LightLevels;
+use document::TextAlign;
 
 pub use self::preset::{LightPreset, guess_frontlight};
+pub use self::gestures::{GestureSettings, GestureKind, ReaderAction};
+pub use self::keys::KeySettings;
 
 pub const SETTINGS_PATH: &str = "Settings.toml";
 pub const DEFAULT_FONT_PATH: &str = "/mnt/onboard/fonts";
@@ -14,6 +20,8 @@ pub const DEFAULT_FONT_SIZE: f32 = 11.0;
 pub const DEFAULT_MARGIN_WIDTH: i32 = 8;
 // Default line height in ems
 pub const DEFAULT_LINE_HEIGHT: f32 = 1.2;
+// Default minimum font size in points
+pub const DEFAULT_MIN_FONT_SIZE: f32 = 6.0;
 // Default font family name
 pub const DEFAULT_FONT_FAMILY: &str = "Libertinus Serif";
 
@@ -24,6 +32,11 @@ pub struct Settings {
     pub library_path: PathBuf,
     pub frontlight: bool,
     pub wifi: bool,
+    // Number of quarter turns, clockwise, of the display.
+    pub rotation: i8,
+    // Fonts that provide the glyphs missing from the fonts of the interface and of the books.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fallback_fonts: Vec<PathBuf>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub frontlight_presets: Vec<LightPreset>,
     pub home: HomeSettings,
@@ -65,6 +78,45 @@ pub struct ReaderSettings {
     pub font_size: f32,
     pub margin_width: i32,
     pub line_height: f32,
+    pub min_font_size: f32,
+    // Bounds the ratio between the font sizes set by the publisher and the body font size.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_font_size_ratio: Option<f32>,
+    pub remember_passwords: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub typography_profiles: Vec<TypographyProfile>,
+    // Stylesheet rules applied to the books of the given categories.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub category_css: BTreeMap<String, String>,
+    pub gestures: GestureSettings,
+    pub keys: KeySettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TypographyProfile {
+    pub name: String,
+    pub font_family: String,
+    pub font_size: f32,
+    pub margin_width: i32,
+    pub line_height: f32,
+    // The alignment forced on the body text, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_align: Option<TextAlign>,
+    pub hyphenation: bool,
+    // The profile is applied to the books of these kinds, or with these categories,
+    // when they're opened for the first time.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+}
+
+impl TypographyProfile {
+    pub fn is_default_for(&self, kind: &str, categories: &BTreeSet<String>) -> bool {
+        self.kinds.iter().any(|k| k == kind) ||
+        self.categories.iter().any(|c| categories.contains(c))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -108,6 +160,29 @@ impl Default for ReaderSettings {
             font_size: DEFAULT_FONT_SIZE,
             margin_width: DEFAULT_MARGIN_WIDTH,
             line_height: DEFAULT_LINE_HEIGHT,
+            min_font_size: DEFAULT_MIN_FONT_SIZE,
+            max_font_size_ratio: None,
+            remember_passwords: false,
+            typography_profiles: Vec::new(),
+            category_css: BTreeMap::new(),
+            gestures: GestureSettings::default(),
+            keys: KeySettings::default(),
+        }
+    }
+}
+
+impl Default for TypographyProfile {
+    fn default() -> Self {
+        TypographyProfile {
+            name: String::default(),
+            font_family: DEFAULT_FONT_FAMILY.to_string(),
+            font_size: DEFAULT_FONT_SIZE,
+            margin_width: DEFAULT_MARGIN_WIDTH,
+            line_height: DEFAULT_LINE_HEIGHT,
+            text_align: None,
+            hyphenation: true,
+            kinds: Vec::new(),
+            categories: Vec::new(),
         }
     }
 }
@@ -137,6 +212,8 @@ impl Default for Settings {
             library_path: PathBuf::from("/mnt/onboard"),
             frontlight: true,
             wifi: false,
+            rotation: 0,
+            fallback_fonts: Vec::new(),
             home: HomeSettings::default(),
             reader: ReaderSettings::default(),
             import: ImportSettings::default(),
@@ -146,3 +223,22 @@ impl Default for Settings {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_typography_profile() {
+        let profile = TypographyProfile { kinds: vec!["epub".to_string()],
+                                          categories: vec!["Poetry".to_string()],
+                                          .. Default::default() };
+        let mut categories = BTreeSet::new();
+        assert!(profile.is_default_for("epub", &categories));
+        assert!(!profile.is_default_for("pdf", &categories));
+        categories.insert("Fiction".to_string());
+        assert!(!profile.is_default_for("pdf", &categories));
+        categories.insert("Poetry".to_string());
+        assert!(profile.is_default_for("pdf", &categories));
+    }
+}
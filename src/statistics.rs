@@ -0,0 +1,277 @@
+use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use chrono::{Local, DateTime, NaiveDate, Duration};
+use failure::Error;
+use helpers::{load_json, save_json, simple_date_format};
+use metadata::{Info, Metadata, ReadingStatistics};
+use app::Context;
+
+pub const STATISTICS_NAME: &str = ".statistics.json";
+// The time spent on a single page is capped, to discard the periods of inactivity.
+const MAX_PAGE_DURATION: i64 = 10 * 60;
+// Shorter sessions aren't recorded.
+const MIN_SESSION_DURATION: u64 = 30;
+// Number of days listed in the daily totals.
+const DAILY_TOTALS_COUNT: i64 = 14;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    pub path: PathBuf,
+    #[serde(with = "simple_date_format")]
+    pub start: DateTime<Local>,
+    #[serde(with = "simple_date_format")]
+    pub end: DateTime<Local>,
+    // Time spent reading, in seconds.
+    pub duration: u64,
+    pub pages_turned: usize,
+    pub start_location: f64,
+    pub end_location: f64,
+    // Distance, in locations, covered by turning pages forward.
+    pub covered: f64,
+}
+
+// Follows the reading of a book, from its opening.
+#[derive(Debug, Clone)]
+pub struct SessionTracker {
+    start: DateTime<Local>,
+    last_activity: DateTime<Local>,
+    duration: i64,
+    pages_turned: usize,
+    start_location: f64,
+    location: f64,
+    covered: f64,
+}
+
+impl SessionTracker {
+    pub fn new(location: f64) -> SessionTracker {
+        let now = Local::now();
+        SessionTracker {
+            start: now,
+            last_activity: now,
+            duration: 0,
+            pages_turned: 0,
+            start_location: location,
+            location,
+            covered: 0.0,
+        }
+    }
+
+    // Records a change of location.
+    pub fn update(&mut self, location: f64) {
+        let now = Local::now();
+        self.duration += now.signed_duration_since(self.last_activity)
+                            .num_seconds().max(0).min(MAX_PAGE_DURATION);
+        self.last_activity = now;
+        self.location = location;
+    }
+
+    // Records a page turn between two neighboring locations.
+    pub fn turn_page(&mut self, from: f64, to: f64) {
+        self.pages_turned += 1;
+        if to > from {
+            self.covered += to - from;
+        }
+        self.update(to);
+    }
+
+    pub fn finish(mut self, path: &Path) -> Option<Session> {
+        let location = self.location;
+        self.update(location);
+        if (self.duration as u64) < MIN_SESSION_DURATION {
+            return None;
+        }
+        Some(Session {
+            path: path.to_path_buf(),
+            start: self.start,
+            end: self.last_activity,
+            duration: self.duration as u64,
+            pages_turned: self.pages_turned,
+            start_location: self.start_location,
+            end_location: self.location,
+            covered: self.covered,
+        })
+    }
+}
+
+impl ReadingStatistics {
+    pub fn add(&mut self, session: &Session) {
+        self.time += session.duration;
+        self.sessions += 1;
+        self.pages_turned += session.pages_turned;
+        self.covered += session.covered;
+    }
+}
+
+pub fn load_sessions(context: &Context) -> Vec<Session> {
+    let path = context.settings.library_path.join(STATISTICS_NAME);
+    if !path.exists() {
+        return Vec::new();
+    }
+    load_json(&path).map_err(|e| eprintln!("Can't load statistics: {}", e))
+                    .unwrap_or_default()
+}
+
+pub fn save_session(session: &Session, context: &Context) -> Result<(), Error> {
+    let path = context.settings.library_path.join(STATISTICS_NAME);
+    let mut sessions = if path.exists() {
+        load_json::<Vec<Session>, _>(&path)?
+    } else {
+        Vec::new()
+    };
+    sessions.push(session.clone());
+    save_json(&sessions, &path)
+}
+
+// Returns the reading time, in seconds, of each day.
+pub fn daily_totals(sessions: &[Session]) -> BTreeMap<NaiveDate, u64> {
+    let mut totals = BTreeMap::new();
+    for s in sessions {
+        *totals.entry(s.start.date().naive_local()).or_insert(0) += s.duration;
+    }
+    totals
+}
+
+// Returns the current and the longest streaks of consecutive reading days.
+// The current streak isn't broken until the end of the given day.
+pub fn streaks(totals: &BTreeMap<NaiveDate, u64>, today: NaiveDate) -> (usize, usize) {
+    let mut longest = 0;
+    let mut length = 0;
+    let mut previous: Option<NaiveDate> = None;
+
+    for &date in totals.keys() {
+        length = match previous {
+            Some(p) if date.signed_duration_since(p) == Duration::days(1) => length + 1,
+            _ => 1,
+        };
+        longest = longest.max(length);
+        previous = Some(date);
+    }
+
+    let current = match previous {
+        Some(p) if today.signed_duration_since(p) <= Duration::days(1) => length,
+        _ => 0,
+    };
+
+    (current, longest)
+}
+
+pub fn format_duration(seconds: u64) -> String {
+    let minutes = seconds / 60;
+    if minutes == 0 {
+        "< 1 min".to_string()
+    } else if minutes < 60 {
+        format!("{} min", minutes)
+    } else {
+        format!("{} h {:02} min", minutes / 60, minutes % 60)
+    }
+}
+
+// The book being read when the statistics are shown.
+pub struct CurrentBook<'a> {
+    pub info: &'a Info,
+    // The location of the next chapter.
+    pub chapter_end: Option<f64>,
+    // Whether the locations are pages.
+    pub paginated: bool,
+}
+
+pub fn statistics_as_html(sessions: &[Session], metadata: &Metadata, current: Option<&CurrentBook>, today: NaiveDate) -> String {
+    let mut buf = r#"<html>
+                         <head>
+                             <title>Statistics</title>
+                             <link rel="stylesheet" type="text/css" href="css/statistics.css"/>
+                         </head>
+                     <body>"#.to_string();
+
+    let totals = daily_totals(sessions);
+    let (current_streak, longest_streak) = streaks(&totals, today);
+    let total_time: u64 = sessions.iter().map(|s| s.duration).sum();
+
+    buf.push_str("<h1>Summary</h1><ul>");
+    buf.push_str(&format!("<li>Time read: {}.</li>", format_duration(total_time)));
+    buf.push_str(&format!("<li>Today: {}.</li>", format_duration(totals.get(&today).cloned().unwrap_or(0))));
+    buf.push_str(&format!("<li>Current streak: {} day{}.</li>", current_streak, plural(current_streak)));
+    buf.push_str(&format!("<li>Longest streak: {} day{}.</li>", longest_streak, plural(longest_streak)));
+    buf.push_str("</ul>");
+
+    if let Some(current) = current {
+        buf.push_str(&format!("<h1>{}</h1><ul>", escape(&current.info.title())));
+        if let Some(ref r) = current.info.reader {
+            let stats = r.statistics.clone().unwrap_or_default();
+            buf.push_str(&format!("<li>Time read: {}.</li>", format_duration(stats.time)));
+            if let Some(speed) = stats.seconds_per_location() {
+                if current.paginated {
+                    buf.push_str(&format!("<li>Speed: {:.0} pages per hour.</li>", 3600.0 / speed));
+                } else {
+                    buf.push_str(&format!("<li>Speed: {:.1} % per hour.</li>",
+                                          100.0 * 3600.0 / (speed * r.pages_count)));
+                }
+                if let Some(end) = current.chapter_end {
+                    let left = ((end - r.current_page).max(0.0) * speed) as u64;
+                    buf.push_str(&format!("<li>Time left in the chapter: {}.</li>", format_duration(left)));
+                }
+                let left = ((r.pages_count - r.current_page).max(0.0) * speed) as u64;
+                buf.push_str(&format!("<li>Time left in the book: {}.</li>", format_duration(left)));
+            }
+        }
+        buf.push_str("</ul>");
+    }
+
+    buf.push_str("<h1>Daily Totals</h1><ul>");
+    for i in 0..DAILY_TOTALS_COUNT {
+        let date = today - Duration::days(i);
+        if let Some(&time) = totals.get(&date) {
+            buf.push_str(&format!("<li>{}: {}.</li>", date.format("%A, %B %-d"), format_duration(time)));
+        }
+    }
+    buf.push_str("</ul>");
+
+    let mut books: Vec<(String, ReadingStatistics)> = metadata.iter().filter_map(|info| {
+        let info = current.filter(|c| c.info.file.path == info.file.path)
+                          .map_or(info, |c| c.info);
+        info.reader.as_ref()
+            .and_then(|r| r.statistics.clone())
+            .map(|s| (info.title(), s))
+    }).collect();
+    books.sort_by(|a, b| b.1.time.cmp(&a.1.time));
+
+    buf.push_str("<h1>Books</h1><ul>");
+    for (title, stats) in books {
+        buf.push_str(&format!("<li>{}: {} in {} session{}.</li>",
+                              escape(&title), format_duration(stats.time),
+                              stats.sessions, plural(stats.sessions)));
+    }
+    buf.push_str("</ul></body></html>");
+
+    buf
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 { "" } else { "s" }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streaks() {
+        let mut totals = BTreeMap::new();
+        for &(m, d) in &[(1, 30), (1, 31), (2, 1), (2, 5), (2, 6)] {
+            totals.insert(NaiveDate::from_ymd(2018, m, d), 600);
+        }
+        assert_eq!(streaks(&totals, NaiveDate::from_ymd(2018, 2, 7)), (2, 3));
+        assert_eq!(streaks(&totals, NaiveDate::from_ymd(2018, 2, 8)), (0, 3));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(59), "< 1 min");
+        assert_eq!(format_duration(3900), "1 h 05 min");
+    }
+}
@@ -0,0 +1,37 @@
+use view::Event;
+use view::search_results::{Results, ResultItem};
+use geom::Rectangle;
+
+#[derive(Debug, Clone)]
+pub struct BookmarkItem {
+    // Index of the bookmark within the bookmarks of the book.
+    pub index: usize,
+    // The name of the bookmark or the title of its chapter.
+    pub heading: String,
+    pub page: String,
+    pub excerpt: String,
+}
+
+impl ResultItem for BookmarkItem {
+    fn heading(&self) -> &str {
+        &self.heading
+    }
+
+    fn label(&self) -> &str {
+        &self.page
+    }
+
+    fn text(&self) -> &str {
+        &self.excerpt
+    }
+
+    fn tap_event(&self) -> Event {
+        Event::SelectBookmark(self.index)
+    }
+
+    fn hold_event(&self, rect: Rectangle) -> Option<Event> {
+        Some(Event::ToggleBookmarkMenu(rect, self.index))
+    }
+}
+
+pub type Bookmarks = Results<BookmarkItem>;
@@ -35,8 +35,6 @@ pub fn overlapping_rectangle(view: &View) -> Rectangle {
 }
 
 pub fn toggle_main_menu(view: &mut View, rect: Rectangle, enable: Option<bool>, hub: &Hub, context: &mut Context) {
-    let fonts = &mut context.fonts;
-
     if let Some(index) = locate_by_id(view, ViewId::MainMenu) {
         if let Some(true) = enable {
             return;
@@ -47,6 +45,11 @@ pub fn toggle_main_menu(view: &mut View, rect: Rectangle, enable: Option<bool>,
         if let Some(false) = enable {
             return;
         }
+        let rotation = context.display.rotation;
+        let orientations = ["Portrait", "Landscape", "Inverted Portrait", "Inverted Landscape"];
+        let rotate = orientations.iter().enumerate().map(|(n, name)| {
+            EntryKind::RadioButton(name.to_string(), EntryId::Rotate(n as i8), n as i8 == rotation)
+        }).collect::<Vec<EntryKind>>();
         let mut entries = vec![EntryKind::CheckBox("Invert Colors".to_string(),
                                                    EntryId::ToggleInverted,
                                                    context.inverted),
@@ -56,9 +59,12 @@ pub fn toggle_main_menu(view: &mut View, rect: Rectangle, enable: Option<bool>,
                                EntryKind::CheckBox("Enable WiFi".to_string(),
                                                    EntryId::ToggleWifi,
                                                    context.settings.wifi),
+                               EntryKind::SubMenu("Rotate".to_string(), rotate),
                                EntryKind::Separator,
                                EntryKind::Command("Take Screenshot".to_string(),
                                                   EntryId::TakeScreenshot),
+                               EntryKind::Command("Statistics".to_string(),
+                                                  EntryId::ShowStatistics),
                                EntryKind::Separator];
         if env::var("PLATO_STANDALONE").is_ok() {
             entries.extend_from_slice(&[EntryKind::Command("Start Nickel".to_string(),
@@ -68,15 +74,13 @@ pub fn toggle_main_menu(view: &mut View, rect: Rectangle, enable: Option<bool>,
         } else {
             entries.push(EntryKind::Command("Quit".to_string(), EntryId::Quit));
         }
-        let main_menu = Menu::new(rect, ViewId::MainMenu, MenuKind::DropDown, entries, fonts);
+        let main_menu = Menu::new(rect, ViewId::MainMenu, MenuKind::DropDown, entries, context.display.dims, &mut context.fonts);
         hub.send(Event::Render(*main_menu.rect(), UpdateMode::Gui)).unwrap();
         view.children_mut().push(Box::new(main_menu) as Box<View>);
     }
 }
 
 pub fn toggle_battery_menu(view: &mut View, rect: Rectangle, enable: Option<bool>, hub: &Hub, context: &mut Context) {
-    let fonts = &mut context.fonts;
-
     if let Some(index) = locate_by_id(view, ViewId::BatteryMenu) {
         if let Some(true) = enable {
             return;
@@ -94,15 +98,13 @@ pub fn toggle_battery_menu(view: &mut View, rect: Rectangle, enable: Option<bool
             _ => "Unknown".to_string(),
         };
         let entries = vec![EntryKind::Message(text)];
-        let battery_menu = Menu::new(rect, ViewId::BatteryMenu, MenuKind::DropDown, entries, fonts);
+        let battery_menu = Menu::new(rect, ViewId::BatteryMenu, MenuKind::DropDown, entries, context.display.dims, &mut context.fonts);
         hub.send(Event::Render(*battery_menu.rect(), UpdateMode::Gui)).unwrap();
         view.children_mut().push(Box::new(battery_menu) as Box<View>);
     }
 }
 
 pub fn toggle_clock_menu(view: &mut View, rect: Rectangle, enable: Option<bool>, hub: &Hub, context: &mut Context) {
-    let fonts = &mut context.fonts;
-
     if let Some(index) = locate_by_id(view, ViewId::ClockMenu) {
         if let Some(true) = enable {
             return;
@@ -115,7 +117,7 @@ pub fn toggle_clock_menu(view: &mut View, rect: Rectangle, enable: Option<bool>,
         }
         let text = Local::now().format("%A, %B %-d, %Y").to_string();
         let entries = vec![EntryKind::Message(text)];
-        let clock_menu = Menu::new(rect, ViewId::ClockMenu, MenuKind::DropDown, entries, fonts);
+        let clock_menu = Menu::new(rect, ViewId::ClockMenu, MenuKind::DropDown, entries, context.display.dims, &mut context.fonts);
         hub.send(Event::Render(*clock_menu.rect(), UpdateMode::Gui)).unwrap();
         view.children_mut().push(Box::new(clock_menu) as Box<View>);
     }
@@ -24,10 +24,10 @@ pub struct Confirmation {
 }
 
 impl Confirmation {
-    pub fn new(id: ViewId, event: Event, text: String, fonts: &mut Fonts) -> Confirmation {
+    pub fn new(id: ViewId, event: Event, text: String, dims: (u32, u32), fonts: &mut Fonts) -> Confirmation {
         let mut children = Vec::new();
         let dpi = CURRENT_DEVICE.dpi;
-        let (width, height) = CURRENT_DEVICE.dims;
+        let (width, height) = dims;
 
         let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
         let x_height = font.x_heights.0 as i32;
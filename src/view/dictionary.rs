@@ -0,0 +1,457 @@
+use std::sync::mpsc::TryRecvError;
+use device::{CURRENT_DEVICE, BAR_SIZES};
+use framebuffer::{Framebuffer, UpdateMode, Pixmap};
+use geom::{Rectangle, Point, CornerSpec, BorderSpec, Dir, CycleDir};
+use font::{Fonts, font_from_style, NORMAL_STYLE};
+use super::{View, Event, Hub, Bus, ViewId, EntryId, EntryKind, Align};
+use super::{THICKNESS_MEDIUM, THICKNESS_LARGE, BORDER_RADIUS_MEDIUM};
+use super::label::Label;
+use super::icon::Icon;
+use super::filler::Filler;
+use super::menu::{Menu, MenuKind};
+use super::common::locate_by_id;
+use gesture::GestureEvent;
+use document::{Document, Location};
+use document::pdf::{PdfOpener, PdfDocument};
+use dictionary::{Definition, Dictionaries, DICTIONARIES_DIRNAME, spawn_loader, lemmas, lookup};
+use color::{BLACK, WHITE};
+use unit::scale_by_dpi;
+use app::Context;
+
+const DICTIONARY_CSS_PATH: &str = "css/dictionary.css";
+
+pub struct DictionaryWindow {
+    rect: Rectangle,
+    children: Vec<Box<View>>,
+    body: Rectangle,
+    word: String,
+    language: String,
+    index: usize,
+    // The definitions found in each dictionary of the current language.
+    definitions: Vec<Vec<Definition>>,
+    doc: Option<PdfDocument>,
+    current_page: usize,
+    pages_count: usize,
+    pixmap: Option<Pixmap>,
+}
+
+impl DictionaryWindow {
+    // *origin* is the position of the word on the screen: the window is placed in the opposite half.
+    pub fn new(word: &str, language: &str, origin: Point, hub: &Hub, context: &mut Context) -> DictionaryWindow {
+        let mut children = Vec::new();
+        let dpi = CURRENT_DEVICE.dpi;
+        let (width, height) = context.display.dims;
+        let &(small_height, _) = BAR_SIZES.get(&(CURRENT_DEVICE.dims.1, dpi)).unwrap();
+        let thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as i32;
+        let sep_thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+        let border_radius = scale_by_dpi(BORDER_RADIUS_MEDIUM, dpi) as i32;
+
+        let padding = {
+            let font = font_from_style(&mut context.fonts, &NORMAL_STYLE, dpi);
+            font.em() as i32
+        };
+
+        let window_width = width as i32 - 2 * padding;
+        let window_height = height as i32 / 2 - padding;
+
+        let dx = (width as i32 - window_width) / 2;
+        let dy = if origin.y < height as i32 / 2 {
+            height as i32 - window_height - padding
+        } else {
+            padding
+        };
+
+        let rect = rect![dx, dy, dx + window_width, dy + window_height];
+
+        let menu_rect = rect![rect.min.x + thickness,
+                              rect.min.y + thickness,
+                              rect.min.x + small_height as i32,
+                              rect.min.y + small_height as i32];
+        let menu_icon = Icon::new("menu",
+                                  menu_rect,
+                                  Event::ToggleNear(ViewId::DictionaryMenu, menu_rect))
+                             .corners(Some(CornerSpec::Uniform(border_radius - thickness)));
+        children.push(Box::new(menu_icon) as Box<View>);
+
+        let label = Label::new(rect![rect.min.x + small_height as i32,
+                                     rect.min.y + thickness,
+                                     rect.max.x - small_height as i32,
+                                     rect.min.y + small_height as i32],
+                               String::new(),
+                               Align::Center)
+                          .event(Some(Event::ToggleNear(ViewId::DictionaryMenu, menu_rect)));
+        children.push(Box::new(label) as Box<View>);
+
+        let close_icon = Icon::new("close",
+                                   rect![rect.max.x - small_height as i32,
+                                         rect.min.y + thickness,
+                                         rect.max.x - thickness,
+                                         rect.min.y + small_height as i32],
+                                   Event::Close(ViewId::Dictionary))
+                              .corners(Some(CornerSpec::Uniform(border_radius - thickness)));
+        children.push(Box::new(close_icon) as Box<View>);
+
+        let separator = Filler::new(rect![rect.min.x + thickness,
+                                          rect.min.y + small_height as i32,
+                                          rect.max.x - thickness,
+                                          rect.min.y + small_height as i32 + sep_thickness],
+                                    BLACK);
+        children.push(Box::new(separator) as Box<View>);
+
+        let separator = Filler::new(rect![rect.min.x + thickness,
+                                          rect.max.y - small_height as i32 - sep_thickness,
+                                          rect.max.x - thickness,
+                                          rect.max.y - small_height as i32],
+                                    BLACK);
+        children.push(Box::new(separator) as Box<View>);
+
+        let footer = Label::new(rect![rect.min.x + small_height as i32,
+                                      rect.max.y - small_height as i32,
+                                      rect.max.x - small_height as i32,
+                                      rect.max.y - thickness],
+                                String::new(),
+                                Align::Center);
+        children.push(Box::new(footer) as Box<View>);
+
+        let body = rect![rect.min.x + padding,
+                         rect.min.y + small_height as i32 + sep_thickness + padding / 2,
+                         rect.max.x - padding,
+                         rect.max.y - small_height as i32 - sep_thickness - padding / 2];
+
+        receive_dictionaries(context);
+
+        let mut window = DictionaryWindow {
+            rect,
+            children,
+            body,
+            word: lemmas(word).into_iter().next().unwrap_or_default(),
+            language: String::new(),
+            index: 0,
+            definitions: Vec::new(),
+            doc: None,
+            current_page: 0,
+            pages_count: 0,
+            pixmap: None,
+        };
+
+        // Prefer the language of the document, then the first language with results.
+        let mut languages: Vec<String> = context.dictionaries.as_ref()
+                                                .map(|d| d.keys().cloned().collect())
+                                                .unwrap_or_default();
+        let language = language.to_lowercase();
+        if let Some(index) = languages.iter().position(|l| {
+            let l = l.to_lowercase();
+            !l.is_empty() && !language.is_empty() &&
+            (l.starts_with(&language) || language.starts_with(&l))
+        }) {
+            let preferred = languages.remove(index);
+            languages.insert(0, preferred);
+        }
+
+        for lang in &languages {
+            window.set_language(lang, context);
+            if window.definitions.iter().any(|d| !d.is_empty()) {
+                break;
+            }
+        }
+
+        if window.definitions.iter().all(|d| d.is_empty()) {
+            if let Some(lang) = languages.first() {
+                window.set_language(lang, context);
+            }
+        }
+
+        window.update(hub, context);
+        window
+    }
+
+    fn set_language(&mut self, language: &str, context: &mut Context) {
+        let word = &self.word;
+        self.language = language.to_string();
+        self.definitions = context.dictionaries.as_mut()
+                                  .and_then(|d| d.get_mut(language))
+                                  .map(|dictionaries| {
+                                      dictionaries.iter_mut()
+                                                  .map(|d| lookup(d.as_mut(), word))
+                                                  .collect()
+                                  }).unwrap_or_default();
+        self.index = self.definitions.iter().position(|d| !d.is_empty()).unwrap_or(0);
+    }
+
+    fn dictionary_name(&self, context: &Context) -> Option<String> {
+        context.dictionaries.as_ref()
+               .and_then(|d| d.get(&self.language))
+               .and_then(|d| d.get(self.index))
+               .map(|d| d.name().to_string())
+    }
+
+    // Renders the definitions of the current dictionary as an HTML document.
+    fn update(&mut self, hub: &Hub, context: &mut Context) {
+        let mut html = format!("<html><head><title>{}</title></head><body>",
+                               escape(&self.word));
+
+        match self.definitions.get(self.index) {
+            Some(definitions) if !definitions.is_empty() => {
+                for definition in definitions {
+                    html.push_str(&format!("<h2 class=\"headword\">{}</h2><div class=\"definition\">{}</div>",
+                                           escape(&definition.headword), definition.content));
+                }
+            },
+            Some(..) => {
+                html.push_str(&format!("<p class=\"message\">No definitions found for <em>{}</em>.</p>",
+                                       escape(&self.word)));
+            },
+            None if context.dictionaries.is_none() => {
+                html.push_str("<p class=\"message\">The dictionaries are still being loaded.</p>");
+            },
+            None => {
+                html.push_str(&format!("<p class=\"message\">No dictionaries found in <em>{}</em>.</p>",
+                                       DICTIONARIES_DIRNAME));
+            },
+        }
+
+        html.push_str("</body></html>");
+
+        self.doc = PdfOpener::new().and_then(|mut opener| {
+            opener.set_user_css(DICTIONARY_CSS_PATH)
+                  .map_err(|e| eprintln!("Can't set user style sheet: {}", e)).ok();
+            opener.open_memory("html", html.as_bytes())
+        });
+
+        if let Some(ref mut doc) = self.doc {
+            doc.layout(self.body.width(), self.body.height(),
+                       context.settings.reader.font_size, CURRENT_DEVICE.dpi);
+            self.pages_count = doc.pages_count() as usize;
+        } else {
+            self.pages_count = 0;
+        }
+
+        let word = self.word.clone();
+        if let Some(label) = self.child_mut(1).downcast_mut::<Label>() {
+            label.update(word, hub);
+        }
+
+        self.go_to_page(0, hub, context);
+    }
+
+    fn go_to_page(&mut self, index: usize, hub: &Hub, context: &Context) {
+        if self.pages_count > 0 && index >= self.pages_count {
+            return;
+        }
+
+        self.current_page = index;
+        self.pixmap = self.doc.as_mut().and_then(|doc| {
+            doc.pixmap(Location::Exact(index as f64), 1.0).map(|(pixmap, _)| pixmap)
+        });
+
+        let mut text = self.dictionary_name(context).unwrap_or_default();
+        if self.pages_count > 1 {
+            text = format!("{} — {}/{}", text, self.current_page + 1, self.pages_count);
+        }
+
+        if let Some(label) = self.child_mut(5).downcast_mut::<Label>() {
+            label.update(text, hub);
+        }
+
+        hub.send(Event::Render(self.rect, UpdateMode::Gui)).unwrap();
+    }
+
+    fn go_to_neighbor(&mut self, dir: CycleDir, hub: &Hub, context: &Context) {
+        match dir {
+            CycleDir::Next if self.current_page + 1 < self.pages_count => {
+                let index = self.current_page + 1;
+                self.go_to_page(index, hub, context);
+            },
+            CycleDir::Previous if self.current_page > 0 => {
+                let index = self.current_page - 1;
+                self.go_to_page(index, hub, context);
+            },
+            _ => (),
+        }
+    }
+
+    fn toggle_dictionary_menu(&mut self, rect: Rectangle, enable: Option<bool>, hub: &Hub, context: &mut Context) {
+        if let Some(index) = locate_by_id(self, ViewId::DictionaryMenu) {
+            if let Some(true) = enable {
+                return;
+            }
+
+            hub.send(Event::Expose(*self.child(index).rect(), UpdateMode::Gui)).unwrap();
+            self.children.remove(index);
+        } else {
+            if let Some(false) = enable {
+                return;
+            }
+
+            let mut entries = Vec::new();
+
+            if let Some(dictionaries) = context.dictionaries.as_ref() {
+                if let Some(list) = dictionaries.get(&self.language) {
+                    for (index, dictionary) in list.iter().enumerate() {
+                        let count = self.definitions.get(index).map_or(0, |d| d.len());
+                        let name = if count > 0 {
+                            format!("{} ({})", dictionary.name(), count)
+                        } else {
+                            dictionary.name().to_string()
+                        };
+                        entries.push(EntryKind::RadioButton(name,
+                                                            EntryId::SetDictionary(index),
+                                                            index == self.index));
+                    }
+                }
+
+                if dictionaries.len() > 1 {
+                    let languages = dictionaries.keys().map(|lang| {
+                        EntryKind::RadioButton(language_label(lang),
+                                               EntryId::SetDictionaryLanguage(lang.clone()),
+                                               *lang == self.language)
+                    }).collect();
+                    if !entries.is_empty() {
+                        entries.push(EntryKind::Separator);
+                    }
+                    entries.push(EntryKind::SubMenu("Language".to_string(), languages));
+                }
+            }
+
+            if entries.is_empty() {
+                return;
+            }
+
+            let dictionary_menu = Menu::new(rect, ViewId::DictionaryMenu, MenuKind::DropDown, entries, context.display.dims, &mut context.fonts);
+            hub.send(Event::Render(*dictionary_menu.rect(), UpdateMode::Gui)).unwrap();
+            self.children.push(Box::new(dictionary_menu) as Box<View>);
+        }
+    }
+}
+
+// Takes the dictionaries from the background loader, if it's done.
+fn receive_dictionaries(context: &mut Context) {
+    if context.dictionaries.is_some() {
+        return;
+    }
+
+    let result = match context.dictionaries_loader.as_ref() {
+        Some(loader) => loader.try_recv(),
+        None => {
+            context.dictionaries_loader = Some(spawn_loader(DICTIONARIES_DIRNAME));
+            return;
+        },
+    };
+
+    match result {
+        Ok(dictionaries) => context.dictionaries = Some(dictionaries),
+        Err(TryRecvError::Empty) => return,
+        Err(TryRecvError::Disconnected) => {
+            eprintln!("Can't load the dictionaries.");
+            context.dictionaries = Some(Dictionaries::new());
+        },
+    }
+
+    context.dictionaries_loader = None;
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn language_label(language: &str) -> String {
+    if language.is_empty() {
+        "Other".to_string()
+    } else {
+        language.to_string()
+    }
+}
+
+impl View for DictionaryWindow {
+    fn handle_event(&mut self, evt: &Event, hub: &Hub, _bus: &mut Bus, context: &mut Context) -> bool {
+        match *evt {
+            Event::Gesture(GestureEvent::Swipe { dir, start, .. }) if self.rect.includes(start) => {
+                match dir {
+                    Dir::North | Dir::West => self.go_to_neighbor(CycleDir::Next, hub, context),
+                    Dir::South | Dir::East => self.go_to_neighbor(CycleDir::Previous, hub, context),
+                }
+                true
+            },
+            Event::Gesture(GestureEvent::Tap(center)) if self.body.includes(center) => {
+                if center.x < self.body.min.x + self.body.width() as i32 / 2 {
+                    self.go_to_neighbor(CycleDir::Previous, hub, context);
+                } else {
+                    self.go_to_neighbor(CycleDir::Next, hub, context);
+                }
+                true
+            },
+            Event::Gesture(GestureEvent::Tap(center)) if !self.rect.includes(center) => {
+                hub.send(Event::Close(ViewId::Dictionary)).unwrap();
+                true
+            },
+            Event::Gesture(..) => true,
+            Event::ToggleNear(ViewId::DictionaryMenu, rect) => {
+                self.toggle_dictionary_menu(rect, None, hub, context);
+                true
+            },
+            Event::Close(ViewId::DictionaryMenu) => {
+                self.toggle_dictionary_menu(Rectangle::default(), Some(false), hub, context);
+                true
+            },
+            Event::Select(EntryId::SetDictionary(index)) => {
+                if index != self.index {
+                    self.index = index;
+                    self.update(hub, context);
+                }
+                true
+            },
+            Event::Select(EntryId::SetDictionaryLanguage(ref language)) => {
+                if *language != self.language {
+                    self.set_language(language, context);
+                    self.update(hub, context);
+                }
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn render(&self, fb: &mut Framebuffer, _fonts: &mut Fonts) {
+        let dpi = CURRENT_DEVICE.dpi;
+
+        let border_radius = scale_by_dpi(BORDER_RADIUS_MEDIUM, dpi) as i32;
+        let border_thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as u16;
+
+        fb.draw_rounded_rectangle_with_border(&self.rect,
+                                              &CornerSpec::Uniform(border_radius),
+                                              &BorderSpec { thickness: border_thickness,
+                                                            color: BLACK },
+                                              &WHITE);
+
+        if let Some(ref pixmap) = self.pixmap {
+            let frame = rect![0, 0,
+                              (pixmap.width as i32).min(self.body.width() as i32),
+                              (pixmap.height as i32).min(self.body.height() as i32)];
+            fb.draw_framed_pixmap(pixmap, &frame, &self.body.min);
+        }
+    }
+
+    fn is_background(&self) -> bool {
+        true
+    }
+
+    fn rect(&self) -> &Rectangle {
+        &self.rect
+    }
+
+    fn rect_mut(&mut self) -> &mut Rectangle {
+        &mut self.rect
+    }
+
+    fn children(&self) -> &Vec<Box<View>> {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> &mut Vec<Box<View>> {
+        &mut self.children
+    }
+
+    fn id(&self) -> Option<ViewId> {
+        Some(ViewId::Dictionary)
+    }
+}
@@ -34,8 +34,8 @@ impl FrontlightWindow {
         let presets = &context.settings.frontlight_presets;
         let mut children = Vec::new();
         let dpi = CURRENT_DEVICE.dpi;
-        let (width, height) = CURRENT_DEVICE.dims;
-        let &(small_height, _) = BAR_SIZES.get(&(height, dpi)).unwrap();
+        let (width, height) = context.display.dims;
+        let &(small_height, _) = BAR_SIZES.get(&(CURRENT_DEVICE.dims.1, dpi)).unwrap();
         let thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as i32;
         let border_radius = scale_by_dpi(BORDER_RADIUS_MEDIUM, dpi) as i32;
 
@@ -9,6 +9,7 @@ use metadata::{Info, Status};
 use settings::SecondColumn;
 use unit::scale_by_dpi;
 use document::HumanSize;
+use statistics::format_duration;
 use font::{Fonts, font_from_style};
 use geom::{Rectangle, CornerSpec, BorderSpec, halves};
 use app::Context;
@@ -43,7 +44,7 @@ impl View for Book {
             Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => {
                 self.active = true;
                 hub.send(Event::Render(self.rect, UpdateMode::Gui)).unwrap();
-                hub.send(Event::Open(Box::new(self.info.clone()))).unwrap();
+                hub.send(Event::Open(Box::new(self.info.clone()), None)).unwrap();
                 true
             },
             Event::Gesture(GestureEvent::HoldFinger(center)) if self.rect.includes(center) => {
@@ -51,14 +52,13 @@ impl View for Book {
                 bus.push_back(Event::ToggleBookMenu(Rectangle::from_point(pt), self.index));
                 true
             },
-            Event::Invalid(ref info) => {
+            Event::Invalid(ref info, _) => {
                 if self.info.file.path == info.file.path {
                     self.active = false;
                     hub.send(Event::Render(self.rect, UpdateMode::Gui)).unwrap();
-                    true
-                } else {
-                    false
                 }
+                // Let the home view ask for the password of protected books.
+                false
             },
             _ => false,
         }
@@ -142,8 +142,23 @@ impl View for Book {
                 let progress_height = scale_by_dpi(PROGRESS_HEIGHT, dpi) as i32;
                 let thickness = scale_by_dpi(THICKNESS_SMALL, dpi) as u16;
                 let (small_radius, big_radius) = halves(progress_height);
-                let center = pt!(self.rect.min.x + first_width + second_width / 2,
-                                 self.rect.min.y + self.rect.height() as i32 / 2);
+                let time = self.info.reader.as_ref()
+                               .and_then(|r| r.statistics.as_ref())
+                               .map(|s| s.time).filter(|&t| t > 0);
+                // When the time read is known, it's shown below the progress bar.
+                let center_y = if time.is_some() {
+                    self.rect.min.y + baseline + x_height / 2
+                } else {
+                    self.rect.min.y + self.rect.height() as i32 / 2
+                };
+                let center = pt!(self.rect.min.x + first_width + second_width / 2, center_y);
+                if let Some(time) = time {
+                    let font = font_from_style(fonts, &MD_SIZE, dpi);
+                    let plan = font.plan(&format_duration(time), None, None);
+                    let pt = pt!(center.x - plan.width as i32 / 2,
+                                 self.rect.max.y - baseline);
+                    font.render(fb, scheme[1], &plan, pt);
+                }
                 match self.info.status() {
                     Status::New | Status::Finished => {
                         let color = if self.info.reader.is_none() { WHITE } else { READING_PROGRESS };
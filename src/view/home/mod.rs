@@ -10,15 +10,22 @@ mod book;
 mod bottom_bar;
 
 use std::f32;
-use std::sync::mpsc;
+use std::thread;
+use std::sync::{Arc, mpsc};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering as AtomicOrdering;
 use std::path::PathBuf;
 use std::collections::{BTreeSet, VecDeque};
 use glob::glob;
+use chrono::Local;
 use regex::Regex;
 use fnv::FnvHashSet;
 use failure::Error;
-use metadata::{Metadata, SortMethod, sort, make_query};
+use metadata::{Info, Metadata, SortMethod, sort, make_query};
 use settings::SecondColumn;
+use document::DocumentOpener;
+use keystore::forget_password;
+use statistics::{statistics_as_html, load_sessions};
 use framebuffer::{Framebuffer, UpdateMode};
 use view::{View, Event, Hub, Bus, ViewId, EntryId, EntryKind, THICKNESS_MEDIUM};
 use view::filler::Filler;
@@ -33,12 +40,14 @@ use view::menu::{Menu, MenuKind};
 use view::menu_entry::MenuEntry;
 use view::search_bar::SearchBar;
 use view::notification::Notification;
+use view::search_results::SearchResults;
 use self::bottom_bar::BottomBar;
 use device::{CURRENT_DEVICE, BAR_SIZES};
 use symbolic_path::SymbolicPath;
 use helpers::{load_json, save_json};
 use unit::scale_by_dpi;
 use trash::{trash, untrash};
+use fulltext::{self, FULLTEXT_PREFIX};
 use app::Context;
 use color::BLACK;
 use geom::{Rectangle, CycleDir, halves, small_half};
@@ -55,6 +64,7 @@ pub struct Home {
     focus: Option<ViewId>,
     query: Option<Regex>,
     target_path: Option<PathBuf>,
+    locked_book: Option<(Box<Info>, Option<f64>)>,
     summary_size: u8,
     sort_method: SortMethod,
     reverse_order: bool,
@@ -74,10 +84,6 @@ struct HistoryEntry {
 impl Home {
     pub fn new(rect: Rectangle, hub: &Hub, context: &mut Context) -> Result<Home, Error> {
         let dpi = CURRENT_DEVICE.dpi;
-        let mut children = Vec::new();
-
-        let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
-        let (small_thickness, big_thickness) = halves(thickness);
         let (_, height) = CURRENT_DEVICE.dims;
         let &(small_height, big_height) = BAR_SIZES.get(&(height, dpi)).unwrap();
 
@@ -95,67 +101,27 @@ impl Home {
         let selected_categories = BTreeSet::default();
         let negated_categories = BTreeSet::default();
 
-        let max_lines = ((height - 3 * small_height) / big_height) as usize;
+        let max_lines = ((rect.height() - 3 * small_height) / big_height) as usize;
         let summary_size = context.settings.home.summary_size.max(1).min(max_lines as u8);
         let max_lines = max_lines - summary_size as usize + 1;
         let count = visible_books.len();
         let pages_count = (visible_books.len() as f32 / max_lines as f32).ceil() as usize;
         let current_page = 0;
 
-        let top_bar = TopBar::new(rect![rect.min.x, rect.min.y,
-                                        rect.max.x, rect.min.y + small_height as i32 - small_thickness],
-                                  sort_method,
-                                  context);
-        children.push(Box::new(top_bar) as Box<View>);
-
-        let separator = Filler::new(rect![rect.min.x, rect.min.y + small_height as i32 - small_thickness,
-                                          rect.max.x, rect.min.y + small_height as i32 + big_thickness],
-                                    BLACK);
-        children.push(Box::new(separator) as Box<View>);
-
-        let summary_height = small_height as i32 - thickness +
-                             (summary_size - 1) as i32 * big_height as i32;
-        let s_min_y = rect.min.y + small_height as i32 + big_thickness;
-        let s_max_y = s_min_y + summary_height;
-
-        let mut summary = Summary::new(rect![rect.min.x, s_min_y,
-                                             rect.max.x, s_max_y]);
+        let mut children = build_children(rect, summary_size, sort_method,
+                                          current_page, pages_count, count, context);
 
         let (tx, _rx) = mpsc::channel();
 
-        summary.update(&visible_categories, &selected_categories,
-                       &negated_categories, false, &tx, &mut context.fonts);
-
-        children.push(Box::new(summary) as Box<View>);
-
-        let separator = Filler::new(rect![rect.min.x, s_max_y,
-                                          rect.max.x, s_max_y + thickness],
-                                    BLACK);
-        children.push(Box::new(separator) as Box<View>);
-
-        let mut shelf = Shelf::new(rect![rect.min.x, s_max_y + thickness,
-                                         rect.max.x, rect.max.y - small_height as i32 - small_thickness],
-                                   context.settings.home.second_column);
+        children[2].as_mut().downcast_mut::<Summary>().unwrap()
+                   .update(&visible_categories, &selected_categories,
+                           &negated_categories, false, &tx, &mut context.fonts);
 
         let index_lower = current_page * max_lines;
         let index_upper = (index_lower + max_lines).min(visible_books.len());
 
-        shelf.update(&visible_books[index_lower..index_upper], &tx);
-
-        children.push(Box::new(shelf) as Box<View>);
-
-        let separator = Filler::new(rect![rect.min.x, rect.max.y - small_height as i32 - small_thickness,
-                                          rect.max.x, rect.max.y - small_height as i32 + big_thickness],
-                                    BLACK);
-        children.push(Box::new(separator) as Box<View>);
-
-        let bottom_bar = BottomBar::new(rect![rect.min.x, rect.max.y - small_height as i32 + big_thickness,
-                                              rect.max.x, rect.max.y],
-                                        current_page,
-                                        pages_count,
-                                        count,
-                                        false);
-        children.push(Box::new(bottom_bar) as Box<View>);
+        children[4].as_mut().downcast_mut::<Shelf>().unwrap()
+                   .update(&visible_books[index_lower..index_upper], &tx);
 
         hub.send(Event::Render(rect, UpdateMode::Full)).unwrap();
 
@@ -167,6 +133,7 @@ impl Home {
             focus: None,
             query: None,
             target_path: None,
+            locked_book: None,
             summary_size,
             sort_method,
             reverse_order,
@@ -178,6 +145,39 @@ impl Home {
         })
     }
 
+    pub fn resize(&mut self, rect: Rectangle, hub: &Hub, context: &mut Context) {
+        let dpi = CURRENT_DEVICE.dpi;
+        let (_, height) = CURRENT_DEVICE.dims;
+        let &(small_height, big_height) = BAR_SIZES.get(&(height, dpi)).unwrap();
+
+        let search_visible = locate::<SearchBar>(self).is_some();
+        let max_lines = self.child(4).downcast_ref::<Shelf>().unwrap().max_lines;
+
+        let summary_lines = ((rect.height() - 3 * small_height) / big_height) as u8;
+        self.summary_size = self.summary_size.max(1).min(summary_lines);
+
+        let mut children = build_children(rect, self.summary_size, self.sort_method,
+                                          self.current_page, self.pages_count,
+                                          self.visible_books.len(), context);
+
+        // Keep the previous number of lines so that update_shelf can find the current page.
+        children[4].as_mut().downcast_mut::<Shelf>().unwrap().max_lines = max_lines;
+
+        // The menus and the keyboard are dropped, the search bar is restored.
+        self.children = children;
+        self.rect = rect;
+        self.focus = None;
+
+        if search_visible {
+            self.toggle_search_bar(Some(true), false, hub, context);
+        }
+
+        self.update_top_bar(search_visible, hub);
+        self.update_summary(true, hub, &mut context.fonts);
+        self.update_shelf(true, hub);
+        self.update_bottom_bar(hub);
+    }
+
     fn refresh_visibles(&mut self, update: bool, reset_page: bool, hub: &Hub, context: &mut Context) {
         let fonts = &mut context.fonts;
         let metadata = &mut context.metadata;
@@ -588,7 +588,7 @@ impl Home {
         }
     }
 
-    fn toggle_go_to_page(&mut self, enable: Option<bool>, hub: &Hub, fonts: &mut Fonts) {
+    fn toggle_go_to_page(&mut self, enable: Option<bool>, hub: &Hub, context: &mut Context) {
         if let Some(index) = locate_by_id(self, ViewId::GoToPage) {
             if let Some(true) = enable {
                 return;
@@ -596,14 +596,14 @@ impl Home {
             hub.send(Event::Expose(*self.child(index).rect(), UpdateMode::Gui)).unwrap();
             self.children.remove(index);
             if let Some(ViewId::GoToPageInput) = self.focus {
-                self.toggle_keyboard(false, true, Some(ViewId::GoToPageInput), hub, fonts);
+                self.toggle_keyboard(false, true, Some(ViewId::GoToPageInput), hub, &mut context.fonts);
                 self.focus = None;
             }
         } else {
             if let Some(false) = enable {
                 return;
             }
-            let go_to_page = NamedInput::new("Go to page".to_string(), ViewId::GoToPage, ViewId::GoToPageInput, 4, fonts);
+            let go_to_page = NamedInput::new("Go to page".to_string(), ViewId::GoToPage, ViewId::GoToPageInput, 4, context.display.dims, &mut context.fonts);
             hub.send(Event::Render(*go_to_page.rect(), UpdateMode::Gui)).unwrap();
             hub.send(Event::Focus(Some(ViewId::GoToPageInput))).unwrap();
             self.focus = Some(ViewId::GoToPageInput);
@@ -611,7 +611,7 @@ impl Home {
         }
     }
 
-    fn toggle_sort_menu(&mut self, rect: Rectangle, enable: Option<bool>, hub: &Hub, fonts: &mut Fonts) {
+    fn toggle_sort_menu(&mut self, rect: Rectangle, enable: Option<bool>, hub: &Hub, context: &mut Context) {
         if let Some(index) = locate_by_id(self, ViewId::SortMenu) {
             if let Some(true) = enable {
                 return;
@@ -643,7 +643,7 @@ impl Home {
                                EntryKind::Separator,
                                EntryKind::CheckBox("Reverse Order".to_string(),
                                                    EntryId::ReverseOrder, self.reverse_order)];
-            let sort_menu = Menu::new(rect, ViewId::SortMenu, MenuKind::DropDown, entries, fonts);
+            let sort_menu = Menu::new(rect, ViewId::SortMenu, MenuKind::DropDown, entries, context.display.dims, &mut context.fonts);
             hub.send(Event::Render(*sort_menu.rect(), UpdateMode::Gui)).unwrap();
             self.children.push(Box::new(sort_menu) as Box<View>);
         }
@@ -655,7 +655,7 @@ impl Home {
         (index_lower + index).min(self.visible_books.len())
     }
 
-    fn toggle_book_menu(&mut self, index: usize, rect: Rectangle, enable: Option<bool>, hub: &Hub, fonts: &mut Fonts) {
+    fn toggle_book_menu(&mut self, index: usize, rect: Rectangle, enable: Option<bool>, hub: &Hub, context: &mut Context) {
         if let Some(index) = locate_by_id(self, ViewId::BookMenu) {
             if let Some(true) = enable {
                 return;
@@ -686,14 +686,13 @@ impl Home {
             entries.push(EntryKind::Separator);
             entries.push(EntryKind::Command("Remove".to_string(), EntryId::Remove(path.clone())));
 
-            let book_menu = Menu::new(rect, ViewId::BookMenu, MenuKind::Contextual, entries, fonts);
+            let book_menu = Menu::new(rect, ViewId::BookMenu, MenuKind::Contextual, entries, context.display.dims, &mut context.fonts);
             hub.send(Event::Render(*book_menu.rect(), UpdateMode::Gui)).unwrap();
             self.children.push(Box::new(book_menu) as Box<View>);
         }
     }
 
     fn toggle_matches_menu(&mut self, rect: Rectangle, enable: Option<bool>, hub: &Hub, context: &mut Context) {
-        let fonts = &mut context.fonts;
         if let Some(index) = locate_by_id(self, ViewId::MatchesMenu) {
             if let Some(true) = enable {
                 return;
@@ -745,7 +744,7 @@ impl Home {
                 entries.push(EntryKind::Command("Undo".to_string(), EntryId::Undo));
             }
 
-            let matches_menu = Menu::new(rect, ViewId::MatchesMenu, MenuKind::DropDown, entries, fonts);
+            let matches_menu = Menu::new(rect, ViewId::MatchesMenu, MenuKind::DropDown, entries, context.display.dims, &mut context.fonts);
             hub.send(Event::Render(*matches_menu.rect(), UpdateMode::Gui)).unwrap();
             self.children.push(Box::new(matches_menu) as Box<View>);
         }
@@ -962,6 +961,43 @@ impl Home {
         hub.send(Event::Render(self.rect, UpdateMode::Gui)).unwrap();
     }
 
+    fn search_library(&mut self, text: &str, hub: &Hub, context: &mut Context) {
+        let query = match make_query(text) {
+            Some(query) => query,
+            None => {
+                let notif = Notification::new(ViewId::InvalidSearchQueryNotif,
+                                              "Invalid search query.".to_string(),
+                                              &mut context.notification_index,
+                                              context.display.dims,
+                                              &mut context.fonts,
+                                              hub);
+                self.children.push(Box::new(notif) as Box<View>);
+                return;
+            },
+        };
+
+        self.toggle_keyboard(false, true, None, hub, &mut context.fonts);
+
+        let running = Arc::new(AtomicBool::new(true));
+        let running2 = Arc::clone(&running);
+        let hub2 = hub.clone();
+        let library_path = context.settings.library_path.clone();
+        let books: Vec<(PathBuf, String)> = context.metadata.iter()
+                                                   .map(|info| (info.file.path.clone(), info.title()))
+                                                   .collect();
+
+        thread::spawn(move || {
+            fulltext::search(&library_path, &books, &query, &running2, &hub2);
+            running2.store(false, AtomicOrdering::Relaxed);
+            hub2.send(Event::EndOfSearch).unwrap();
+        });
+
+        let search_results = SearchResults::new(self.rect, ViewId::SearchResults, text.to_string(),
+                                                "result", false, Some(running));
+        hub.send(Event::Render(*search_results.rect(), UpdateMode::Gui)).unwrap();
+        self.children.push(Box::new(search_results) as Box<View>);
+    }
+
     fn export_matches(&mut self, filename: &str, context: &mut Context) {
         let path = context.settings.library_path.join(format!(".metadata-{}.json", filename));
         save_json(&self.visible_books, path).map_err(|e| {
@@ -969,6 +1005,44 @@ impl Home {
         }).ok();
     }
 
+    // Asks for the password of a book that couldn't be opened because it's protected.
+    fn unlock_book(&mut self, info: &Info, location: Option<f64>, hub: &Hub, context: &mut Context) {
+        let path = context.settings.library_path.join(&info.file.path);
+        let opener = DocumentOpener::new(context.settings.reader.epub_engine);
+
+        if !opener.open(&path).map_or(false, |doc| doc.is_protected()) {
+            return;
+        }
+
+        let typed = context.passwords.remove(&info.file.path).is_some();
+        let stored = forget_password(&info.file.path, context)
+                                    .map_err(|e| eprintln!("Can't forget password: {}", e))
+                                    .unwrap_or(false);
+
+        if typed || stored {
+            let notif = Notification::new(ViewId::WrongPasswordNotif,
+                                          "Wrong password.".to_string(),
+                                          &mut context.notification_index,
+                                          context.display.dims,
+                                          &mut context.fonts,
+                                          hub);
+            self.children.push(Box::new(notif) as Box<View>);
+        }
+
+        self.locked_book = Some((Box::new(info.clone()), location));
+
+        let unlock_document = NamedInput::new("Password".to_string(),
+                                              ViewId::UnlockDocument,
+                                              ViewId::UnlockDocumentInput,
+                                              16,
+                                              context.display.dims,
+                                              &mut context.fonts)
+                                         .hidden(true);
+        hub.send(Event::Render(*unlock_document.rect(), UpdateMode::Gui)).unwrap();
+        hub.send(Event::Focus(Some(ViewId::UnlockDocumentInput))).unwrap();
+        self.children.push(Box::new(unlock_document) as Box<View>);
+    }
+
     fn load_metadata(&mut self, filename: &PathBuf, hub: &Hub, context: &mut Context) {
         let metadata = load_json::<Metadata, _>(context.settings.library_path.join(filename))
                                  .map_err(|e| eprintln!("Can't load metadata: {}", e))
@@ -993,6 +1067,63 @@ impl Home {
 // generic method for updating everything based on the bit field to avoid needlessly updating
 // things multiple times?
 
+// Builds the top bar, the summary, the shelf, the bottom bar and their separators.
+// The summary and the shelf are left empty.
+fn build_children(rect: Rectangle, summary_size: u8, sort_method: SortMethod, current_page: usize,
+                  pages_count: usize, count: usize, context: &mut Context) -> Vec<Box<View>> {
+    let dpi = CURRENT_DEVICE.dpi;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let (small_thickness, big_thickness) = halves(thickness);
+    let (_, height) = CURRENT_DEVICE.dims;
+    let &(small_height, big_height) = BAR_SIZES.get(&(height, dpi)).unwrap();
+    let mut children = Vec::new();
+
+    let top_bar = TopBar::new(rect![rect.min.x, rect.min.y,
+                                    rect.max.x, rect.min.y + small_height as i32 - small_thickness],
+                              sort_method,
+                              context);
+    children.push(Box::new(top_bar) as Box<View>);
+
+    let separator = Filler::new(rect![rect.min.x, rect.min.y + small_height as i32 - small_thickness,
+                                      rect.max.x, rect.min.y + small_height as i32 + big_thickness],
+                                BLACK);
+    children.push(Box::new(separator) as Box<View>);
+
+    let summary_height = small_height as i32 - thickness +
+                         (summary_size - 1) as i32 * big_height as i32;
+    let s_min_y = rect.min.y + small_height as i32 + big_thickness;
+    let s_max_y = s_min_y + summary_height;
+
+    let summary = Summary::new(rect![rect.min.x, s_min_y,
+                                     rect.max.x, s_max_y]);
+    children.push(Box::new(summary) as Box<View>);
+
+    let separator = Filler::new(rect![rect.min.x, s_max_y,
+                                      rect.max.x, s_max_y + thickness],
+                                BLACK);
+    children.push(Box::new(separator) as Box<View>);
+
+    let shelf = Shelf::new(rect![rect.min.x, s_max_y + thickness,
+                                 rect.max.x, rect.max.y - small_height as i32 - small_thickness],
+                           context.settings.home.second_column);
+    children.push(Box::new(shelf) as Box<View>);
+
+    let separator = Filler::new(rect![rect.min.x, rect.max.y - small_height as i32 - small_thickness,
+                                      rect.max.x, rect.max.y - small_height as i32 + big_thickness],
+                                BLACK);
+    children.push(Box::new(separator) as Box<View>);
+
+    let bottom_bar = BottomBar::new(rect![rect.min.x, rect.max.y - small_height as i32 + big_thickness,
+                                          rect.max.x, rect.max.y],
+                                    current_page,
+                                    pages_count,
+                                    count,
+                                    false);
+    children.push(Box::new(bottom_bar) as Box<View>);
+
+    children
+}
+
 impl View for Home {
     fn handle_event(&mut self, evt: &Event, hub: &Hub, _bus: &mut Bus, context: &mut Context) -> bool {
         match *evt {
@@ -1006,7 +1137,7 @@ impl View for Home {
                 true
             },
             Event::Toggle(ViewId::GoToPage) => {
-                self.toggle_go_to_page(None, hub, &mut context.fonts);
+                self.toggle_go_to_page(None, hub, context);
                 true
             },
             Event::Toggle(ViewId::SearchBar) => {
@@ -1014,11 +1145,11 @@ impl View for Home {
                 true
             },
             Event::ToggleNear(ViewId::SortMenu, rect) => {
-                self.toggle_sort_menu(rect, None, hub, &mut context.fonts);
+                self.toggle_sort_menu(rect, None, hub, context);
                 true
             },
             Event::ToggleBookMenu(rect, index) => {
-                self.toggle_book_menu(index, rect, None, hub, &mut context.fonts);
+                self.toggle_book_menu(index, rect, None, hub, context);
                 true
             },
             Event::ToggleNear(ViewId::MainMenu, rect) => {
@@ -1042,7 +1173,7 @@ impl View for Home {
                 true
             },
             Event::Close(ViewId::SortMenu) => {
-                self.toggle_sort_menu(Rectangle::default(), Some(false), hub, &mut context.fonts);
+                self.toggle_sort_menu(Rectangle::default(), Some(false), hub, context);
                 true
             },
             Event::Close(ViewId::MatchesMenu) => {
@@ -1054,7 +1185,7 @@ impl View for Home {
                 true
             },
             Event::Close(ViewId::GoToPage) => {
-                self.toggle_go_to_page(Some(false), hub, &mut context.fonts);
+                self.toggle_go_to_page(Some(false), hub, context);
                 true
             },
             Event::Select(EntryId::Sort(sort_method)) => {
@@ -1066,11 +1197,18 @@ impl View for Home {
                 self.set_reverse_order(next_value, hub, context);
                 true
             },
+            Event::Select(EntryId::ShowStatistics) => {
+                let html = statistics_as_html(&load_sessions(context), &context.metadata,
+                                              None, Local::today().naive_local());
+                hub.send(Event::OpenStatistics(html)).unwrap();
+                true
+            },
             Event::Select(EntryId::ExportMatches) => {
                 let export_as = NamedInput::new("Export as".to_string(),
                                                 ViewId::ExportAs,
                                                 ViewId::ExportAsInput,
                                                 12,
+                                                context.display.dims,
                                                 &mut context.fonts);
                 hub.send(Event::Render(*export_as.rect(), UpdateMode::Gui)).unwrap();
                 hub.send(Event::Focus(Some(ViewId::ExportAsInput))).unwrap();
@@ -1098,6 +1236,7 @@ impl View for Home {
                                                  ViewId::AddCategories,
                                                  ViewId::AddCategoriesInput,
                                                  21,
+                                                 context.display.dims,
                                                  &mut context.fonts);
                 hub.send(Event::Render(*add_categs.rect(), UpdateMode::Gui)).unwrap();
                 hub.send(Event::Focus(Some(ViewId::AddCategoriesInput))).unwrap();
@@ -1121,6 +1260,18 @@ impl View for Home {
                 self.update_second_column(hub, context);
                 true
             },
+            Event::Invalid(ref info, location) => {
+                self.unlock_book(info, location, hub, context);
+                true
+            },
+            Event::Submit(ViewId::UnlockDocumentInput, ref text) => {
+                self.toggle_keyboard(false, true, None, hub, &mut context.fonts);
+                if let Some((info, location)) = self.locked_book.take() {
+                    context.passwords.insert(info.file.path.clone(), text.to_string());
+                    hub.send(Event::Open(info, location)).unwrap();
+                }
+                true
+            },
             Event::Submit(ViewId::ExportAsInput, ref text) => {
                 if !text.is_empty() {
                     self.export_matches(text, context);
@@ -1138,6 +1289,10 @@ impl View for Home {
                 self.toggle_keyboard(false, true, None, hub, &mut context.fonts);
                 true
             },
+            Event::Submit(ViewId::SearchInput, ref text) if text.starts_with(FULLTEXT_PREFIX) => {
+                self.search_library(&text[FULLTEXT_PREFIX.len_utf8()..], hub, context);
+                true
+            },
             Event::Submit(ViewId::SearchInput, ref text) => {
                 self.query = make_query(text);
                 if self.query.is_some() {
@@ -1148,6 +1303,7 @@ impl View for Home {
                     let notif = Notification::new(ViewId::InvalidSearchQueryNotif,
                                                   "Invalid search query.".to_string(),
                                                   &mut context.notification_index,
+                                                  context.display.dims,
                                                   &mut context.fonts,
                                                   hub);
                     self.children.push(Box::new(notif) as Box<View>);
@@ -1202,6 +1358,12 @@ impl View for Home {
                 }
                 true
             },
+            Event::SelectHit(ref path, location) => {
+                if let Some(info) = context.metadata.iter().find(|info| info.file.path == *path) {
+                    hub.send(Event::Open(Box::new(info.clone()), Some(location))).unwrap();
+                }
+                true
+            },
             Event::Reseed => {
                 self.reseed(false, hub, context);
                 true
@@ -26,7 +26,7 @@ lazy_static! {
                      "close",  "check_mark-small", "check_mark","check_mark-large",
                      "bullet", "arrow-left", "arrow-right", "double_angle-left", "double_angle-right",
                      "angle-down", "plus", "minus", "crop", "toc", "font_family", "font_size",
-                     "line_height", "margin", "plug"].iter().cloned() {
+                     "line_height", "margin", "plug", "zoom", "reflow"].iter().cloned() {
             let path = dir.join(&format!("{}.svg", name));
             let doc = PdfOpener::new().and_then(|o| o.open(path)).unwrap();
             let pixmap = doc.page(0).and_then(|p| p.pixmap(scale)).unwrap();
@@ -18,6 +18,7 @@ pub struct InputField {
     placeholder: String,
     cursor: usize,
     border: bool,
+    hidden: bool,
     focused: bool,
 }
 
@@ -74,7 +75,6 @@ fn word_boundary(text: &str, index: usize, dir: LinearDir) -> usize {
     }
 }
 
-// TODO: hidden chars (password…)
 impl InputField {
     pub fn new(rect: Rectangle, id: ViewId) -> InputField {
         InputField {
@@ -86,6 +86,7 @@ impl InputField {
             placeholder: "".to_string(),
             cursor: 0,
             border: true,
+            hidden: false,
             focused: false,
         }
     }
@@ -95,15 +96,29 @@ impl InputField {
         self
     }
 
+    // Shows a bullet in place of each character, for passwords.
+    pub fn hidden(mut self, hidden: bool) -> InputField {
+        self.set_hidden(hidden);
+        self
+    }
+
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
+    }
+
     pub fn placeholder(mut self, placeholder: &str) -> InputField {
         self.placeholder = placeholder.to_string();
         self
     }
 
     pub fn text(mut self, text: &str) -> InputField {
+        self.set_text(text);
+        self
+    }
+
+    pub fn set_text(&mut self, text: &str) {
         self.text = text.to_string();
         self.cursor = self.text.len();
-        self
     }
 
     fn char_move(&mut self, dir: LinearDir) {
@@ -239,6 +254,9 @@ impl View for InputField {
         let (mut plan, foreground) = if self.text.is_empty() {
             (font.plan(&self.placeholder, Some(max_width as u32), None),
              TEXT_NORMAL[2])
+        } else if self.hidden {
+            (font.plan(&"•".repeat(self.text.chars().count()), None, None),
+             TEXT_NORMAL[1])
         } else {
             (font.plan(&self.text, None, Some(&["-liga".to_string()])),
             TEXT_NORMAL[1])
@@ -38,11 +38,11 @@ pub enum MenuKind {
 //     C     BOTTOM MENU
 
 impl Menu {
-    pub fn new(target: Rectangle, id: ViewId, kind: MenuKind, mut entries: Vec<EntryKind>, fonts: &mut Fonts) -> Menu {
+    pub fn new(target: Rectangle, id: ViewId, kind: MenuKind, mut entries: Vec<EntryKind>, dims: (u32, u32), fonts: &mut Fonts) -> Menu {
         let mut children = Vec::new();
         let dpi = CURRENT_DEVICE.dpi;
-        let (width, height) = CURRENT_DEVICE.dims;
-        let &(small_height, _) = BAR_SIZES.get(&(height, dpi)).unwrap();
+        let (width, height) = dims;
+        let &(small_height, _) = BAR_SIZES.get(&(CURRENT_DEVICE.dims.1, dpi)).unwrap();
 
         let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
         let border_thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as i32;
@@ -264,7 +264,7 @@ impl View for Menu {
             Event::Gesture(GestureEvent::HoldFinger(center)) if !self.rect.includes(center) => self.root,
             Event::SubMenu(rect, ref entries) => {
                 let menu = Menu::new(rect, ViewId::SubMenu(self.sub_id),
-                                     MenuKind::SubMenu, entries.clone(), &mut context.fonts).root(false);
+                                     MenuKind::SubMenu, entries.clone(), context.display.dims, &mut context.fonts).root(false);
                 hub.send(Event::Render(*menu.rect(), UpdateMode::Gui)).unwrap();
                 self.children.push(Box::new(menu) as Box<View>);
                 self.sub_id = self.sub_id.wrapping_add(1);
@@ -25,6 +25,9 @@ pub mod confirmation;
 pub mod notification;
 pub mod intermission;
 pub mod frontlight;
+pub mod dictionary;
+pub mod search_results;
+pub mod bookmarks;
 pub mod presets_list;
 pub mod preset;
 pub mod menu;
@@ -44,13 +47,14 @@ use std::fmt::{self, Debug};
 use fnv::FnvHashMap;
 use downcast_rs::Downcast;
 use font::Fonts;
-use document::TocEntry;
+use document::{TocEntry, RenderLayers, StyleTweak, TextAlign};
 use settings::SecondColumn;
-use metadata::{Info, SortMethod, PageScheme, Margin};
+use metadata::{Info, SortMethod, PageScheme, Margin, ZoomMode};
 use framebuffer::{Framebuffer, UpdateMode};
 use input::{DeviceEvent, FingerStatus};
 use gesture::GestureEvent;
 use view::key::KeyKind;
+use view::search_results::Hit;
 use app::Context;
 use geom::{LinearDir, CycleDir, Rectangle};
 
@@ -196,9 +200,12 @@ pub enum Event {
     Gesture(GestureEvent),
     Keyboard(KeyboardEvent),
     Key(KeyKind),
-    Open(Box<Info>),
+    // The optional location is a location of the full-text index.
+    Open(Box<Info>, Option<f64>),
     OpenToc(Vec<TocEntry>, f64),
-    Invalid(Box<Info>),
+    SynthesizedToc(Vec<TocEntry>),
+    OpenStatistics(String),
+    Invalid(Box<Info>, Option<f64>),
     Remove(Box<Info>),
     Page(CycleDir),
     ResultsPage(CycleDir),
@@ -224,7 +231,13 @@ pub enum Event {
     Show(ViewId),
     Close(ViewId),
     CloseSub(ViewId),
-    SearchResult(f64, Rectangle),
+    SearchResult(Box<Hit>, Vec<(f64, Rectangle)>),
+    SearchHit(Box<Hit>),
+    SelectHit(PathBuf, f64),
+    SelectBookmark(usize),
+    ToggleBookmarkMenu(Rectangle, usize),
+    Rescale(usize),
+    Rotate(i8),
     EndOfSearch,
     Finished,
     ClockTick,
@@ -235,6 +248,8 @@ pub enum Event {
     Save,
     Guess,
     CheckBattery,
+    // Writes the settings right away instead of waiting for the exit.
+    SaveSettings,
     PrepareSuspend,
     Suspend,
     Share,
@@ -255,6 +270,8 @@ pub enum ViewId {
     BatteryMenu,
     ClockMenu,
     Frontlight,
+    Dictionary,
+    DictionaryMenu,
     FontSizeMenu,
     FontFamilyMenu,
     MarginWidthMenu,
@@ -262,8 +279,12 @@ pub enum ViewId {
     MatchesMenu,
     PageMenu,
     BookMenu,
+    BookmarkMenu,
     PresetMenu,
     MarginCropperMenu,
+    ReflowMenu,
+    ZoomMenu,
+    ImageAdjustments,
     SearchMenu,
     GoToPage,
     GoToPageInput,
@@ -273,8 +294,16 @@ pub enum ViewId {
     ExportAsInput,
     AddCategories,
     AddCategoriesInput,
+    UnlockDocument,
+    UnlockDocumentInput,
+    RenameBookmark,
+    RenameBookmarkInput,
+    SaveTypographyProfile,
+    SaveTypographyProfileInput,
     SearchInput,
     SearchBar,
+    SearchResults,
+    Bookmarks,
     Keyboard,
     ConfirmShare,
     MarginCropper,
@@ -284,6 +313,10 @@ pub enum ViewId {
     TakeScreenshotNotif,
     NoSearchResultsNotif,
     InvalidSearchQueryNotif,
+    SynthesizeTocNotif,
+    NoBookmarksNotif,
+    ScaleIndicator,
+    WrongPasswordNotif,
     LowBatteryNotif,
     NetUpNotif,
     SubMenu(u8),
@@ -294,6 +327,10 @@ pub enum SliderId {
     FontSize,
     LightIntensity,
     LightWarmth,
+    Gamma,
+    Contrast,
+    TintRemoval,
+    Binarization,
 }
 
 impl SliderId {
@@ -302,6 +339,10 @@ impl SliderId {
             SliderId::LightIntensity => "Intensity".to_string(),
             SliderId::LightWarmth => "Warmth".to_string(),
             SliderId::FontSize => "Font Size".to_string(),
+            SliderId::Gamma => "Gamma".to_string(),
+            SliderId::Contrast => "Contrast".to_string(),
+            SliderId::TintRemoval => "Tint Removal".to_string(),
+            SliderId::Binarization => "Binarization".to_string(),
         }
     }
 }
@@ -356,11 +397,24 @@ pub enum EntryId {
     ApplyCroppings(usize, PageScheme),
     SetFontFamily(String),
     SetFontSize(i32),
+    ApplyTypographyProfile(usize),
+    ToggleTypographyProfileKind(usize),
+    ToggleTypographyProfileCategory(usize, String),
+    SaveTypographyProfile,
+    SetTextAlign(Option<TextAlign>),
+    ToggleHyphenation,
+    ToggleIgnoreDocumentCss,
+    ToggleStyleTweak(StyleTweak),
     SetMarginWidth(i32),
     SetLineHeight(i32),
+    SetDictionary(usize),
+    SetDictionaryLanguage(String),
     RemoveCroppings,
+    ToggleIgnoreCroppingNoise,
     Remove(PathBuf),
     SearchDirection(LinearDir),
+    ToggleCaseSensitive,
+    ToggleWholeWord,
     AddBookCategories(PathBuf),
     RemoveBookCategory(PathBuf, String),
     RemoveMatches,
@@ -371,11 +425,22 @@ pub enum EntryId {
     Load(PathBuf),
     ExportMatches,
     ToggleFirstPage,
+    ToggleBookmark,
+    ShowBookmarks,
+    RenameBookmark(usize),
+    RemoveBookmark(usize),
+    ToggleReflow,
+    SetReflowZoom(i32),
+    SetZoomMode(ZoomMode),
+    ShowImageAdjustments,
+    SetRenderLayers(RenderLayers),
     ReverseOrder,
     ToggleInverted,
     ToggleMonochrome,
     ToggleWifi,
+    Rotate(i8),
     TakeScreenshot,
+    ShowStatistics,
     StartNickel,
     Reboot,
     Quit,
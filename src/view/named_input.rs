@@ -17,9 +17,9 @@ pub struct NamedInput {
 }
 
 impl NamedInput {
-    pub fn new(text: String, id: ViewId, input_id: ViewId, input_size: usize, fonts: &mut Fonts) -> NamedInput {
+    pub fn new(text: String, id: ViewId, input_id: ViewId, input_size: usize, dims: (u32, u32), fonts: &mut Fonts) -> NamedInput {
         let dpi = CURRENT_DEVICE.dpi;
-        let (width, height) = CURRENT_DEVICE.dims;
+        let (width, height) = dims;
 
         let input_size = input_size.max(3);
         let mut children = Vec::new();
@@ -74,6 +74,20 @@ impl NamedInput {
             id,
         }
     }
+
+    pub fn text(mut self, text: &str) -> NamedInput {
+        if let Some(input_field) = self.children[1].downcast_mut::<InputField>() {
+            input_field.set_text(text);
+        }
+        self
+    }
+
+    pub fn hidden(mut self, hidden: bool) -> NamedInput {
+        if let Some(input_field) = self.children[1].downcast_mut::<InputField>() {
+            input_field.set_hidden(hidden);
+        }
+        self
+    }
 }
 
 impl View for NamedInput {
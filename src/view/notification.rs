@@ -22,7 +22,7 @@ pub struct Notification {
 }
 
 impl Notification {
-    pub fn new(id: ViewId, text: String, index: &mut u8, fonts: &mut Fonts, hub: &Hub) -> Notification {
+    pub fn new(id: ViewId, text: String, index: &mut u8, dims: (u32, u32), fonts: &mut Fonts, hub: &Hub) -> Notification {
         let hub2 = hub.clone();
 
         thread::spawn(move || {
@@ -31,8 +31,8 @@ impl Notification {
         });
 
         let dpi = CURRENT_DEVICE.dpi;
-        let (width, height) = CURRENT_DEVICE.dims;
-        let &(small_height, _) = BAR_SIZES.get(&(height, dpi)).unwrap();
+        let (width, _) = dims;
+        let &(small_height, _) = BAR_SIZES.get(&(CURRENT_DEVICE.dims.1, dpi)).unwrap();
 
         let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
         let x_height = font.x_heights.0 as i32;
@@ -13,6 +13,7 @@ pub struct PageLabel {
     current_page: f64,
     pages_count: f64,
     synthetic: bool,
+    label: Option<String>,
 }
 
 impl PageLabel {
@@ -23,9 +24,15 @@ impl PageLabel {
             current_page,
             pages_count,
             synthetic,
+            label: None,
         }
     }
 
+    // Sets the label, defined by the document, of the current page.
+    pub fn set_label(&mut self, label: Option<String>) {
+        self.label = label;
+    }
+
     pub fn update(&mut self, current_page: f64, pages_count: f64, hub: &Hub) {
         self.current_page = current_page;
         self.pages_count = pages_count;
@@ -40,6 +47,8 @@ impl PageLabel {
             let pages_count = self.pages_count as usize;
             if pages_count == 0 {
                 "No pages".to_string()
+            } else if let Some(ref label) = self.label {
+                format!("Page {} ({} of {})", label, current_page + 1, pages_count)
             } else {
                 format!("Page {} of {}", current_page + 1, pages_count)
             }
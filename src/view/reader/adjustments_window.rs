@@ -0,0 +1,153 @@
+use device::{CURRENT_DEVICE, BAR_SIZES};
+use framebuffer::Framebuffer;
+use geom::{Rectangle, CornerSpec, BorderSpec};
+use font::{Fonts, font_from_style, NORMAL_STYLE};
+use view::{View, Event, Hub, Bus, ViewId, SliderId, Align};
+use view::{THICKNESS_LARGE, BORDER_RADIUS_MEDIUM};
+use view::label::Label;
+use view::slider::Slider;
+use view::icon::Icon;
+use gesture::GestureEvent;
+use metadata::ImageAdjustments;
+use color::{BLACK, WHITE};
+use unit::scale_by_dpi;
+use app::Context;
+
+pub const MIN_GAMMA: f32 = 0.5;
+pub const MAX_GAMMA: f32 = 2.0;
+
+pub struct AdjustmentsWindow {
+    rect: Rectangle,
+    children: Vec<Box<View>>,
+}
+
+impl AdjustmentsWindow {
+    pub fn new(adjustments: &ImageAdjustments, context: &mut Context) -> AdjustmentsWindow {
+        let fonts = &mut context.fonts;
+        let mut children = Vec::new();
+        let dpi = CURRENT_DEVICE.dpi;
+        let (width, height) = context.display.dims;
+        let &(small_height, _) = BAR_SIZES.get(&(CURRENT_DEVICE.dims.1, dpi)).unwrap();
+        let thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as i32;
+        let border_radius = scale_by_dpi(BORDER_RADIUS_MEDIUM, dpi) as i32;
+
+        let padding = {
+            let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+            font.em() as i32
+        };
+
+        let sliders = [(SliderId::Gamma, adjustments.gamma, MIN_GAMMA, MAX_GAMMA),
+                       (SliderId::Contrast, adjustments.contrast, 0.0, 1.0),
+                       (SliderId::TintRemoval, adjustments.tint_removal, 0.0, 1.0),
+                       (SliderId::Binarization, adjustments.binarization, 0.0, 1.0)];
+
+        let window_width = width as i32 - 2 * padding;
+        let window_height = small_height as i32 * (sliders.len() + 1) as i32 + padding;
+
+        let dx = (width as i32 - window_width) / 2;
+        let dy = (height as i32 - window_height) / 3;
+
+        let rect = rect![dx, dy, dx + window_width, dy + window_height];
+
+        let close_icon = Icon::new("close",
+                                   rect![rect.max.x - small_height as i32,
+                                         rect.min.y + thickness,
+                                         rect.max.x - thickness,
+                                         rect.min.y + small_height as i32],
+                                   Event::Close(ViewId::ImageAdjustments))
+                              .corners(Some(CornerSpec::Uniform(border_radius - thickness)));
+
+        children.push(Box::new(close_icon) as Box<View>);
+
+        let label = Label::new(rect![rect.min.x + small_height as i32,
+                                     rect.min.y + thickness,
+                                     rect.max.x - small_height as i32,
+                                     rect.min.y + small_height as i32],
+                               "Image Adjustments".to_string(),
+                               Align::Center);
+
+        children.push(Box::new(label) as Box<View>);
+
+        let max_label_width = {
+            let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+            sliders.iter().map(|s| font.plan(&s.0.label(), None, None).width)
+                          .max().unwrap() as i32
+        };
+
+        for (index, &(slider_id, value, min_value, max_value)) in sliders.iter().enumerate() {
+            let min_y = rect.min.y + (index + 1) as i32 * small_height as i32;
+            let label = Label::new(rect![rect.min.x + padding,
+                                         min_y,
+                                         rect.min.x + 2 * padding + max_label_width,
+                                         min_y + small_height as i32],
+                                   slider_id.label(),
+                                   Align::Right(padding / 2));
+            children.push(Box::new(label) as Box<View>);
+
+            let slider = Slider::new(rect![rect.min.x + max_label_width + 3 * padding,
+                                           min_y,
+                                           rect.max.x - padding,
+                                           min_y + small_height as i32],
+                                     slider_id,
+                                     value,
+                                     min_value,
+                                     max_value);
+            children.push(Box::new(slider) as Box<View>);
+        }
+
+        AdjustmentsWindow {
+            rect,
+            children,
+        }
+    }
+}
+
+impl View for AdjustmentsWindow {
+    fn handle_event(&mut self, evt: &Event, hub: &Hub, _bus: &mut Bus, _context: &mut Context) -> bool {
+        match *evt {
+            Event::Gesture(GestureEvent::Tap(center)) if !self.rect.includes(center) => {
+                hub.send(Event::Close(ViewId::ImageAdjustments)).unwrap();
+                true
+            },
+            Event::Gesture(..) => true,
+            _ => false,
+        }
+    }
+
+    fn render(&self, fb: &mut Framebuffer, _fonts: &mut Fonts) {
+        let dpi = CURRENT_DEVICE.dpi;
+
+        let border_radius = scale_by_dpi(BORDER_RADIUS_MEDIUM, dpi) as i32;
+        let border_thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as u16;
+
+        fb.draw_rounded_rectangle_with_border(&self.rect,
+                                              &CornerSpec::Uniform(border_radius),
+                                              &BorderSpec { thickness: border_thickness,
+                                                            color: BLACK },
+                                              &WHITE);
+    }
+
+    fn is_background(&self) -> bool {
+        true
+    }
+
+    fn rect(&self) -> &Rectangle {
+        &self.rect
+    }
+
+    fn rect_mut(&mut self) -> &mut Rectangle {
+        &mut self.rect
+    }
+
+    fn children(&self) -> &Vec<Box<View>> {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> &mut Vec<Box<View>> {
+        &mut self.children
+    }
+
+    fn id(&self) -> Option<ViewId> {
+        Some(ViewId::ImageAdjustments)
+    }
+}
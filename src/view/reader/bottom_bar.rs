@@ -7,7 +7,7 @@ use view::page_label::PageLabel;
 use gesture::GestureEvent;
 use input::DeviceEvent;
 use geom::{Rectangle, CycleDir, halves};
-use document::{Document, Neighbors, chapter_at};
+use document::{TocEntry, Neighbors, chapter_at};
 use color::WHITE;
 use font::Fonts;
 use app::Context;
@@ -21,7 +21,7 @@ pub struct BottomBar {
 }
 
 impl BottomBar {
-    pub fn new(rect: Rectangle, doc: &mut Document, current_page: f64, pages_count: f64, neighbors: &Neighbors, synthetic: bool) -> BottomBar {
+    pub fn new(rect: Rectangle, toc: Option<&[TocEntry]>, current_page: f64, pages_count: f64, neighbors: &Neighbors, synthetic: bool, label: Option<String>) -> BottomBar {
         let mut children = Vec::new();
         let side = rect.height() as i32;
         let is_prev_disabled = neighbors.previous_page.is_none();
@@ -45,7 +45,7 @@ impl BottomBar {
         let chapter_rect = rect![pt!(rect.min.x + side, rect.min.y),
                                  pt!(rect.min.x + side + small_half_width, rect.max.y)];
 
-        let chapter = doc.toc().as_ref().and_then(|t| chapter_at(t, current_page))
+        let chapter = toc.and_then(|t| chapter_at(t, current_page))
                                .map(|c| c.title.clone())
                                .unwrap_or_default();
         let chapter_label = Label::new(chapter_rect,
@@ -53,11 +53,12 @@ impl BottomBar {
                                        Align::Center);
         children.push(Box::new(chapter_label) as Box<View>);
 
-        let page_label = PageLabel::new(rect![pt!(rect.max.x - side - big_half_width, rect.min.y),
-                                              pt!(rect.max.x - side, rect.max.y)],
-                                        current_page,
-                                        pages_count,
-                                        synthetic);
+        let mut page_label = PageLabel::new(rect![pt!(rect.max.x - side - big_half_width, rect.min.y),
+                                                  pt!(rect.max.x - side, rect.max.y)],
+                                            current_page,
+                                            pages_count,
+                                            synthetic);
+        page_label.set_label(label);
         children.push(Box::new(page_label) as Box<View>);
 
         let next_rect = rect![rect.max - side, rect.max];
@@ -80,8 +81,9 @@ impl BottomBar {
         }
     }
 
-    pub fn update_page_label(&mut self, current_page: f64, pages_count: f64, hub: &Hub) {
+    pub fn update_page_label(&mut self, current_page: f64, pages_count: f64, label: Option<String>, hub: &Hub) {
         let page_label = self.child_mut(2).downcast_mut::<PageLabel>().unwrap();
+        page_label.set_label(label);
         page_label.update(current_page, pages_count, hub);
     }
 
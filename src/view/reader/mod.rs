@@ -1,22 +1,27 @@
 mod top_bar;
 mod tool_bar;
 mod bottom_bar;
-mod results_bar;
+pub mod results_bar;
 mod margin_cropper;
 mod results_label;
+mod adjustments_window;
+mod pixmap_cache;
+mod scale_indicator;
 
+use std::f32;
 use std::thread;
 use std::sync::{Arc, Mutex, mpsc};
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering as AtomicOrdering;
-use std::rc::Rc;
 use std::cmp::Ordering;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::collections::VecDeque;
+use std::time::Duration;
 use chrono::Local;
 use regex::Regex;
 use input::{DeviceEvent, FingerStatus, ButtonCode, ButtonStatus};
 use framebuffer::{Framebuffer, UpdateMode, Pixmap};
+use framebuffer::filters;
 use view::{View, Event, Hub, ViewId, EntryKind, EntryId, SliderId, Bus, THICKNESS_MEDIUM};
 use unit::{scale_by_dpi, mm_to_px};
 use device::{CURRENT_DEVICE, BAR_SIZES};
@@ -27,6 +32,9 @@ use self::top_bar::TopBar;
 use self::tool_bar::ToolBar;
 use self::bottom_bar::BottomBar;
 use self::results_bar::ResultsBar;
+use self::adjustments_window::AdjustmentsWindow;
+use self::pixmap_cache::{PixmapCache, PixmapKey};
+use self::scale_indicator::ScaleIndicator;
 use view::common::{locate, locate_by_id, shift};
 use view::common::{toggle_main_menu, toggle_battery_menu, toggle_clock_menu};
 use view::filler::Filler;
@@ -35,41 +43,109 @@ use view::search_bar::SearchBar;
 use view::keyboard::{Keyboard, DEFAULT_LAYOUT};
 use view::menu::{Menu, MenuKind};
 use view::notification::Notification;
-use settings::{guess_frontlight, FinishedAction, DEFAULT_FONT_FAMILY};
+use view::dictionary::DictionaryWindow;
+use view::search_results::{SearchResults, Hit};
+use view::bookmarks::{Bookmarks, BookmarkItem};
+use settings::{guess_frontlight, FinishedAction, GestureKind, ReaderAction, ReaderSettings, TypographyProfile, DEFAULT_FONT_FAMILY};
 use frontlight::LightLevels;
 use gesture::GestureEvent;
-use document::{Document, DocumentOpener, Location, Neighbors};
+use document::{Document, DocumentOpener, Location, Neighbors, BoundedText, RenderLayers, TextAlign};
+use document::{StyleTweak, STYLE_TWEAKS};
 use document::{TocEntry, toc_as_html, chapter_at, chapter_relative};
 use document::pdf::PdfOpener;
+use document::reflow::{ReflowDocument, DEFAULT_REFLOW_ZOOM};
+use document::columns::detect_columns;
+use document::page_labels::PageLabels;
+use document::headings::{text_lines, synthesize_toc};
+use document::crop::content_frame;
 use document::epub::LOCATION_EPSILON;
-use metadata::{Info, FileInfo, ReaderInfo, PageScheme, Margin, CroppingMargins, make_query};
-use geom::{Rectangle, CornerSpec, BorderSpec, Dir, CycleDir, LinearDir, halves};
+use fulltext::{snippet, document_location};
+use metadata::{Info, FileInfo, ReaderInfo, Bookmark, PageScheme, Margin, CroppingMargins, ZoomMode, ImageAdjustments, make_search_query};
+use geom::{Point, Rectangle, CornerSpec, BorderSpec, Dir, CycleDir, LinearDir, halves};
 use color::{BLACK, WHITE};
+use keystore::{load_password, save_password};
+use statistics::{SessionTracker, CurrentBook, statistics_as_html, load_sessions, save_session};
 use app::Context;
 
 const HISTORY_SIZE: usize = 32;
+// Part of the screen that remains visible when scrolling through a page.
+const SCROLL_OVERLAP: f32 = 0.1;
+const MAX_ZOOM_PERCENT: u16 = 400;
+// Distance, in millimeters, covered by a pinch or a spread for each step of the font size.
+const PINCH_STEP_DISTANCE: f32 = 8.0;
+const FONT_SIZE_STEP: f32 = 0.5;
+const REFLOW_ZOOM_STEP: f32 = 0.25;
+const MIN_REFLOW_ZOOM: f32 = 1.0;
+const MAX_REFLOW_ZOOM: f32 = 3.0;
+// Delay after the last pinch or spread before the document is laid out.
+const RESCALE_DELAY: Duration = Duration::from_millis(800);
+// Number of rendered pages kept in memory.
+const CACHE_CAPACITY: usize = 5;
+// Sensitivity of the binarization at the end of its slider.
+const MAX_SAUVOLA_K: f32 = 0.5;
+// Padding added around the detected columns and content, relative to the page dimensions.
+const CONTENT_PADDING: f32 = 0.01;
+// Minimum length, in bytes, of the excerpts captured by the bookmarks.
+const EXCERPT_LENGTH: usize = 120;
 
 pub struct Reader {
     rect: Rectangle,
     children: Vec<Box<View>>,
     info: Info,
     doc: Arc<Mutex<Box<Document>>>,
-    pixmap: Rc<Pixmap>,
+    pixmap: Arc<Pixmap>,
+    cache: Arc<Mutex<PixmapCache>>,
     current_page: f64,
     pages_count: f64,
     synthetic: bool,
+    page_labels: Option<PageLabels>,
+    prerenderer: Option<Worker>,
+    toc_synthesizer: Option<Worker>,
+    // Index of the bookmark being renamed.
+    renamed_bookmark: Option<usize>,
+    // The value set by the pending pinches and spreads, and the number of these gestures.
+    scale_target: Option<ScaleTarget>,
+    rescale_id: usize,
+    session: Option<SessionTracker>,
     page_turns: usize,
     finished: bool,
     ephemeral: bool,
     refresh_every: u8,
     search_direction: LinearDir,
+    case_sensitive: bool,
+    whole_word: bool,
     frame: Rectangle,
+    page_frame: Rectangle,
+    view_port: Point,
     scale: f32,
+    // Index of the current column, and the columns of a page, in column mode.
+    column: usize,
+    columns: Option<(usize, Vec<Margin>)>,
+    // The automatic cropping margin of a page.
+    auto_margin: Option<(usize, Margin)>,
     focus: Option<ViewId>,
     search: Option<Search>,
     history: VecDeque<f64>,
 }
 
+#[derive(Debug, Copy, Clone)]
+enum ScaleTarget {
+    FontSize(f32),
+    ReflowZoom(f32),
+    // Zoom percentage and center.
+    Zoom(f32, Point),
+}
+
+impl ScaleTarget {
+    fn label(&self) -> String {
+        match *self {
+            ScaleTarget::FontSize(font_size) => format!("Font size: {:.1}", font_size),
+            ScaleTarget::ReflowZoom(zoom) => format!("Reflow zoom: {:.2}", zoom),
+            ScaleTarget::Zoom(percent, _) => format!("Zoom: {}%", percent.round()),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Search {
     query: String,
@@ -77,6 +153,34 @@ struct Search {
     running: Arc<AtomicBool>,
     current_page: usize,
     results_count: usize,
+    hits: Vec<Hit>,
+}
+
+// A thread working on the document in the background, that checks its running flag between pages.
+#[derive(Debug)]
+struct Worker {
+    running: Arc<AtomicBool>,
+    handle: thread::JoinHandle<()>,
+}
+
+impl Worker {
+    fn spawn<F>(f: F) -> Worker where F: FnOnce(&AtomicBool) + Send + 'static {
+        let running = Arc::new(AtomicBool::new(true));
+        let running2 = Arc::clone(&running);
+        let handle = thread::spawn(move || f(&running2));
+        Worker { running, handle }
+    }
+
+    // Asks the thread to stop and waits until it's done with the current page.
+    fn stop(self) {
+        self.running.store(false, AtomicOrdering::Relaxed);
+        self.handle.join().ok();
+    }
+
+    // Asks the thread to stop without waiting for it.
+    fn cancel(self) {
+        self.running.store(false, AtomicOrdering::Relaxed);
+    }
 }
 
 #[derive(Debug)]
@@ -93,23 +197,43 @@ impl Default for Search {
             running: Arc::new(AtomicBool::new(true)),
             current_page: 0,
             results_count: 0,
+            hits: Vec::new(),
         }
     }
 }
 
 impl Reader {
-    pub fn new(rect: Rectangle, mut info: Info, hub: &Hub, context: &mut Context) -> Option<Reader> {
+    // The optional location, found in the full-text index, is where the book is opened.
+    pub fn new(rect: Rectangle, mut info: Info, location: Option<f64>, hub: &Hub, context: &mut Context) -> Option<Reader> {
         let settings = &context.settings;
         let path = settings.library_path.join(&info.file.path);
         let opener = DocumentOpener::new(settings.reader.epub_engine);
 
         opener.open(&path).and_then(|mut doc| {
-            let (width, height) = CURRENT_DEVICE.dims;
+            if !unlock(doc.as_mut(), &info.file.path, context) {
+                return None;
+            }
+
+            if let Some(layers) = info.reader.as_ref().and_then(|r| r.render_layers) {
+                doc.set_render_layers(layers);
+            }
+
+            if let Some(zoom) = info.reader.as_ref().and_then(|r| r.reflow) {
+                if !doc.is_reflowable() {
+                    doc = Box::new(ReflowDocument::new(doc, zoom));
+                }
+            }
+
+            let (width, height) = (rect.width(), rect.height());
             let font_size = info.reader.as_ref().and_then(|r| r.font_size)
                                 .unwrap_or(settings.reader.font_size);
             let first_location = doc.resolve_location(Location::Exact(0.0))?;
 
             doc.layout(width, height, font_size, CURRENT_DEVICE.dpi);
+            doc.set_fallback_fonts(&settings.fallback_fonts);
+            doc.set_min_font_size(settings.reader.min_font_size);
+            doc.set_max_font_size_ratio(settings.reader.max_font_size_ratio);
+            doc.set_user_stylesheet(&user_stylesheet(&info, &settings.reader));
             doc.set_margin_width(info.reader.as_ref().and_then(|r| r.margin_width)
                                      .unwrap_or(settings.reader.margin_width));
 
@@ -131,34 +255,83 @@ impl Reader {
                 if let Some(line_height) = r.line_height {
                     doc.set_line_height(line_height);
                 }
+                if let Some(text_align) = r.text_align {
+                    doc.set_text_align(Some(text_align));
+                }
+                if let Some(hyphenation) = r.hyphenation {
+                    doc.set_hyphenation(hyphenation);
+                }
+                if let Some(ignore) = r.ignore_document_css {
+                    doc.set_ignore_document_css(ignore);
+                }
             } else {
                 current_page = first_location;
+                let mut reader_info = ReaderInfo::default();
+                let profile = settings.reader.typography_profiles.iter()
+                                      .find(|p| p.is_default_for(&info.file.kind, &info.categories))
+                                      .filter(|_| doc.is_reflowable());
+                if let Some(profile) = profile {
+                    set_typography(doc.as_mut(), profile, (width, height), &settings.reader.font_path);
+                    record_typography(&mut reader_info, profile);
+                } else {
+                    if settings.reader.font_family != DEFAULT_FONT_FAMILY {
+                        doc.set_font_family(&settings.reader.font_family, &settings.reader.font_path);
+                    }
+                    doc.set_line_height(settings.reader.line_height);
+                }
                 pages_count = doc.pages_count();
                 info.reader = Some(ReaderInfo {
                     current_page,
                     pages_count,
-                    .. Default::default()
+                    .. reader_info
                 });
-                if settings.reader.font_family != DEFAULT_FONT_FAMILY {
-                    doc.set_font_family(&settings.reader.font_family, &settings.reader.font_path);
-                }
-                doc.set_line_height(settings.reader.line_height);
+            }
+
+            if let Some(location) = location.and_then(|l| document_location(doc.as_mut(), l)) {
+                current_page = location;
             }
 
             let synthetic = doc.has_synthetic_page_numbers();
+            let page_labels = if synthetic { None } else { doc.page_labels() };
 
             println!("{}", info.file.path.display());
 
-            let margin = info.reader.as_ref()
-                             .and_then(|r| r.cropping_margins.as_ref()
-                                            .map(|c| c.margin(current_page as usize)))
-                             .cloned().unwrap_or_default();
-            let ((pixmap, location), scale) = build_pixmap(&rect, doc.as_mut(), current_page, &margin);
-            let frame = rect![(margin.left * pixmap.width as f32).ceil() as i32,
-                              (margin.top * pixmap.height as f32).ceil() as i32,
-                              ((1.0 - margin.right) * pixmap.width as f32).floor() as i32,
-                              ((1.0 - margin.bottom) * pixmap.height as f32).floor() as i32];
-            let pixmap = Rc::new(pixmap);
+            let index = current_page as usize;
+            let mut auto_margin = None;
+            let mut margin = match info.reader.as_ref()
+                                          .filter(|r| r.reflow.is_none())
+                                          .and_then(|r| r.cropping_margins.as_ref()) {
+                Some(&CroppingMargins::Auto { ignore_noise }) => {
+                    let margin = content_margin(doc.as_mut(), index, ignore_noise);
+                    auto_margin = Some((index, margin.clone()));
+                    margin
+                },
+                Some(c) => c.margin(index).clone(),
+                None => Margin::default(),
+            };
+            let zoom_mode = info.reader.as_ref()
+                                .filter(|r| r.reflow.is_none())
+                                .and_then(|r| r.zoom_mode)
+                                .unwrap_or_default();
+            let mut columns = None;
+            if zoom_mode == ZoomMode::Columns {
+                let margins = column_margins(doc.as_mut(), index);
+                if let Some(m) = margins.first() {
+                    margin = m.clone();
+                }
+                columns = Some((index, margins));
+            }
+            let ((mut pixmap, location), scale) = build_pixmap(&rect, doc.as_mut(), current_page, &margin, zoom_mode);
+            if let Some(adjustments) = info.reader.as_ref().and_then(|r| r.image_adjustments) {
+                adjust_pixmap(&mut pixmap, &adjustments);
+            }
+            let page_frame = rect![(margin.left * pixmap.width as f32).ceil() as i32,
+                                   (margin.top * pixmap.height as f32).ceil() as i32,
+                                   ((1.0 - margin.right) * pixmap.width as f32).floor() as i32,
+                                   ((1.0 - margin.bottom) * pixmap.height as f32).floor() as i32];
+            let mut view_port = Point::default();
+            let frame = visible_frame(&page_frame, &mut view_port, &rect);
+            let pixmap = Arc::new(pixmap);
             current_page = location;
 
             hub.send(Event::Render(rect, UpdateMode::Partial)).unwrap();
@@ -169,16 +342,31 @@ impl Reader {
                 info,
                 doc: Arc::new(Mutex::new(doc)),
                 pixmap,
+                cache: Arc::new(Mutex::new(PixmapCache::new(CACHE_CAPACITY))),
                 current_page,
                 pages_count,
                 synthetic,
+                page_labels,
+                prerenderer: None,
+                toc_synthesizer: None,
+                renamed_bookmark: None,
+                scale_target: None,
+                rescale_id: 0,
+                session: Some(SessionTracker::new(current_page)),
                 page_turns: 0,
                 finished: false,
                 ephemeral: false,
                 refresh_every: settings.reader.refresh_every,
                 search_direction: LinearDir::Forward,
+                case_sensitive: false,
+                whole_word: false,
                 frame,
+                page_frame,
+                view_port,
                 scale,
+                column: 0,
+                columns,
+                auto_margin,
                 focus: None,
                 search: None,
                 history: VecDeque::new(),
@@ -186,13 +374,18 @@ impl Reader {
         })
     }
 
-    pub fn from_toc(rect: Rectangle, toc: &[TocEntry], mut current_page: f64, hub: &Hub, context: &mut Context) -> Reader {
+    pub fn from_toc(rect: Rectangle, toc: &[TocEntry], current_page: f64, hub: &Hub, context: &mut Context) -> Reader {
         let html = toc_as_html(toc, current_page);
+        let link_uri = chapter_at(toc, current_page).map(|chap| format!("@{}", chap.location));
+        Reader::from_html(rect, &html, "Table of Contents", "css/toc.css", link_uri.as_ref().map(String::as_str), hub, context)
+    }
 
+    // Shows the given HTML, starting at the page holding the given link, if any.
+    pub fn from_html(rect: Rectangle, html: &str, title: &str, css_path: &str, link_uri: Option<&str>, hub: &Hub, context: &mut Context) -> Reader {
         let info = Info {
-            title: "Table of Contents".to_string(),
+            title: title.to_string(),
             file: FileInfo {
-                path: PathBuf::from("toc:"),
+                path: PathBuf::from("html:"),
                 kind: "html".to_string(),
                 size: html.len() as u64,
             },
@@ -200,15 +393,14 @@ impl Reader {
         };
 
         let mut opener = PdfOpener::new().unwrap();
-        opener.set_user_css("css/toc.css").unwrap();
+        opener.set_user_css(css_path).unwrap();
         let mut doc = opener.open_memory("html", html.as_bytes()).unwrap();
-        let (width, height) = CURRENT_DEVICE.dims;
+        let (width, height) = (rect.width(), rect.height());
         let font_size = context.settings.reader.font_size;
         doc.layout(width, height, font_size, CURRENT_DEVICE.dpi);
         let pages_count = doc.pages_count();
 
-        current_page = chapter_at(toc, current_page).and_then(|chap| {
-            let link_uri = format!("@{}", chap.location);
+        let mut current_page = link_uri.and_then(|link_uri| {
             let mut loc = Location::Exact(0.0);
             while let Some((links, l)) = doc.links(loc) {
                 if links.iter().any(|link| link.text == link_uri) {
@@ -219,9 +411,9 @@ impl Reader {
             None
         }).unwrap_or(0.0);
 
-        let ((pixmap, location), scale) = build_pixmap(&rect, &mut doc, current_page, &Margin::default());
+        let ((pixmap, location), scale) = build_pixmap(&rect, &mut doc, current_page, &Margin::default(), ZoomMode::FitToPage);
         current_page = location;
-        let pixmap = Rc::new(pixmap);
+        let pixmap = Arc::new(pixmap);
         let frame = pixmap.rect();
 
         hub.send(Event::Render(rect, UpdateMode::Partial)).unwrap();
@@ -232,16 +424,31 @@ impl Reader {
             info,
             doc: Arc::new(Mutex::new(Box::new(doc))),
             pixmap,
+            cache: Arc::new(Mutex::new(PixmapCache::new(CACHE_CAPACITY))),
             current_page,
             pages_count,
             synthetic: false,
+            page_labels: None,
+            prerenderer: None,
+            toc_synthesizer: None,
+            renamed_bookmark: None,
+            scale_target: None,
+            rescale_id: 0,
+            session: None,
             page_turns: 0,
             finished: false,
             ephemeral: true,
             refresh_every: context.settings.reader.refresh_every,
             search_direction: LinearDir::Forward,
+            case_sensitive: false,
+            whole_word: false,
             frame,
+            page_frame: frame,
+            view_port: Point::default(),
             scale,
+            column: 0,
+            columns: None,
+            auto_margin: None,
             focus: None,
             search: None,
             history: VecDeque::new(),
@@ -249,6 +456,10 @@ impl Reader {
     }
 
     fn go_to_page(&mut self, location: f64, record: bool, hub: &Hub) {
+        self.go_to_column(location, 0, record, hub);
+    }
+
+    fn go_to_column(&mut self, location: f64, column: usize, record: bool, hub: &Hub) {
         let loc = {
             let mut doc = self.doc.lock().unwrap();
             doc.resolve_location(Location::Exact(location))
@@ -275,6 +486,11 @@ impl Reader {
             }
 
             self.current_page = location;
+            self.column = column;
+            self.view_port = Point::default();
+            if let Some(ref mut session) = self.session {
+                session.update(location);
+            }
             self.update(hub);
             self.update_bottom_bar(hub);
 
@@ -288,7 +504,7 @@ impl Reader {
         let current_page = self.current_page;
         let chap = {
             let mut doc = self.doc.lock().unwrap();
-            doc.toc().and_then(|toc| chapter_relative(&toc, current_page, dir))
+            document_toc(doc.as_mut(), &self.info).and_then(|toc| chapter_relative(&toc, current_page, dir))
         };
         if let Some(location) = chap {
             self.go_to_page(location, true, hub);
@@ -300,11 +516,13 @@ impl Reader {
         if let Some(ref r) = self.info.reader {
             match dir {
                 CycleDir::Next => {
-                    loc = r.bookmarks.iter().find(|&loc| *loc > self.current_page).cloned();
+                    loc = r.bookmarks.iter().map(|b| b.location)
+                                     .find(|&loc| loc > self.current_page);
                 },
                 CycleDir::Previous => {
-                    loc = r.bookmarks.iter().filter(|&loc| *loc < self.current_page)
-                                     .next_back().cloned();
+                    loc = r.bookmarks.iter().map(|b| b.location)
+                                     .filter(|&loc| loc < self.current_page)
+                                     .next_back();
                 },
             }
         }
@@ -320,6 +538,30 @@ impl Reader {
     }
 
     fn go_to_neighbor(&mut self, dir: CycleDir, hub: &Hub, context: &mut Context) {
+        if self.scroll(dir, hub) {
+            return;
+        }
+
+        if self.zoom_mode() == ZoomMode::Columns {
+            let count = self.columns.as_ref().map_or(0, |(_, c)| c.len());
+            match dir {
+                CycleDir::Next if self.column + 1 < count => {
+                    self.column += 1;
+                    self.view_port = Point::default();
+                    self.update(hub);
+                    return;
+                },
+                CycleDir::Previous if self.column > 0 => {
+                    self.column -= 1;
+                    self.update(hub);
+                    self.view_port = pt!(i32::max_value());
+                    self.frame = visible_frame(&self.page_frame, &mut self.view_port, &self.rect);
+                    return;
+                },
+                _ => (),
+            }
+        }
+
         let current_page = self.current_page;
         let loc = {
             let neighloc = if dir == CycleDir::Previous {
@@ -331,7 +573,17 @@ impl Reader {
             doc.resolve_location(neighloc)
         };
         if let Some(location) = loc {
-            self.go_to_page(location, false, hub);
+            // Start with the last column of the previous page.
+            let column = if dir == CycleDir::Previous { usize::max_value() } else { 0 };
+            self.go_to_column(location, column, false, hub);
+            if let Some(ref mut session) = self.session {
+                session.turn_page(current_page, location);
+            }
+            // Show the end of the previous page.
+            if dir == CycleDir::Previous {
+                self.view_port = pt!(i32::max_value());
+                self.frame = visible_frame(&self.page_frame, &mut self.view_port, &self.rect);
+            }
         } else {
             match dir {
                 CycleDir::Next => {
@@ -341,6 +593,7 @@ impl Reader {
                             let notif = Notification::new(ViewId::BoundaryNotif,
                                                           "No next page.".to_string(),
                                                           &mut context.notification_index,
+                                                          context.display.dims,
                                                           &mut context.fonts,
                                                           hub);
                             self.children.push(Box::new(notif) as Box<View>);
@@ -355,6 +608,7 @@ impl Reader {
                     let notif = Notification::new(ViewId::BoundaryNotif,
                                                   "No previous page.".to_string(),
                                                   &mut context.notification_index,
+                                                  context.display.dims,
                                                   &mut context.fonts,
                                                   hub);
                     self.children.push(Box::new(notif) as Box<View>);
@@ -373,6 +627,8 @@ impl Reader {
         }
         if let Some(location) = loc {
             self.current_page = location;
+            self.column = 0;
+            self.view_port = Point::default();
             self.update_results_bar(hub);
             self.update_bottom_bar(hub);
             self.update(hub);
@@ -400,12 +656,242 @@ impl Reader {
                 };
             }
             self.current_page = location;
+            self.column = 0;
+            self.view_port = Point::default();
             self.update_results_bar(hub);
             self.update_bottom_bar(hub);
             self.update(hub);
         }
     }
 
+    // Moves the view port to the next or previous part of the page, if any.
+    fn scroll(&mut self, dir: CycleDir, hub: &Hub) -> bool {
+        let width = self.rect.width() as i32;
+        let height = self.rect.height() as i32;
+        let max_x = (self.page_frame.width() as i32 - width).max(0);
+        let max_y = (self.page_frame.height() as i32 - height).max(0);
+        let step_x = width - (SCROLL_OVERLAP * width as f32) as i32;
+        let step_y = height - (SCROLL_OVERLAP * height as f32) as i32;
+        let mut view_port = self.view_port;
+
+        match dir {
+            CycleDir::Next => {
+                if view_port.y < max_y {
+                    view_port.y = (view_port.y + step_y).min(max_y);
+                } else if view_port.x < max_x {
+                    view_port.x = (view_port.x + step_x).min(max_x);
+                    view_port.y = 0;
+                } else {
+                    return false;
+                }
+            },
+            CycleDir::Previous => {
+                if view_port.y > 0 {
+                    view_port.y = (view_port.y - step_y).max(0);
+                } else if view_port.x > 0 {
+                    view_port.x = (view_port.x - step_x).max(0);
+                    view_port.y = max_y;
+                } else {
+                    return false;
+                }
+            },
+        }
+
+        self.view_port = view_port;
+        self.frame = visible_frame(&self.page_frame, &mut self.view_port, &self.rect);
+        hub.send(Event::Render(self.rect, UpdateMode::Partial)).unwrap();
+        true
+    }
+
+    fn pan(&mut self, delta: Point, hub: &Hub) {
+        self.view_port += delta;
+        self.frame = visible_frame(&self.page_frame, &mut self.view_port, &self.rect);
+        hub.send(Event::Render(self.rect, UpdateMode::Partial)).unwrap();
+    }
+
+    fn zoom_mode(&self) -> ZoomMode {
+        self.info.reader.as_ref()
+            .filter(|r| r.reflow.is_none())
+            .and_then(|r| r.zoom_mode)
+            .unwrap_or_default()
+    }
+
+    fn cropping_margin(&mut self) -> Margin {
+        let index = self.current_page as usize;
+        let ignore_noise = match self.info.reader.as_ref()
+                                     .filter(|r| r.reflow.is_none())
+                                     .and_then(|r| r.cropping_margins.as_ref()) {
+            Some(&CroppingMargins::Auto { ignore_noise }) => ignore_noise,
+            Some(c) => return c.margin(index).clone(),
+            None => return Margin::default(),
+        };
+
+        if self.auto_margin.as_ref().map_or(true, |(i, _)| *i != index) {
+            let mut doc = self.doc.lock().unwrap();
+            self.auto_margin = Some((index, content_margin(doc.as_mut(), index, ignore_noise)));
+        }
+
+        self.auto_margin.as_ref().unwrap().1.clone()
+    }
+
+    // Returns the margin that isolates the current column, or the cropping margin
+    // when no columns were detected.
+    fn column_margin(&mut self) -> Margin {
+        let index = self.current_page as usize;
+
+        if self.columns.as_ref().map_or(true, |(i, _)| *i != index) {
+            let mut doc = self.doc.lock().unwrap();
+            self.columns = Some((index, column_margins(doc.as_mut(), index)));
+        }
+
+        let margins = &self.columns.as_ref().unwrap().1;
+
+        if margins.is_empty() {
+            return self.cropping_margin();
+        }
+
+        self.column = self.column.min(margins.len() - 1);
+        margins[self.column].clone()
+    }
+
+    fn set_zoom_mode(&mut self, zoom_mode: ZoomMode, hub: &Hub, context: &mut Context) {
+        if let Some(ref mut r) = self.info.reader {
+            r.zoom_mode = Some(zoom_mode);
+        }
+        self.column = 0;
+        self.view_port = Point::default();
+        self.update(hub);
+        self.update_tool_bar(hub, context);
+    }
+
+    // Returns the scale at which the current page fits the screen, in fixed layout mode.
+    fn fit_scale(&mut self) -> Option<f32> {
+        if self.info.reader.as_ref().map_or(true, |r| r.reflow.is_some()) {
+            return None;
+        }
+
+        let margin = self.cropping_margin();
+        let doc = self.doc.lock().unwrap();
+        if doc.is_reflowable() {
+            return None;
+        }
+        let (w_ratio, h_ratio) = fit_scales(&self.rect, doc.as_ref(), self.current_page, &margin);
+        Some(w_ratio.min(h_ratio))
+    }
+
+    // Sets the zoom percentage, keeping the given point in place.
+    fn zoom_to(&mut self, percent: f32, center: Point, hub: &Hub, context: &mut Context) {
+        if self.fit_scale().is_none() {
+            return;
+        }
+
+        let percent = percent.round()
+                             .max(100.0)
+                             .min(MAX_ZOOM_PERCENT as f32) as u16;
+        let zoom_mode = if percent > 100 { ZoomMode::Custom(percent) } else { ZoomMode::FitToPage };
+
+        let dx = (self.rect.width() - self.frame.width()) as i32 / 2;
+        let dy = (self.rect.height() - self.frame.height()) as i32 / 2;
+        let anchor = center - pt!(dx, dy) + self.frame.min;
+        let scale = self.scale;
+
+        if let Some(ref mut r) = self.info.reader {
+            r.zoom_mode = Some(zoom_mode);
+        }
+        self.update(hub);
+
+        let ratio = self.scale / scale;
+        let anchor = pt!((anchor.x as f32 * ratio) as i32,
+                         (anchor.y as f32 * ratio) as i32);
+        self.view_port = anchor - self.page_frame.min - (center - self.rect.min);
+        self.frame = visible_frame(&self.page_frame, &mut self.view_port, &self.rect);
+        self.update_tool_bar(hub, context);
+    }
+
+    // Steps the font size of reflowable documents, or the zoom level of fixed layout ones.
+    // The gestures are coalesced: the document is laid out once they stop.
+    fn rescale(&mut self, ge: &GestureEvent, hub: &Hub, context: &mut Context) {
+        let (starts, ends, strength, sign) = match *ge {
+            GestureEvent::Spread { starts, ends, strength, .. } => (starts, ends, strength, 1.0),
+            GestureEvent::Pinch { starts, ends, strength, .. } => (starts, ends, strength, -1.0),
+            // Scales up when the fingers move apart, like a spread.
+            GestureEvent::MultiSwipe { starts, ends, .. } => {
+                let delta = (ends[1] - ends[0]).length() - (starts[1] - starts[0]).length();
+                (starts, ends, delta.abs() as u32, if delta < 0.0 { -1.0 } else { 1.0 })
+            },
+            _ => return,
+        };
+
+        let target = match self.scale_target.take() {
+            Some(target) => target,
+            None => {
+                if let Some(zoom) = self.info.reader.as_ref().and_then(|r| r.reflow) {
+                    ScaleTarget::ReflowZoom(zoom)
+                } else if let Some(fit_scale) = self.fit_scale() {
+                    ScaleTarget::Zoom(100.0 * self.scale / fit_scale, Point::default())
+                } else {
+                    let font_size = self.info.reader.as_ref().and_then(|r| r.font_size)
+                                        .unwrap_or(context.settings.reader.font_size);
+                    ScaleTarget::FontSize(font_size)
+                }
+            },
+        };
+
+        let steps = 1.0 + (strength as f32 / mm_to_px(PINCH_STEP_DISTANCE, CURRENT_DEVICE.dpi)).floor();
+
+        let target = match target {
+            ScaleTarget::FontSize(font_size) => {
+                let default_font_size = context.settings.reader.font_size;
+                ScaleTarget::FontSize((font_size + sign * steps * FONT_SIZE_STEP)
+                                          .max(default_font_size / 2.0)
+                                          .min(3.0 * default_font_size / 2.0))
+            },
+            ScaleTarget::ReflowZoom(zoom) => {
+                ScaleTarget::ReflowZoom((zoom + sign * steps * REFLOW_ZOOM_STEP)
+                                            .max(MIN_REFLOW_ZOOM)
+                                            .min(MAX_REFLOW_ZOOM))
+            },
+            ScaleTarget::Zoom(percent, _) => {
+                let factor = (ends[1] - ends[0]).length() / (starts[1] - starts[0]).length().max(1.0);
+                ScaleTarget::Zoom((percent * factor).max(100.0).min(MAX_ZOOM_PERCENT as f32),
+                                  (ends[0] + ends[1]) / 2)
+            },
+        };
+
+        if let Some(index) = locate_by_id(self, ViewId::ScaleIndicator) {
+            let indicator = self.child_mut(index).downcast_mut::<ScaleIndicator>().unwrap();
+            indicator.update(target.label(), hub);
+        } else {
+            let indicator = ScaleIndicator::new(&self.rect, target.label(), context);
+            hub.send(Event::Render(*indicator.rect(), UpdateMode::Gui)).unwrap();
+            self.children.push(Box::new(indicator) as Box<View>);
+        }
+
+        self.scale_target = Some(target);
+        self.rescale_id = self.rescale_id.wrapping_add(1);
+
+        let id = self.rescale_id;
+        let hub2 = hub.clone();
+        thread::spawn(move || {
+            thread::sleep(RESCALE_DELAY);
+            hub2.send(Event::Rescale(id)).unwrap();
+        });
+    }
+
+    fn apply_scale_target(&mut self, hub: &Hub, context: &mut Context) {
+        if let Some(index) = locate_by_id(self, ViewId::ScaleIndicator) {
+            hub.send(Event::Expose(*self.child(index).rect(), UpdateMode::Gui)).unwrap();
+            self.children.remove(index);
+        }
+
+        match self.scale_target.take() {
+            Some(ScaleTarget::FontSize(font_size)) => self.set_font_size(font_size, hub, context),
+            Some(ScaleTarget::ReflowZoom(zoom)) => self.set_reflow(Some(zoom), hub, context),
+            Some(ScaleTarget::Zoom(percent, center)) => self.zoom_to(percent, center, hub, context),
+            None => (),
+        }
+    }
+
     fn update_bottom_bar(&mut self, hub: &Hub) {
         if let Some(index) = locate::<BottomBar>(self) {
             let current_page = self.current_page;
@@ -415,9 +901,11 @@ impl Reader {
                 previous_page: doc.resolve_location(Location::Previous(current_page)),
                 next_page: doc.resolve_location(Location::Next(current_page)),
             };
-            bottom_bar.update_page_label(self.current_page, self.pages_count, hub);
+            let label = self.page_labels.as_ref().and_then(|l| l.label(current_page as usize));
+            bottom_bar.update_page_label(self.current_page, self.pages_count, label, hub);
             bottom_bar.update_icons(&neighbors, hub);
-            let chapter = doc.toc().as_ref().and_then(|t| chapter_at(t, current_page))
+            let chapter = document_toc(doc.as_mut(), &self.info).as_ref()
+                                  .and_then(|t| chapter_at(t, current_page))
                                    .map(|c| c.title.clone())
                                    .unwrap_or_default();
             bottom_bar.update_chapter(chapter, hub);
@@ -427,7 +915,15 @@ impl Reader {
     fn update_tool_bar(&mut self, hub: &Hub, context: &mut Context) {
         if let Some(index) = locate::<ToolBar>(self) {
             let settings = &context.settings;
+            let is_reflowable = self.doc.lock().unwrap().is_reflowable();
             let tool_bar = self.children[index].as_mut().downcast_mut::<ToolBar>().unwrap();
+            if !is_reflowable {
+                let reflow = self.info.reader.as_ref().and_then(|r| r.reflow);
+                tool_bar.update_reflow(reflow, hub);
+                let zoom_mode = self.info.reader.as_ref().and_then(|r| r.zoom_mode).unwrap_or_default();
+                tool_bar.update_zoom_mode(zoom_mode, hub);
+                return;
+            }
             let font_family = self.info.reader.as_ref()
                                   .and_then(|r| r.font_family.clone())
                                   .unwrap_or_else(|| settings.reader.font_family.clone());
@@ -464,6 +960,7 @@ impl Reader {
     }
 
     fn update(&mut self, hub: &Hub) {
+        self.cancel_prerender();
         self.page_turns += 1;
         let update_mode = if self.refresh_every > 0 {
             if self.page_turns % (self.refresh_every as usize) == 0 {
@@ -474,21 +971,182 @@ impl Reader {
         } else {
             UpdateMode::Partial
         };
-        let margin = self.info.reader.as_ref()
-                         .and_then(|r| r.cropping_margins.as_ref()
-                                        .map(|c| c.margin(self.current_page as usize)))
-                         .cloned().unwrap_or_default();
-        let mut doc = self.doc.lock().unwrap();
-        let ((pixmap, location), scale) = build_pixmap(&self.rect, doc.as_mut(), self.current_page, &margin);
-        self.current_page = location;
-        self.pixmap = Rc::new(pixmap);
-        let frame = rect![(margin.left * self.pixmap.width as f32).ceil() as i32,
-                          (margin.top * self.pixmap.height as f32).ceil() as i32,
-                          ((1.0 - margin.right) * self.pixmap.width as f32).floor() as i32,
-                          ((1.0 - margin.bottom) * self.pixmap.height as f32).floor() as i32];
-        self.frame = frame;
-        self.scale = scale;
+        let zoom_mode = self.zoom_mode();
+        let margin = if zoom_mode == ZoomMode::Columns {
+            self.column_margin()
+        } else {
+            self.cropping_margin()
+        };
+        {
+            let mut doc = self.doc.lock().unwrap();
+            let scale = page_scale(&self.rect, doc.as_ref(), self.current_page, &margin, zoom_mode);
+            let key = PixmapKey::new(self.current_page, scale, &margin);
+            let cached = self.cache.lock().unwrap().get(&key);
+            let (pixmap, location) = match cached {
+                Some(entry) => entry,
+                None => {
+                    let (mut pixmap, location) = doc.pixmap(Location::Exact(self.current_page), scale).unwrap();
+                    if let Some(adjustments) = self.info.reader.as_ref().and_then(|r| r.image_adjustments) {
+                        adjust_pixmap(&mut pixmap, &adjustments);
+                    }
+                    let pixmap = Arc::new(pixmap);
+                    let mut cache = self.cache.lock().unwrap();
+                    let generation = cache.generation();
+                    cache.insert(generation, key, Arc::clone(&pixmap), location);
+                    (pixmap, location)
+                },
+            };
+            self.current_page = location;
+            self.pixmap = pixmap;
+            self.scale = scale;
+        }
+        self.page_frame = rect![(margin.left * self.pixmap.width as f32).ceil() as i32,
+                                (margin.top * self.pixmap.height as f32).ceil() as i32,
+                                ((1.0 - margin.right) * self.pixmap.width as f32).floor() as i32,
+                                ((1.0 - margin.bottom) * self.pixmap.height as f32).floor() as i32];
+        self.frame = visible_frame(&self.page_frame, &mut self.view_port, &self.rect);
         hub.send(Event::Render(self.rect, update_mode)).unwrap();
+        self.prerender();
+    }
+
+    // Renders the neighbors of the current page in the background.
+    // The pages whose margins depend on their content aren't rendered in advance.
+    fn prerender(&mut self) {
+        let zoom_mode = self.zoom_mode();
+        let cropping_margins = self.info.reader.as_ref()
+                                   .filter(|r| r.reflow.is_none())
+                                   .and_then(|r| r.cropping_margins.clone());
+
+        if zoom_mode == ZoomMode::Columns || cropping_margins.as_ref().map_or(false, |c| c.is_auto()) {
+            return;
+        }
+
+        let adjustments = self.info.reader.as_ref().and_then(|r| r.image_adjustments);
+        // A weak reference lets the document be dropped while the worker is idle.
+        let doc = Arc::downgrade(&self.doc);
+        let cache = Arc::clone(&self.cache);
+        let generation = cache.lock().unwrap().generation();
+        let current_page = self.current_page;
+        let rect = self.rect;
+
+        self.cancel_prerender();
+        self.prerenderer = Some(Worker::spawn(move |running| {
+            for neighloc in &[Location::Next(current_page), Location::Previous(current_page)] {
+                if !running.load(AtomicOrdering::Relaxed) {
+                    break;
+                }
+
+                let doc = match doc.upgrade() {
+                    Some(doc) => doc,
+                    None => break,
+                };
+                let mut doc = doc.lock().unwrap();
+
+                if let Some(location) = doc.resolve_location(*neighloc) {
+                    let margin = cropping_margins.as_ref()
+                                                 .map(|c| c.margin(location as usize).clone())
+                                                 .unwrap_or_default();
+                    let scale = page_scale(&rect, doc.as_ref(), location, &margin, zoom_mode);
+                    let key = PixmapKey::new(location, scale, &margin);
+
+                    if cache.lock().unwrap().contains(&key) {
+                        continue;
+                    }
+
+                    if let Some((mut pixmap, location)) = doc.pixmap(Location::Exact(location), scale) {
+                        if let Some(adjustments) = adjustments {
+                            adjust_pixmap(&mut pixmap, &adjustments);
+                        }
+                        cache.lock().unwrap().insert(generation, key, Arc::new(pixmap), location);
+                    }
+                }
+            }
+        }));
+    }
+
+    // The pixmaps of a cancelled prerenderer are discarded by the cache if the layout changed.
+    fn cancel_prerender(&mut self) {
+        if let Some(worker) = self.prerenderer.take() {
+            worker.cancel();
+        }
+    }
+
+    fn stop_prerender(&mut self) {
+        if let Some(worker) = self.prerenderer.take() {
+            worker.stop();
+        }
+    }
+
+    fn stop_workers(&mut self) {
+        self.stop_prerender();
+        if let Some(worker) = self.toc_synthesizer.take() {
+            worker.stop();
+        }
+    }
+
+    // Prepares a change of the layout of the document, which isn't possible during
+    // a search: the locations of its hits depend on the layout.
+    fn prepare_layout_change(&mut self) -> bool {
+        if self.search.as_ref().map_or(false, |s| s.running.load(AtomicOrdering::Relaxed)) {
+            return false;
+        }
+        self.stop_workers();
+        self.clear_synthetic_toc();
+        true
+    }
+
+    // The locations of the synthesized table of contents depend on the layout,
+    // unless the page numbers are synthetic.
+    fn clear_synthetic_toc(&mut self) {
+        if self.synthetic {
+            return;
+        }
+        if let Some(ref mut r) = self.info.reader {
+            r.synthetic_toc = None;
+        }
+    }
+
+    // Builds, in the background, a table of contents from the headings of the document.
+    fn synthesize_toc(&mut self, hub: &Hub, context: &mut Context) {
+        if self.toc_synthesizer.is_some() {
+            return;
+        }
+
+        let notif = Notification::new(ViewId::SynthesizeTocNotif,
+                                      "Looking for headings.".to_string(),
+                                      &mut context.notification_index,
+                                      context.display.dims,
+                                      &mut context.fonts,
+                                      hub);
+        self.children.push(Box::new(notif) as Box<View>);
+
+        let hub2 = hub.clone();
+        let doc2 = Arc::downgrade(&self.doc);
+
+        self.toc_synthesizer = Some(Worker::spawn(move |running| {
+            let mut lines = Vec::new();
+            let mut loc = Location::Exact(0.0);
+
+            loop {
+                if !running.load(AtomicOrdering::Relaxed) {
+                    return;
+                }
+
+                let doc = match doc2.upgrade() {
+                    Some(doc) => doc,
+                    None => return,
+                };
+                let mut doc = doc.lock().unwrap();
+                if let Some((words, location)) = doc.words(loc) {
+                    lines.extend(text_lines(&words, location));
+                    loc = Location::Next(location);
+                } else {
+                    break;
+                }
+            }
+
+            hub2.send(Event::SynthesizedToc(synthesize_toc(&lines))).ok();
+        }));
     }
 
     fn search(&mut self, text: &str, query: Regex, hub: &Hub) {
@@ -502,10 +1160,16 @@ impl Reader {
         let running = Arc::clone(&s.running);
         let current_page = self.current_page;
         let search_direction = self.search_direction;
+        let synthetic = self.synthetic;
+        let pages_count = self.pages_count;
+        let page_labels = self.page_labels.clone();
+        let toc = document_toc(self.doc.lock().unwrap().as_mut(), &self.info).unwrap_or_default();
 
         thread::spawn(move || {
             let mut loc = Location::Exact(current_page);
             let mut started = false;
+            // The words of the previously searched page, if it's adjacent to the current one.
+            let mut previous: Option<(f64, Vec<BoundedText>)> = None;
 
             loop {
                 if !running.load(AtomicOrdering::Relaxed) {
@@ -514,27 +1178,54 @@ impl Reader {
 
                 let mut doc = doc2.lock().unwrap();
 
-                if let Some((ref words, location)) = doc.words(loc) {
-                    if (location - current_page).abs() < LOCATION_EPSILON && started {
-                        break;
+                if let Some((words, location)) = doc.words(loc) {
+                    let revisited = (location - current_page).abs() < LOCATION_EPSILON && started;
+
+                    // Lay out the two pages in reading order, so that matches can span the page break.
+                    let mut pages = vec![(location, &words)];
+                    if let Some((previous_location, ref previous_words)) = previous {
+                        match search_direction {
+                            LinearDir::Forward => pages.insert(0, (previous_location, previous_words)),
+                            LinearDir::Backward => pages.push((previous_location, previous_words)),
+                        }
                     }
-                    for word in words {
-                        if query.is_match(&word.text) {
-                            if !running.load(AtomicOrdering::Relaxed) {
-                                break;
-                            }
-                            hub2.send(Event::SearchResult(location, word.rect)).unwrap();
+
+                    for (positions, snippet, index) in find_matches(&pages, &query) {
+                        if !running.load(AtomicOrdering::Relaxed) {
+                            break;
+                        }
+                        if !is_new_match(&positions, location, revisited) {
+                            continue;
                         }
+                        let hit_location = positions[0].0;
+                        let heading = chapter_at(&toc, hit_location).map(|c| c.title.clone())
+                                              .unwrap_or_else(|| page_name(hit_location, pages_count, synthetic,
+                                                                           page_labels.as_ref()));
+                        let hit = Hit {
+                            heading,
+                            snippet,
+                            index,
+                            path: PathBuf::default(),
+                            location: hit_location,
+                        };
+                        hub2.send(Event::SearchResult(Box::new(hit), positions)).unwrap();
                     }
+
+                    if revisited {
+                        break;
+                    }
+
                     loc = match search_direction {
                         LinearDir::Forward => Location::Next(location),
                         LinearDir::Backward => Location::Previous(location),
                     };
+                    previous = Some((location, words));
                 } else {
                     loc = match search_direction {
                         LinearDir::Forward => Location::Exact(0.0),
                         LinearDir::Backward => Location::Exact(doc.pages_count()),
                     };
+                    previous = None;
                 }
 
                 started = true;
@@ -698,7 +1389,7 @@ impl Reader {
 
             if let Some(ref s) = self.search {
                 let results_bar = ResultsBar::new(rect, s.current_page,
-                                                  s.highlights.len(), s.results_count,
+                                                  s.highlights.len(), s.results_count, "result",
                                                   !s.running.load(AtomicOrdering::Relaxed));
                 self.children.insert(2, Box::new(results_bar) as Box<View>);
                 let separator = Filler::new(s_rect, BLACK);
@@ -765,7 +1456,8 @@ impl Reader {
                                                         self.rect.max.x,
                                                         self.rect.max.y - 2 * small_height as i32 - small_thickness],
                                                   s.current_page, s.highlights.len(),
-                                                  s.results_count, !s.running.load(AtomicOrdering::Relaxed));
+                                                  s.results_count, "result",
+                                                  !s.running.load(AtomicOrdering::Relaxed));
                 self.children.insert(index, Box::new(results_bar) as Box<View>);
                 index += 1;
 
@@ -822,18 +1514,21 @@ impl Reader {
                                                   self.rect.max.y - small_height as i32 + big_thickness,
                                                   self.rect.max.x,
                                                   self.rect.max.y],
-                                            doc.as_mut(),
+                                            document_toc(doc.as_mut(), &self.info).as_ref()
+                                                                                  .map(Vec::as_slice),
                                             self.current_page,
                                             self.pages_count,
                                             &neighbors,
-                                            self.synthetic);
+                                            self.synthetic,
+                                            self.page_labels.as_ref()
+                                                .and_then(|l| l.label(self.current_page as usize)));
             self.children.insert(index, Box::new(bottom_bar) as Box<View>);
 
             hub.send(Event::Render(self.rect, UpdateMode::Gui)).unwrap();
         }
     }
 
-    fn toggle_go_to_page(&mut self, enable: Option<bool>, id: ViewId, hub: &Hub, fonts: &mut Fonts) {
+    fn toggle_go_to_page(&mut self, enable: Option<bool>, id: ViewId, hub: &Hub, context: &mut Context) {
         let (text, input_id) = if id == ViewId::GoToPage {
             ("Go to page", ViewId::GoToPageInput)
         } else {
@@ -857,7 +1552,7 @@ impl Reader {
                 return;
             }
 
-            let go_to_page = NamedInput::new(text.to_string(), id, input_id, 4, fonts);
+            let go_to_page = NamedInput::new(text.to_string(), id, input_id, 4, context.display.dims, &mut context.fonts);
             hub.send(Event::Render(*go_to_page.rect(), UpdateMode::Gui)).unwrap();
             hub.send(Event::Focus(Some(input_id))).unwrap();
 
@@ -884,10 +1579,38 @@ impl Reader {
                                      .and_then(|r| r.font_family.clone())
                                      .unwrap_or_else(|| context.settings.reader.font_family.clone());
             families.insert(DEFAULT_FONT_FAMILY.to_string());
-            let entries = families.iter().map(|f| EntryKind::RadioButton(f.clone(),
-                                                                         EntryId::SetFontFamily(f.clone()),
-                                                                         *f == current_family)).collect();
-            let font_family_menu = Menu::new(rect, ViewId::FontFamilyMenu, MenuKind::DropDown, entries, &mut context.fonts);
+            let mut entries = vec![EntryKind::SubMenu("Profiles".to_string(), self.typography_entries(context))];
+            // Only the built-in engine lays out the text itself.
+            if self.synthetic {
+                let reader_info = self.info.reader.as_ref();
+                let text_align = reader_info.and_then(|r| r.text_align);
+                let alignments = [("Publisher", None),
+                                  ("Justify", Some(TextAlign::Justify)),
+                                  ("Left", Some(TextAlign::Left))];
+                let align_entries = alignments.iter().map(|&(name, value)| {
+                    EntryKind::RadioButton(name.to_string(), EntryId::SetTextAlign(value), value == text_align)
+                }).collect();
+                entries.extend_from_slice(&[EntryKind::SubMenu("Alignment".to_string(), align_entries),
+                                            EntryKind::CheckBox("Hyphenation".to_string(),
+                                                                EntryId::ToggleHyphenation,
+                                                                reader_info.and_then(|r| r.hyphenation)
+                                                                           .unwrap_or(true)),
+                                            EntryKind::CheckBox("Ignore Publisher Styles".to_string(),
+                                                                EntryId::ToggleIgnoreDocumentCss,
+                                                                reader_info.and_then(|r| r.ignore_document_css)
+                                                                           .unwrap_or(false))]);
+                let tweak_entries = STYLE_TWEAKS.iter().map(|&tweak| {
+                    EntryKind::CheckBox(tweak.label().to_string(),
+                                        EntryId::ToggleStyleTweak(tweak),
+                                        reader_info.map_or(false, |r| r.style_tweaks.contains(&tweak)))
+                }).collect();
+                entries.push(EntryKind::SubMenu("Style Tweaks".to_string(), tweak_entries));
+            }
+            entries.push(EntryKind::Separator);
+            entries.extend(families.iter().map(|f| EntryKind::RadioButton(f.clone(),
+                                                                          EntryId::SetFontFamily(f.clone()),
+                                                                          *f == current_family)));
+            let font_family_menu = Menu::new(rect, ViewId::FontFamilyMenu, MenuKind::DropDown, entries, context.display.dims, &mut context.fonts);
             hub.send(Event::Render(*font_family_menu.rect(), UpdateMode::Gui)).unwrap();
             self.children.push(Box::new(font_family_menu) as Box<View>);
         }
@@ -906,7 +1629,6 @@ impl Reader {
                 return;
             }
 
-            let fonts = &mut context.fonts;
             let font_size = self.info.reader.as_ref().and_then(|r| r.font_size)
                                 .unwrap_or(context.settings.reader.font_size);
             let entries = (0..=20).map(|v| {
@@ -915,7 +1637,7 @@ impl Reader {
                                        EntryId::SetFontSize(v),
                                        (fs - font_size).abs() < 0.05)
             }).collect();
-            let font_size_menu = Menu::new(rect, ViewId::FontSizeMenu, MenuKind::Contextual, entries, fonts);
+            let font_size_menu = Menu::new(rect, ViewId::FontSizeMenu, MenuKind::Contextual, entries, context.display.dims, &mut context.fonts);
             hub.send(Event::Render(*font_size_menu.rect(), UpdateMode::Gui)).unwrap();
             self.children.push(Box::new(font_size_menu) as Box<View>);
         }
@@ -934,7 +1656,6 @@ impl Reader {
                 return;
             }
 
-            let fonts = &mut context.fonts;
             let line_height = self.info.reader.as_ref()
                                   .and_then(|r| r.line_height).unwrap_or(context.settings.reader.line_height);
             let entries = (0..=10).map(|x| {
@@ -943,7 +1664,7 @@ impl Reader {
                                        EntryId::SetLineHeight(x),
                                        (lh - line_height).abs() < 0.05)
             }).collect();
-            let line_height_menu = Menu::new(rect, ViewId::LineHeightMenu, MenuKind::DropDown, entries, fonts);
+            let line_height_menu = Menu::new(rect, ViewId::LineHeightMenu, MenuKind::DropDown, entries, context.display.dims, &mut context.fonts);
             hub.send(Event::Render(*line_height_menu.rect(), UpdateMode::Gui)).unwrap();
             self.children.push(Box::new(line_height_menu) as Box<View>);
         }
@@ -962,19 +1683,18 @@ impl Reader {
                 return;
             }
 
-            let fonts = &mut context.fonts;
             let margin_width = self.info.reader.as_ref().and_then(|r| r.margin_width)
                                    .unwrap_or(context.settings.reader.margin_width);
             let entries = (0..=10).map(|mw| EntryKind::RadioButton(format!("{}", mw),
                                                                   EntryId::SetMarginWidth(mw),
                                                                   mw == margin_width)).collect();
-            let margin_width_menu = Menu::new(rect, ViewId::MarginWidthMenu, MenuKind::DropDown, entries, fonts);
+            let margin_width_menu = Menu::new(rect, ViewId::MarginWidthMenu, MenuKind::DropDown, entries, context.display.dims, &mut context.fonts);
             hub.send(Event::Render(*margin_width_menu.rect(), UpdateMode::Gui)).unwrap();
             self.children.push(Box::new(margin_width_menu) as Box<View>);
         }
     }
 
-    fn toggle_page_menu(&mut self, rect: Rectangle, enable: Option<bool>, hub: &Hub, fonts: &mut Fonts) {
+    fn toggle_page_menu(&mut self, rect: Rectangle, enable: Option<bool>, hub: &Hub, context: &mut Context) {
         if let Some(index) = locate_by_id(self, ViewId::PageMenu) {
             if let Some(true) = enable {
                 return;
@@ -990,16 +1710,24 @@ impl Reader {
             let first_page = self.info.reader.as_ref()
                                  .and_then(|r| r.first_page).unwrap_or(0);
             let current_page = self.current_page as usize;
+            let is_bookmarked = self.info.reader.as_ref()
+                                    .map_or(false, |r| r.find_bookmark(self.current_page).is_ok());
             let entries = vec![EntryKind::CheckBox("First Page".to_string(),
                                                    EntryId::ToggleFirstPage,
-                                                   current_page == first_page)];
-            let page_menu = Menu::new(rect, ViewId::PageMenu, MenuKind::DropDown, entries, fonts);
+                                                   current_page == first_page),
+                               EntryKind::CheckBox("Bookmark".to_string(),
+                                                   EntryId::ToggleBookmark,
+                                                   is_bookmarked),
+                               EntryKind::Separator,
+                               EntryKind::Command("Bookmarks".to_string(),
+                                                  EntryId::ShowBookmarks)];
+            let page_menu = Menu::new(rect, ViewId::PageMenu, MenuKind::DropDown, entries, context.display.dims, &mut context.fonts);
             hub.send(Event::Render(*page_menu.rect(), UpdateMode::Gui)).unwrap();
             self.children.push(Box::new(page_menu) as Box<View>);
         }
     }
 
-    fn toggle_margin_cropper_menu(&mut self, rect: Rectangle, enable: Option<bool>, hub: &Hub, fonts: &mut Fonts) {
+    fn toggle_margin_cropper_menu(&mut self, rect: Rectangle, enable: Option<bool>, hub: &Hub, context: &mut Context) {
         if let Some(index) = locate_by_id(self, ViewId::MarginCropperMenu) {
             if let Some(true) = enable {
                 return;
@@ -1013,16 +1741,31 @@ impl Reader {
             }
 
             let current_page = self.current_page as usize;
-            let is_split = self.info.reader.as_ref()
-                               .and_then(|r| r.cropping_margins
-                                              .as_ref().map(|c| c.is_split()));
+            let scheme = self.info.reader.as_ref()
+                             .and_then(|r| r.cropping_margins.as_ref().map(|c| {
+                                 match *c {
+                                     CroppingMargins::Any(..) => PageScheme::Any,
+                                     CroppingMargins::EvenOdd(..) => PageScheme::EvenOdd,
+                                     CroppingMargins::Auto { .. } => PageScheme::Auto,
+                                 }
+                             }));
 
             let mut entries = vec![EntryKind::RadioButton("Any".to_string(),
                                                           EntryId::ApplyCroppings(current_page, PageScheme::Any),
-                                                          is_split.is_some() && !is_split.unwrap()),
+                                                          scheme == Some(PageScheme::Any)),
                                    EntryKind::RadioButton("Even/Odd".to_string(),
                                                           EntryId::ApplyCroppings(current_page, PageScheme::EvenOdd),
-                                                          is_split.is_some() && is_split.unwrap())];
+                                                          scheme == Some(PageScheme::EvenOdd)),
+                                   EntryKind::RadioButton("Auto".to_string(),
+                                                          EntryId::ApplyCroppings(current_page, PageScheme::Auto),
+                                                          scheme == Some(PageScheme::Auto))];
+
+            if let Some(&CroppingMargins::Auto { ignore_noise }) = self.info.reader.as_ref()
+                                                                       .and_then(|r| r.cropping_margins.as_ref()) {
+                entries.push(EntryKind::CheckBox("Ignore Specks and Page Numbers".to_string(),
+                                                 EntryId::ToggleIgnoreCroppingNoise,
+                                                 ignore_noise));
+            }
 
             let is_applied = self.info.reader.as_ref()
                                  .map(|r| r.cropping_margins.is_some())
@@ -1032,14 +1775,14 @@ impl Reader {
                                             EntryKind::Command("Remove".to_string(), EntryId::RemoveCroppings)]);
             }
 
-            let margin_cropper_menu = Menu::new(rect, ViewId::MarginCropperMenu, MenuKind::DropDown, entries, fonts);
+            let margin_cropper_menu = Menu::new(rect, ViewId::MarginCropperMenu, MenuKind::DropDown, entries, context.display.dims, &mut context.fonts);
             hub.send(Event::Render(*margin_cropper_menu.rect(), UpdateMode::Gui)).unwrap();
             self.children.push(Box::new(margin_cropper_menu) as Box<View>);
         }
     }
 
-    fn toggle_search_menu(&mut self, rect: Rectangle, enable: Option<bool>, hub: &Hub, fonts: &mut Fonts) {
-        if let Some(index) = locate_by_id(self, ViewId::SearchMenu) {
+    fn toggle_reflow_menu(&mut self, rect: Rectangle, enable: Option<bool>, hub: &Hub, context: &mut Context) {
+        if let Some(index) = locate_by_id(self, ViewId::ReflowMenu) {
             if let Some(true) = enable {
                 return;
             }
@@ -1051,39 +1794,131 @@ impl Reader {
                 return;
             }
 
-            let mut entries = vec![EntryKind::RadioButton("Forward".to_string(),
-                                                          EntryId::SearchDirection(LinearDir::Forward),
-                                                          self.search_direction == LinearDir::Forward),
-                                   EntryKind::RadioButton("Backward".to_string(),
-                                                          EntryId::SearchDirection(LinearDir::Backward),
-                                                          self.search_direction == LinearDir::Backward)];
-
-            let kind = if locate::<SearchBar>(self).is_some() {
-                MenuKind::Contextual
-            } else {
-                MenuKind::DropDown
-            };
-            let search_menu = Menu::new(rect, ViewId::SearchMenu, kind, entries, fonts);
-            hub.send(Event::Render(*search_menu.rect(), UpdateMode::Gui)).unwrap();
-            self.children.push(Box::new(search_menu) as Box<View>);
+            let reflow = self.info.reader.as_ref().and_then(|r| r.reflow);
+            let mut entries = vec![EntryKind::CheckBox("Reflow".to_string(),
+                                                       EntryId::ToggleReflow,
+                                                       reflow.is_some()),
+                                   EntryKind::Separator];
+            entries.extend((0..=8).map(|z| {
+                let zoom = 1.0 + z as f32 / 4.0;
+                EntryKind::RadioButton(format!("{:.2}", zoom),
+                                       EntryId::SetReflowZoom(z),
+                                       reflow.map_or(false, |r| (r - zoom).abs() < 0.01))
+            }));
+            let reflow_menu = Menu::new(rect, ViewId::ReflowMenu, MenuKind::Contextual, entries, context.display.dims, &mut context.fonts);
+            hub.send(Event::Render(*reflow_menu.rect(), UpdateMode::Gui)).unwrap();
+            self.children.push(Box::new(reflow_menu) as Box<View>);
         }
     }
 
-    fn toggle_search_bar(&mut self, enable: bool, hub: &Hub, context: &mut Context) {
-        if locate::<SearchBar>(self).is_some() {
-            if enable {
+    fn toggle_zoom_menu(&mut self, rect: Rectangle, enable: Option<bool>, hub: &Hub, context: &mut Context) {
+        if let Some(index) = locate_by_id(self, ViewId::ZoomMenu) {
+            if let Some(true) = enable {
                 return;
             }
 
-            self.toggle_bars(Some(false), hub, context);
-
-            if let Some(ref mut s) = self.search {
-                s.running.store(false, AtomicOrdering::Relaxed);
-            }
-
-            self.search = None;
+            hub.send(Event::Expose(*self.child(index).rect(), UpdateMode::Gui)).unwrap();
+            self.children.remove(index);
         } else {
-            if !enable {
+            if let Some(false) = enable {
+                return;
+            }
+
+            let zoom_mode = self.info.reader.as_ref().and_then(|r| r.zoom_mode).unwrap_or_default();
+            let mut entries = vec![EntryKind::RadioButton("Fit to Page".to_string(),
+                                                          EntryId::SetZoomMode(ZoomMode::FitToPage),
+                                                          zoom_mode == ZoomMode::FitToPage),
+                                   EntryKind::RadioButton("Fit to Width".to_string(),
+                                                          EntryId::SetZoomMode(ZoomMode::FitToWidth),
+                                                          zoom_mode == ZoomMode::FitToWidth),
+                                   EntryKind::RadioButton("Columns".to_string(),
+                                                          EntryId::SetZoomMode(ZoomMode::Columns),
+                                                          zoom_mode == ZoomMode::Columns),
+                                   EntryKind::Separator];
+            let mut percents = vec![150, 200, 300];
+            if let ZoomMode::Custom(percent) = zoom_mode {
+                if !percents.contains(&percent) {
+                    percents.push(percent);
+                    percents.sort();
+                }
+            }
+            entries.extend(percents.into_iter().map(|p| {
+                EntryKind::RadioButton(format!("{}%", p),
+                                       EntryId::SetZoomMode(ZoomMode::Custom(p)),
+                                       zoom_mode == ZoomMode::Custom(p))
+            }));
+            entries.push(EntryKind::Separator);
+            if self.doc.lock().unwrap().has_layers() {
+                let layers = self.info.reader.as_ref().and_then(|r| r.render_layers).unwrap_or_default();
+                let layers_entries = [("Color", RenderLayers::Color),
+                                      ("Mask", RenderLayers::Mask),
+                                      ("Foreground", RenderLayers::Foreground),
+                                      ("Background", RenderLayers::Background)].iter().map(|&(name, l)| {
+                    EntryKind::RadioButton(name.to_string(), EntryId::SetRenderLayers(l), layers == l)
+                }).collect();
+                entries.push(EntryKind::SubMenu("Layers".to_string(), layers_entries));
+            }
+            entries.push(EntryKind::Command("Image Adjustments".to_string(),
+                                            EntryId::ShowImageAdjustments));
+            let zoom_menu = Menu::new(rect, ViewId::ZoomMenu, MenuKind::Contextual, entries, context.display.dims, &mut context.fonts);
+            hub.send(Event::Render(*zoom_menu.rect(), UpdateMode::Gui)).unwrap();
+            self.children.push(Box::new(zoom_menu) as Box<View>);
+        }
+    }
+
+    fn toggle_search_menu(&mut self, rect: Rectangle, enable: Option<bool>, hub: &Hub, context: &mut Context) {
+        if let Some(index) = locate_by_id(self, ViewId::SearchMenu) {
+            if let Some(true) = enable {
+                return;
+            }
+
+            hub.send(Event::Expose(*self.child(index).rect(), UpdateMode::Gui)).unwrap();
+            self.children.remove(index);
+        } else {
+            if let Some(false) = enable {
+                return;
+            }
+
+            let mut entries = vec![EntryKind::RadioButton("Forward".to_string(),
+                                                          EntryId::SearchDirection(LinearDir::Forward),
+                                                          self.search_direction == LinearDir::Forward),
+                                   EntryKind::RadioButton("Backward".to_string(),
+                                                          EntryId::SearchDirection(LinearDir::Backward),
+                                                          self.search_direction == LinearDir::Backward),
+                                   EntryKind::Separator,
+                                   EntryKind::CheckBox("Case Sensitive".to_string(),
+                                                       EntryId::ToggleCaseSensitive,
+                                                       self.case_sensitive),
+                                   EntryKind::CheckBox("Whole Word".to_string(),
+                                                       EntryId::ToggleWholeWord,
+                                                       self.whole_word)];
+
+            let kind = if locate::<SearchBar>(self).is_some() {
+                MenuKind::Contextual
+            } else {
+                MenuKind::DropDown
+            };
+            let search_menu = Menu::new(rect, ViewId::SearchMenu, kind, entries, context.display.dims, &mut context.fonts);
+            hub.send(Event::Render(*search_menu.rect(), UpdateMode::Gui)).unwrap();
+            self.children.push(Box::new(search_menu) as Box<View>);
+        }
+    }
+
+    fn toggle_search_bar(&mut self, enable: bool, hub: &Hub, context: &mut Context) {
+        if locate::<SearchBar>(self).is_some() {
+            if enable {
+                return;
+            }
+
+            self.toggle_bars(Some(false), hub, context);
+
+            if let Some(ref mut s) = self.search {
+                s.running.store(false, AtomicOrdering::Relaxed);
+            }
+
+            self.search = None;
+        } else {
+            if !enable {
                 return;
             }
 
@@ -1134,16 +1969,14 @@ impl Reader {
             let pixmap_rect = rect![self.rect.min + pt!(padding),
                                     self.rect.max - pt!(padding)];
 
-            let margin = self.info.reader.as_ref()
-                             .and_then(|r| r.cropping_margins.as_ref()
-                                            .map(|c| c.margin(self.current_page as usize)))
-                             .cloned().unwrap_or_default();
+            let margin = self.cropping_margin();
 
             let mut doc = self.doc.lock().unwrap();
             let ((pixmap, location), _) = build_pixmap(&pixmap_rect,
                                                        doc.as_mut(),
                                                        self.current_page,
-                                                       &Margin::default());
+                                                       &Margin::default(),
+                                                       ZoomMode::FitToPage);
 
             self.current_page = location;
             let margin_cropper = MarginCropper::new(self.rect, pixmap, &margin);
@@ -1152,8 +1985,115 @@ impl Reader {
         }
     }
 
+    // Returns the text of the word displayed at the given screen position.
+    fn word_at(&mut self, pt: Point) -> Option<String> {
+        let dx = (self.rect.width() - self.frame.width()) as i32 / 2;
+        let dy = (self.rect.height() - self.frame.height()) as i32 / 2;
+
+        let (words, _) = self.doc.lock().ok()
+                             .and_then(|mut doc| doc.words(Location::Exact(self.current_page)))?;
+
+        words.into_iter().find(|word| {
+            let r = word.rect;
+            let rect = rect![(r.min.x as f32 * self.scale) as i32 - self.frame.min.x + dx,
+                             (r.min.y as f32 * self.scale) as i32 - self.frame.min.y + dy,
+                             (r.max.x as f32 * self.scale) as i32 - self.frame.min.x + dx,
+                             (r.max.y as f32 * self.scale) as i32 - self.frame.min.y + dy];
+            rect.includes(pt)
+        }).map(|word| word.text)
+    }
+
+    fn toggle_dictionary(&mut self, word: &str, origin: Point, enable: bool, hub: &Hub, context: &mut Context) {
+        if let Some(index) = locate_by_id(self, ViewId::Dictionary) {
+            hub.send(Event::Expose(*self.child(index).rect(), UpdateMode::Gui)).unwrap();
+            self.children.remove(index);
+        }
+
+        if enable {
+            let dictionary = DictionaryWindow::new(word, &self.info.language, origin, hub, context);
+            self.children.push(Box::new(dictionary) as Box<View>);
+        }
+    }
+
+    fn toggle_adjustments_window(&mut self, enable: bool, hub: &Hub, context: &mut Context) {
+        if let Some(index) = locate_by_id(self, ViewId::ImageAdjustments) {
+            hub.send(Event::Expose(*self.child(index).rect(), UpdateMode::Gui)).unwrap();
+            self.children.remove(index);
+        }
+
+        if enable {
+            let adjustments = self.info.reader.as_ref()
+                                  .and_then(|r| r.image_adjustments)
+                                  .unwrap_or_default();
+            let adjustments_window = AdjustmentsWindow::new(&adjustments, context);
+            hub.send(Event::Render(*adjustments_window.rect(), UpdateMode::Gui)).unwrap();
+            self.children.push(Box::new(adjustments_window) as Box<View>);
+        }
+    }
+
+    fn set_render_layers(&mut self, layers: RenderLayers, hub: &Hub) {
+        if let Some(ref mut r) = self.info.reader {
+            r.render_layers = if layers == RenderLayers::Color {
+                None
+            } else {
+                Some(layers)
+            };
+        }
+        self.doc.lock().unwrap().set_render_layers(layers);
+        self.cache.lock().unwrap().clear();
+        self.update(hub);
+    }
+
+    fn set_image_adjustment(&mut self, id: SliderId, value: f32, hub: &Hub) {
+        if let Some(ref mut r) = self.info.reader {
+            let mut adjustments = r.image_adjustments.unwrap_or_default();
+            match id {
+                SliderId::Gamma => adjustments.gamma = value,
+                SliderId::Contrast => adjustments.contrast = value,
+                SliderId::TintRemoval => adjustments.tint_removal = value,
+                SliderId::Binarization => adjustments.binarization = value,
+                _ => return,
+            }
+            r.image_adjustments = if adjustments.is_identity() {
+                None
+            } else {
+                Some(adjustments)
+            };
+        }
+        self.cache.lock().unwrap().clear();
+        self.update(hub);
+    }
+
+    fn toggle_search_results(&mut self, enable: Option<bool>, hub: &Hub) {
+        if let Some(index) = locate_by_id(self, ViewId::SearchResults) {
+            if let Some(true) = enable {
+                return;
+            }
+
+            self.children.remove(index);
+            hub.send(Event::Render(self.rect, UpdateMode::Gui)).unwrap();
+        } else {
+            if let Some(false) = enable {
+                return;
+            }
+
+            if let Some(ref s) = self.search {
+                let mut search_results = SearchResults::new(self.rect, ViewId::SearchResults,
+                                                            s.query.clone(), "result",
+                                                            !s.running.load(AtomicOrdering::Relaxed),
+                                                            None);
+                // Start on the page of the first result after the current location.
+                let current_hit = s.hits.iter().position(|h| h.location >= self.current_page)
+                                   .unwrap_or(0);
+                search_results.set_items(s.hits.clone(), current_hit, hub);
+                self.children.push(Box::new(search_results) as Box<View>);
+                hub.send(Event::Render(self.rect, UpdateMode::Gui)).unwrap();
+            }
+        }
+    }
+
     fn set_font_size(&mut self, font_size: f32, hub: &Hub, context: &mut Context) {
-        if Arc::strong_count(&self.doc) > 1 {
+        if !self.prepare_layout_change() {
             return;
         }
 
@@ -1161,7 +2101,7 @@ impl Reader {
             r.font_size = Some(font_size);
         }
 
-        let (width, height) = CURRENT_DEVICE.dims;
+        let (width, height) = (self.rect.width(), self.rect.height());
         {
             let mut doc = self.doc.lock().unwrap();
 
@@ -1174,13 +2114,89 @@ impl Reader {
             }
         }
 
+        self.cache.lock().unwrap().clear();
+        self.update(hub);
+        self.update_tool_bar(hub, context);
+        self.update_bottom_bar(hub);
+    }
+
+    fn set_text_align(&mut self, text_align: Option<TextAlign>, hub: &Hub, context: &mut Context) {
+        if !self.prepare_layout_change() {
+            return;
+        }
+
+        if let Some(ref mut r) = self.info.reader {
+            r.text_align = text_align;
+        }
+
+        self.doc.lock().unwrap().set_text_align(text_align);
+        self.relayout(hub, context);
+    }
+
+    fn toggle_hyphenation(&mut self, hub: &Hub, context: &mut Context) {
+        if !self.prepare_layout_change() {
+            return;
+        }
+
+        let enable = !self.info.reader.as_ref().and_then(|r| r.hyphenation).unwrap_or(true);
+
+        if let Some(ref mut r) = self.info.reader {
+            r.hyphenation = Some(enable);
+        }
+
+        self.doc.lock().unwrap().set_hyphenation(enable);
+        self.relayout(hub, context);
+    }
+
+    fn toggle_ignore_document_css(&mut self, hub: &Hub, context: &mut Context) {
+        if !self.prepare_layout_change() {
+            return;
+        }
+
+        let ignore = !self.info.reader.as_ref().and_then(|r| r.ignore_document_css).unwrap_or(false);
+
+        if let Some(ref mut r) = self.info.reader {
+            r.ignore_document_css = Some(ignore);
+        }
+
+        self.doc.lock().unwrap().set_ignore_document_css(ignore);
+        self.relayout(hub, context);
+    }
+
+    fn toggle_style_tweak(&mut self, tweak: StyleTweak, hub: &Hub, context: &mut Context) {
+        if !self.prepare_layout_change() {
+            return;
+        }
+
+        if let Some(ref mut r) = self.info.reader {
+            if let Some(index) = r.style_tweaks.iter().position(|t| *t == tweak) {
+                r.style_tweaks.remove(index);
+            } else {
+                r.style_tweaks.push(tweak);
+            }
+        }
+
+        let css = user_stylesheet(&self.info, &context.settings.reader);
+        self.doc.lock().unwrap().set_user_stylesheet(&css);
+        self.relayout(hub, context);
+    }
+
+    // Updates the pages after a change of the layout settings of the document.
+    fn relayout(&mut self, hub: &Hub, context: &mut Context) {
+        if !self.synthetic {
+            let doc = self.doc.lock().unwrap();
+            self.pages_count = doc.pages_count();
+            self.current_page = self.current_page.min(self.pages_count - 1.0);
+        }
+
+        self.cache.lock().unwrap().clear();
         self.update(hub);
         self.update_tool_bar(hub, context);
         self.update_bottom_bar(hub);
     }
 
     fn set_font_family(&mut self, font_family: &str, hub: &Hub, context: &mut Context) {
-        if Arc::strong_count(&self.doc) > 1 {
+        if !self.prepare_layout_change() {
             return;
         }
 
@@ -1204,13 +2220,14 @@ impl Reader {
             }
         }
 
+        self.cache.lock().unwrap().clear();
         self.update(hub);
         self.update_tool_bar(hub, context);
         self.update_bottom_bar(hub);
     }
 
     fn set_line_height(&mut self, line_height: f32, hub: &Hub, context: &mut Context) {
-        if Arc::strong_count(&self.doc) > 1 {
+        if !self.prepare_layout_change() {
             return;
         }
 
@@ -1228,13 +2245,14 @@ impl Reader {
             }
         }
 
+        self.cache.lock().unwrap().clear();
         self.update(hub);
         self.update_tool_bar(hub, context);
         self.update_bottom_bar(hub);
     }
 
     fn set_margin_width(&mut self, width: i32, hub: &Hub, context: &mut Context) {
-        if Arc::strong_count(&self.doc) > 1 {
+        if !self.prepare_layout_change() {
             return;
         }
 
@@ -1252,29 +2270,385 @@ impl Reader {
             }
         }
 
+        self.cache.lock().unwrap().clear();
         self.update(hub);
         self.update_tool_bar(hub, context);
         self.update_bottom_bar(hub);
     }
 
-    fn add_remove_bookmark(&mut self, hub: &Hub) {
-        let current_page = self.current_page;
+    fn typography_entries(&self, context: &Context) -> Vec<EntryKind> {
+        let kind = &self.info.file.kind;
+        let categories = &self.info.categories;
+        let mut entries: Vec<EntryKind> = context.settings.reader.typography_profiles.iter().enumerate().map(|(index, p)| {
+            let mut profile_entries = vec![EntryKind::Command("Apply".to_string(),
+                                                              EntryId::ApplyTypographyProfile(index)),
+                                           EntryKind::Separator,
+                                           EntryKind::CheckBox(format!("Default for {}", kind.to_uppercase()),
+                                                               EntryId::ToggleTypographyProfileKind(index),
+                                                               p.kinds.contains(kind))];
+            profile_entries.extend(categories.iter().map(|c| {
+                EntryKind::CheckBox(format!("Default for {}", c),
+                                    EntryId::ToggleTypographyProfileCategory(index, c.clone()),
+                                    p.categories.contains(c))
+            }));
+            EntryKind::SubMenu(p.name.clone(), profile_entries)
+        }).collect();
+        if !entries.is_empty() {
+            entries.push(EntryKind::Separator);
+        }
+        entries.push(EntryKind::Command("Save As…".to_string(), EntryId::SaveTypographyProfile));
+        entries
+    }
+
+    // Returns the typography of the current book, as a profile with the given name.
+    fn typography_profile(&self, name: String, context: &Context) -> TypographyProfile {
+        let settings = &context.settings.reader;
+        let reader_info = self.info.reader.as_ref();
+        TypographyProfile {
+            name,
+            font_family: reader_info.and_then(|r| r.font_family.clone())
+                                    .unwrap_or_else(|| settings.font_family.clone()),
+            font_size: reader_info.and_then(|r| r.font_size).unwrap_or(settings.font_size),
+            margin_width: reader_info.and_then(|r| r.margin_width).unwrap_or(settings.margin_width),
+            line_height: reader_info.and_then(|r| r.line_height).unwrap_or(settings.line_height),
+            text_align: reader_info.and_then(|r| r.text_align),
+            hyphenation: reader_info.and_then(|r| r.hyphenation).unwrap_or(true),
+            .. Default::default()
+        }
+    }
+
+    fn apply_typography_profile(&mut self, index: usize, hub: &Hub, context: &mut Context) {
+        if !self.prepare_layout_change() {
+            return;
+        }
+
+        let profile = match context.settings.reader.typography_profiles.get(index) {
+            Some(profile) => profile.clone(),
+            None => return,
+        };
+
         if let Some(ref mut r) = self.info.reader {
-            if let Ok(index) = r.bookmarks.binary_search_by(|a| a.partial_cmp(&current_page)
-                                                                 .unwrap_or(Ordering::Equal)) {
-                r.bookmarks.remove(index);
+            record_typography(r, &profile);
+        }
+
+        {
+            let mut doc = self.doc.lock().unwrap();
+            set_typography(doc.as_mut(), &profile, (self.rect.width(), self.rect.height()),
+                           &context.settings.reader.font_path);
+
+            if !self.synthetic {
+                let ratio = doc.pages_count() / self.pages_count;
+                self.pages_count = doc.pages_count();
+                self.current_page = (ratio * self.current_page).min(self.pages_count - 1.0);
+            }
+        }
+
+        self.cache.lock().unwrap().clear();
+        self.update(hub);
+        self.update_tool_bar(hub, context);
+        self.update_bottom_bar(hub);
+    }
+
+    // Saves the typography of the current book under the given name, replacing
+    // the values of the profile with the same name, if any.
+    fn save_typography_profile(&mut self, name: &str, hub: &Hub, context: &mut Context) {
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+
+        let profile = self.typography_profile(name.to_string(), context);
+        let profiles = &mut context.settings.reader.typography_profiles;
+
+        if let Some(p) = profiles.iter_mut().find(|p| p.name == name) {
+            let kinds = p.kinds.clone();
+            let categories = p.categories.clone();
+            *p = TypographyProfile { kinds, categories, .. profile };
+        } else {
+            profiles.push(profile);
+        }
+
+        hub.send(Event::SaveSettings).unwrap();
+    }
+
+    fn toggle_save_typography_profile(&mut self, enable: Option<bool>, hub: &Hub, context: &mut Context) {
+        if let Some(index) = locate_by_id(self, ViewId::SaveTypographyProfile) {
+            if let Some(true) = enable {
+                return;
+            }
+
+            hub.send(Event::Expose(*self.child(index).rect(), UpdateMode::Gui)).unwrap();
+            self.children.remove(index);
+
+            if self.focus == Some(ViewId::SaveTypographyProfileInput) {
+                self.toggle_keyboard(false, None, hub);
+                hub.send(Event::Focus(None)).unwrap();
+            }
+        } else {
+            if let Some(false) = enable {
+                return;
+            }
+
+            let save_profile = NamedInput::new("Profile name".to_string(), ViewId::SaveTypographyProfile,
+                                               ViewId::SaveTypographyProfileInput, 16, context.display.dims, &mut context.fonts);
+            hub.send(Event::Render(*save_profile.rect(), UpdateMode::Gui)).unwrap();
+            hub.send(Event::Focus(Some(ViewId::SaveTypographyProfileInput))).unwrap();
+
+            self.focus = Some(ViewId::SaveTypographyProfileInput);
+            self.children.push(Box::new(save_profile) as Box<View>);
+        }
+    }
+
+    // Reopens the document, reflowed at the given zoom factor if any.
+    fn set_reflow(&mut self, reflow: Option<f32>, hub: &Hub, context: &mut Context) {
+        if !self.prepare_layout_change() {
+            return;
+        }
+
+        let settings = &context.settings;
+        let path = settings.library_path.join(&self.info.file.path);
+        let opener = DocumentOpener::new(settings.reader.epub_engine);
+
+        if let Some(mut doc) = opener.open(&path) {
+            if doc.is_reflowable() || !unlock(doc.as_mut(), &self.info.file.path, context) {
+                return;
+            }
+
+            if let Some(layers) = self.info.reader.as_ref().and_then(|r| r.render_layers) {
+                doc.set_render_layers(layers);
+            }
+
+            if let Some(zoom) = reflow {
+                doc = Box::new(ReflowDocument::new(doc, zoom));
+            }
+
+            let (width, height) = (self.rect.width(), self.rect.height());
+            doc.layout(width, height, settings.reader.font_size, CURRENT_DEVICE.dpi);
+            self.page_labels = doc.page_labels();
+
+            // The screens of the reflowed pages have fractional locations, the source pages don't.
+            let page = self.current_page.floor();
+            self.current_page = if reflow.is_some() {
+                doc.resolve_location(Location::Exact(self.current_page)).unwrap_or(page)
             } else {
-                r.bookmarks.push(self.current_page);
-                r.bookmarks.sort_unstable_by(|a, b| a.partial_cmp(b)
-                                                     .unwrap_or(Ordering::Equal));
+                page
+            };
+
+            *self.doc.lock().unwrap() = doc;
+
+            if let Some(ref mut r) = self.info.reader {
+                r.reflow = reflow;
             }
         }
+
+        self.cache.lock().unwrap().clear();
         self.update(hub);
+        self.update_tool_bar(hub, context);
+        self.update_bottom_bar(hub);
+    }
+
+    pub fn resize(&mut self, rect: Rectangle, hub: &Hub, context: &mut Context) {
+        if let Some(s) = self.search.take() {
+            s.running.store(false, AtomicOrdering::Relaxed);
+        }
+
+        self.stop_workers();
+        self.clear_synthetic_toc();
+        self.children.clear();
+        self.focus = None;
+        self.rect = rect;
+        self.view_port = Point::default();
+
+        let font_size = self.info.reader.as_ref().and_then(|r| r.font_size)
+                            .unwrap_or(context.settings.reader.font_size);
+        {
+            let mut doc = self.doc.lock().unwrap();
+
+            doc.layout(rect.width(), rect.height(), font_size, CURRENT_DEVICE.dpi);
+
+            if !self.synthetic {
+                let ratio = doc.pages_count() / self.pages_count;
+                self.pages_count = doc.pages_count();
+                self.current_page = (ratio * self.current_page).min(self.pages_count - 1.0);
+            }
+        }
+
+        if let Some(ref mut r) = self.info.reader {
+            let rotation = context.display.rotation;
+            r.rotation = if rotation == context.settings.rotation { None } else { Some(rotation) };
+        }
+
+        self.cache.lock().unwrap().clear();
+        self.update(hub);
+    }
+
+    fn add_remove_bookmark(&mut self, hub: &Hub) {
+        let current_page = self.current_page;
+        let search = self.info.reader.as_ref().map(|r| r.find_bookmark(current_page));
+        match search {
+            Some(Ok(index)) => {
+                if let Some(ref mut r) = self.info.reader {
+                    r.bookmarks.remove(index);
+                }
+            },
+            Some(Err(index)) => {
+                let bookmark = self.make_bookmark(current_page);
+                if let Some(ref mut r) = self.info.reader {
+                    r.bookmarks.insert(index, bookmark);
+                }
+            },
+            None => (),
+        }
+        self.update(hub);
+    }
+
+    // Captures the chapter and the beginning of the text of the given location.
+    fn make_bookmark(&mut self, location: f64) -> Bookmark {
+        let mut bookmark = Bookmark::new(location);
+        let mut doc = self.doc.lock().unwrap();
+        if let Some(chapter) = document_toc(doc.as_mut(), &self.info).as_ref()
+                                           .and_then(|toc| chapter_at(toc, location)) {
+            bookmark.chapter = chapter.title.clone();
+        }
+        if let Some((words, _)) = doc.words(Location::Exact(location)) {
+            for word in words.iter().map(|w| w.text.trim()).filter(|t| !t.is_empty()) {
+                if bookmark.excerpt.len() >= EXCERPT_LENGTH {
+                    break;
+                }
+                if !bookmark.excerpt.is_empty() {
+                    bookmark.excerpt.push(' ');
+                }
+                bookmark.excerpt.push_str(word);
+            }
+        }
+        bookmark
+    }
+
+    fn bookmark_items(&self) -> Vec<BookmarkItem> {
+        self.info.reader.as_ref().map_or_else(Vec::new, |r| {
+            r.bookmarks.iter().enumerate().map(|(index, b)| {
+                let heading = if !b.name.is_empty() {
+                    b.name.clone()
+                } else if !b.chapter.is_empty() {
+                    b.chapter.clone()
+                } else {
+                    b.created.format("%B %-d, %Y").to_string()
+                };
+                let page = page_name(b.location, self.pages_count, self.synthetic, self.page_labels.as_ref());
+                BookmarkItem { index, heading, page, excerpt: b.excerpt.clone() }
+            }).collect()
+        })
+    }
+
+    fn toggle_bookmarks(&mut self, enable: Option<bool>, hub: &Hub, context: &mut Context) {
+        if let Some(index) = locate_by_id(self, ViewId::Bookmarks) {
+            if let Some(true) = enable {
+                return;
+            }
+
+            self.children.remove(index);
+            hub.send(Event::Render(self.rect, UpdateMode::Gui)).unwrap();
+        } else {
+            if let Some(false) = enable {
+                return;
+            }
+
+            let items = self.bookmark_items();
+            if items.is_empty() {
+                let notif = Notification::new(ViewId::NoBookmarksNotif,
+                                              "No bookmarks.".to_string(),
+                                              &mut context.notification_index,
+                                              context.display.dims,
+                                              &mut context.fonts,
+                                              hub);
+                self.children.push(Box::new(notif) as Box<View>);
+                return;
+            }
+
+            self.toggle_bars(Some(false), hub, context);
+            // Start on the page of the first bookmark after the current location.
+            let current_item = self.info.reader.as_ref()
+                                   .and_then(|r| r.bookmarks.iter()
+                                                  .position(|b| b.location >= self.current_page))
+                                   .unwrap_or(0);
+            let mut bookmarks = Bookmarks::new(self.rect, ViewId::Bookmarks, "Bookmarks".to_string(),
+                                               "bookmark", true, None);
+            bookmarks.set_items(items, current_item, hub);
+            self.children.push(Box::new(bookmarks) as Box<View>);
+            hub.send(Event::Render(self.rect, UpdateMode::Gui)).unwrap();
+        }
+    }
+
+    // Refreshes the bookmarks list, and shows the page of the bookmark at the given index.
+    fn update_bookmarks(&mut self, index: usize, hub: &Hub) {
+        let items = self.bookmark_items();
+        if let Some(child_index) = locate_by_id(self, ViewId::Bookmarks) {
+            let bookmarks = self.child_mut(child_index).downcast_mut::<Bookmarks>().unwrap();
+            bookmarks.set_items(items, index, hub);
+        }
+    }
+
+    fn toggle_bookmark_menu(&mut self, index: usize, rect: Rectangle, enable: Option<bool>, hub: &Hub, context: &mut Context) {
+        if let Some(index) = locate_by_id(self, ViewId::BookmarkMenu) {
+            if let Some(true) = enable {
+                return;
+            }
+
+            hub.send(Event::Expose(*self.child(index).rect(), UpdateMode::Gui)).unwrap();
+            self.children.remove(index);
+        } else {
+            if let Some(false) = enable {
+                return;
+            }
+
+            let entries = vec![EntryKind::Command("Rename".to_string(), EntryId::RenameBookmark(index)),
+                               EntryKind::Command("Remove".to_string(), EntryId::RemoveBookmark(index))];
+            let bookmark_menu = Menu::new(rect, ViewId::BookmarkMenu, MenuKind::Contextual, entries, context.display.dims, &mut context.fonts);
+            hub.send(Event::Render(*bookmark_menu.rect(), UpdateMode::Gui)).unwrap();
+            self.children.push(Box::new(bookmark_menu) as Box<View>);
+        }
+    }
+
+    // The keyboard would be hidden by the bookmarks list, which is therefore closed during the renaming.
+    fn toggle_rename_bookmark(&mut self, index: Option<usize>, hub: &Hub, context: &mut Context) {
+        if let Some(child_index) = locate_by_id(self, ViewId::RenameBookmark) {
+            hub.send(Event::Expose(*self.child(child_index).rect(), UpdateMode::Gui)).unwrap();
+            self.children.remove(child_index);
+
+            if self.focus == Some(ViewId::RenameBookmarkInput) {
+                self.toggle_keyboard(false, None, hub);
+                hub.send(Event::Focus(None)).unwrap();
+            }
+
+            if let Some(index) = self.renamed_bookmark.take() {
+                self.toggle_bookmarks(Some(true), hub, context);
+                self.update_bookmarks(index, hub);
+            }
+        }
+
+        if let Some(index) = index {
+            let name = match self.info.reader.as_ref().and_then(|r| r.bookmarks.get(index)) {
+                Some(bookmark) => bookmark.name.clone(),
+                None => return,
+            };
+
+            self.toggle_bookmarks(Some(false), hub, context);
+
+            let rename_bookmark = NamedInput::new("Name".to_string(), ViewId::RenameBookmark,
+                                                  ViewId::RenameBookmarkInput, 16, context.display.dims, &mut context.fonts)
+                                             .text(&name);
+            hub.send(Event::Render(*rename_bookmark.rect(), UpdateMode::Gui)).unwrap();
+            hub.send(Event::Focus(Some(ViewId::RenameBookmarkInput))).unwrap();
+
+            self.focus = Some(ViewId::RenameBookmarkInput);
+            self.renamed_bookmark = Some(index);
+            self.children.push(Box::new(rename_bookmark) as Box<View>);
+        }
     }
 
     fn crop_margins(&mut self, index: usize, margin: &Margin, hub: &Hub) {
         if let Some(r) = self.info.reader.as_mut() {
-            if r.cropping_margins.is_none() {
+            if r.cropping_margins.as_ref().map_or(true, |c| c.is_auto()) {
                 r.cropping_margins = Some(CroppingMargins::Any(Margin::default()));
             }
             for c in r.cropping_margins.iter_mut() {
@@ -1292,7 +2666,193 @@ impl Reader {
             hub.send(Event::ClockTick).unwrap();
             hub.send(Event::BatteryTick).unwrap();
         }
-        hub.send(Event::Render(self.rect, UpdateMode::Gui)).unwrap();
+        hub.send(Event::Render(self.rect, UpdateMode::Gui)).unwrap();
+    }
+
+    // Saves the current reading session, and adds it to the statistics of the book.
+    fn record_session(&mut self, context: &mut Context) {
+        let session = self.session.take().and_then(|s| s.finish(&self.info.file.path));
+        if let Some(session) = session {
+            if let Some(ref mut r) = self.info.reader {
+                r.statistics.get_or_insert_with(Default::default).add(&session);
+            }
+            save_session(&session, context).map_err(|e| eprintln!("Can't save session: {}", e)).ok();
+        }
+    }
+
+    fn show_statistics(&mut self, hub: &Hub, context: &mut Context) {
+        if let Some(ref mut r) = self.info.reader {
+            r.current_page = self.current_page;
+            r.pages_count = self.pages_count;
+        }
+
+        let html = if self.ephemeral {
+            statistics_as_html(&load_sessions(context), &context.metadata, None, Local::today().naive_local())
+        } else {
+            self.record_session(context);
+            self.session = Some(SessionTracker::new(self.current_page));
+            let current_page = self.current_page;
+            let chapter_end = {
+                let mut doc = self.doc.lock().unwrap();
+                document_toc(doc.as_mut(), &self.info).and_then(|toc| chapter_relative(&toc, current_page, CycleDir::Next))
+            };
+            let current = CurrentBook {
+                info: &self.info,
+                chapter_end,
+                paginated: !self.synthetic,
+            };
+            statistics_as_html(&load_sessions(context), &context.metadata, Some(&current), Local::today().naive_local())
+        };
+
+        hub.send(Event::OpenStatistics(html)).unwrap();
+    }
+
+    // Follows the link displayed at the given screen position, if any.
+    fn follow_link(&mut self, center: Point, hub: &Hub, context: &mut Context) -> bool {
+        let dx = (self.rect.width() - self.frame.width()) as i32 / 2;
+        let dy = (self.rect.height() - self.frame.height()) as i32 / 2;
+
+        let (links, _) = self.doc.lock().ok()
+                             .and_then(|mut doc| doc.links(Location::Exact(self.current_page)))
+                             .unwrap_or((Vec::new(), 0.0));
+
+        for link in links {
+            let r = link.rect;
+            let x_min = r.min.x as f32 * self.scale;
+            let y_min = r.min.y as f32 * self.scale;
+            let x_max = r.max.x as f32 * self.scale;
+            let y_max = r.max.y as f32 * self.scale;
+            let rect = rect![x_min as i32 - self.frame.min.x + dx,
+                             y_min as i32 - self.frame.min.y + dy,
+                             x_max as i32 - self.frame.min.x + dx,
+                             y_max as i32 - self.frame.min.y + dy];
+
+            if rect.includes(center) {
+                let pdf_page = Regex::new(r"^#(\d+)(?:,\d+,\d+)?$").unwrap();
+                let toc_page = Regex::new(r"^@(.*)$").unwrap();
+                if let Some(caps) = toc_page.captures(&link.text) {
+                    if let Ok(location) = caps[1].parse::<f64>() {
+                        self.quit(context);
+                        hub.send(Event::Back).unwrap();
+                        hub.send(Event::GoTo(location)).unwrap();
+                    }
+                } else if let Some(caps) = pdf_page.captures(&link.text) {
+                    if let Ok(index) = caps[1].parse::<usize>() {
+                        self.go_to_page(index.saturating_sub(1) as f64, true, hub);
+                    }
+                } else {
+                    println!("Unrecognized URI: {}.", link.text);
+                }
+                return true;
+            }
+        }
+
+        false
+    }
+
+    // The gesture is missing when the action is triggered by a key.
+    fn perform_action(&mut self, action: ReaderAction, ge: Option<&GestureEvent>, hub: &Hub, context: &mut Context) {
+        match action {
+            ReaderAction::Ignore => (),
+            ReaderAction::NextPage | ReaderAction::PreviousPage => {
+                let dir = if action == ReaderAction::NextPage { CycleDir::Next } else { CycleDir::Previous };
+                if self.search.is_none() {
+                    self.go_to_neighbor(dir, hub, context);
+                } else {
+                    self.go_to_results_neighbor(dir, hub);
+                }
+            },
+            ReaderAction::NextChapter => {
+                if self.search.is_none() {
+                    self.go_to_chapter(CycleDir::Next, hub);
+                } else {
+                    let last_page = self.search.as_ref().unwrap().highlights.len().saturating_sub(1);
+                    self.go_to_results_page(last_page, hub);
+                }
+            },
+            ReaderAction::PreviousChapter => {
+                if self.search.is_none() {
+                    self.go_to_chapter(CycleDir::Previous, hub);
+                } else {
+                    self.go_to_results_page(0, hub);
+                }
+            },
+            ReaderAction::NextBookmark => self.go_to_bookmark(CycleDir::Next, hub),
+            ReaderAction::PreviousBookmark => self.go_to_bookmark(CycleDir::Previous, hub),
+            ReaderAction::ToggleBookmark => self.add_remove_bookmark(hub),
+            ReaderAction::Bookmarks => self.toggle_bookmarks(Some(true), hub, context),
+            ReaderAction::PreviousLocation => self.go_to_last_page(hub),
+            ReaderAction::TableOfContents => {
+                if self.search.is_none() {
+                    if self.ephemeral {
+                        self.quit(context);
+                        hub.send(Event::Back).unwrap();
+                    } else {
+                        hub.send(Event::Show(ViewId::TableOfContents)).unwrap();
+                    }
+                } else {
+                    self.go_to_neighbor(CycleDir::Previous, hub, context);
+                }
+            },
+            ReaderAction::GoToPage => {
+                if self.search.is_none() {
+                    hub.send(Event::Toggle(ViewId::GoToPage)).unwrap();
+                } else {
+                    self.go_to_neighbor(CycleDir::Next, hub, context);
+                }
+            },
+            ReaderAction::ToggleBars => self.toggle_bars(None, hub, context),
+            ReaderAction::Search => hub.send(Event::Show(ViewId::SearchBar)).unwrap(),
+            ReaderAction::Frontlight => {
+                if context.settings.frontlight_presets.len() > 1 {
+                    if context.settings.frontlight {
+                        let lightsensor_level = if CURRENT_DEVICE.has_lightsensor() {
+                            context.lightsensor.level().ok()
+                        } else {
+                            None
+                        };
+                        if let Some(ref frontlight_levels) = guess_frontlight(lightsensor_level, &context.settings.frontlight_presets) {
+                            let LightLevels { intensity, warmth } = *frontlight_levels;
+                            context.frontlight.set_intensity(intensity);
+                            context.frontlight.set_warmth(warmth);
+                        }
+                    }
+                } else {
+                    hub.send(Event::ToggleFrontlight).unwrap();
+                }
+            },
+            ReaderAction::ToggleMonochrome => hub.send(Event::Select(EntryId::ToggleMonochrome)).unwrap(),
+            ReaderAction::Dictionary => {
+                let word = match ge {
+                    Some(&GestureEvent::Tap(pt)) |
+                    Some(&GestureEvent::HoldFinger(pt)) => self.word_at(pt).map(|w| (w, pt)),
+                    _ => None,
+                };
+                if let Some((word, pt)) = word {
+                    self.toggle_dictionary(&word, pt, true, hub, context);
+                } else {
+                    hub.send(Event::Render(self.rect, UpdateMode::Full)).unwrap();
+                }
+            },
+            ReaderAction::FullRefresh => hub.send(Event::Render(self.rect, UpdateMode::Full)).unwrap(),
+            ReaderAction::Zoom => {
+                if let Some(ge) = ge {
+                    self.rescale(ge, hub, context);
+                }
+            },
+            ReaderAction::Rotate => {
+                if let Some(&GestureEvent::Rotate { quarter_turns, .. }) = ge {
+                    if quarter_turns != 0 {
+                        let n = (context.display.rotation + quarter_turns).rem_euclid(4);
+                        hub.send(Event::Rotate(n)).unwrap();
+                    }
+                }
+            },
+            ReaderAction::Back => {
+                self.quit(context);
+                hub.send(Event::Back).unwrap();
+            },
+        }
     }
 
     fn quit(&mut self, context: &mut Context) {
@@ -1310,6 +2870,8 @@ impl Reader {
             r.finished = self.finished;
         }
 
+        self.record_session(context);
+
         for i in &mut context.metadata {
             if i.file.path == self.info.file.path {
                 *i = self.info.clone();
@@ -1322,182 +2884,68 @@ impl Reader {
 impl View for Reader {
     fn handle_event(&mut self, evt: &Event, hub: &Hub, _bus: &mut Bus, context: &mut Context) -> bool {
         match *evt {
-            Event::Gesture(GestureEvent::Swipe { dir, start, .. }) if self.rect.includes(start) => {
-                match dir {
-                    Dir::West => self.go_to_neighbor(CycleDir::Next, hub, context),
-                    Dir::East => self.go_to_neighbor(CycleDir::Previous, hub, context),
-                    _ => (),
+            Event::Gesture(ge) => {
+                let (kind, pt) = match GestureKind::from_event(&ge) {
+                    Some((kind, pt)) if self.rect.includes(pt) => (kind, pt),
+                    _ => return false,
                 };
-                true
-            },
-            Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => {
-                if self.focus.is_some() {
-                    return true;
-                }
 
-                let dx = (self.rect.width() - self.frame.width()) as i32 / 2;
-                let dy = (self.rect.height() - self.frame.height()) as i32 / 2;
-
-                let (links, _) = self.doc.lock().ok()
-                                     .and_then(|mut doc| doc.links(Location::Exact(self.current_page)))
-                                     .unwrap_or((Vec::new(), 0.0));
-
-                for link in links {
-                    let r = link.rect;
-                    let x_min = r.min.x as f32 * self.scale;
-                    let y_min = r.min.y as f32 * self.scale;
-                    let x_max = r.max.x as f32 * self.scale;
-                    let y_max = r.max.y as f32 * self.scale;
-                    let rect = rect![x_min as i32 - self.frame.min.x + dx,
-                                     y_min as i32 - self.frame.min.y + dy,
-                                     x_max as i32 - self.frame.min.x + dx,
-                                     y_max as i32 - self.frame.min.y + dy];
-
-                    if rect.includes(center) {
-                        let pdf_page = Regex::new(r"^#(\d+)(?:,\d+,\d+)?$").unwrap();
-                        let toc_page = Regex::new(r"^@(.*)$").unwrap();
-                        if let Some(caps) = toc_page.captures(&link.text) {
-                            if let Ok(location) = caps[1].parse::<f64>() {
-                                self.quit(context);
-                                hub.send(Event::Back).unwrap();
-                                hub.send(Event::GoTo(location)).unwrap();
-                            }
-                        } else if let Some(caps) = pdf_page.captures(&link.text) {
-                            if let Ok(index) = caps[1].parse::<usize>() {
-                                self.go_to_page(index.saturating_sub(1) as f64, true, hub);
-                            }
-                        } else {
-                            println!("Unrecognized URI: {}.", link.text);
-                        }
+                match ge {
+                    GestureEvent::Tap(..) | GestureEvent::HoldFinger(..) if self.focus.is_some() => {
                         return true;
-                    }
-                }
-
-                let w = self.rect.width() as i32;
-                let x1 = self.rect.min.x + w / 3;
-                let x2 = self.rect.max.x - w / 3;
-
-                if center.x < x1 {
-                    let dx = x1 - center.x;
-                    // Top left corner.
-                    if center.y < self.rect.min.y + dx {
-                        self.go_to_last_page(hub);
-                    // Bottom left corner.
-                    } else if center.y > self.rect.max.y - dx {
-                        if self.search.is_none() {
-                            if self.ephemeral {
-                                self.quit(context);
-                                hub.send(Event::Back).unwrap();
-                            } else {
-                                hub.send(Event::Show(ViewId::TableOfContents)).unwrap();
-                            }
-                        } else {
-                            self.go_to_neighbor(CycleDir::Previous, hub, context);
-                        }
-                    // Left ear.
-                    } else {
-                        if self.search.is_none() {
-                            self.go_to_neighbor(CycleDir::Previous, hub, context);
-                        } else {
-                            self.go_to_results_neighbor(CycleDir::Previous, hub);
+                    },
+                    GestureEvent::Tap(center) => {
+                        if self.follow_link(center, hub, context) {
+                            return true;
                         }
-                    }
-                } else if center.x > x2 {
-                    let dx = center.x - x2;
-                    // Top right corner.
-                    if center.y < self.rect.min.y + dx {
-                        self.add_remove_bookmark(hub);
-                    // Bottom right corner.
-                    } else if center.y > self.rect.max.y - dx {
-                        if self.search.is_none() {
-                            hub.send(Event::Toggle(ViewId::GoToPage)).unwrap();
-                        } else {
-                            self.go_to_neighbor(CycleDir::Next, hub, context);
+                    },
+                    // Holding a word looks it up, whatever the region.
+                    GestureEvent::HoldFinger(center) => {
+                        if let Some(word) = self.word_at(center) {
+                            self.toggle_dictionary(&word, center, true, hub, context);
+                            return true;
                         }
-                    // Right ear.
-                    } else {
-                        if self.search.is_none() {
-                            self.go_to_neighbor(CycleDir::Next, hub, context);
-                        } else {
-                            self.go_to_results_neighbor(CycleDir::Next, hub);
+                    },
+                    GestureEvent::Swipe { dir, start, end } => {
+                        let overflow_x = self.page_frame.width() > self.rect.width();
+                        let overflow_y = self.page_frame.height() > self.rect.height();
+                        match dir {
+                            Dir::West | Dir::East if overflow_x => {
+                                self.pan(start - end, hub);
+                                return true;
+                            },
+                            Dir::North | Dir::South if overflow_y => {
+                                self.pan(start - end, hub);
+                                return true;
+                            },
+                            _ => (),
                         }
-                    }
-                // Middle band.
-                } else {
-                    self.toggle_bars(None, hub, context);
+                    },
+                    _ => (),
                 }
 
-                true
-            },
-            Event::Gesture(GestureEvent::HoldFinger(center)) if self.rect.includes(center) => {
-                if self.focus.is_some() {
-                    return true;
+                let action = context.settings.reader.gestures.action(kind, pt, &self.rect);
+                if let Some(action) = action {
+                    self.perform_action(action, Some(&ge), hub, context);
                 }
 
-                let w = self.rect.width() as i32;
-                let x1 = self.rect.min.x + w / 3;
-                let x2 = self.rect.max.x - w / 3;
-
-                if center.x < x1 {
-                    let dx = x1 - center.x;
-                    // Top left corner.
-                    if center.y < self.rect.min.y + dx {
-                        self.go_to_bookmark(CycleDir::Previous, hub);
-                    // Bottom left corner.
-                    } else if center.y > self.rect.max.y - dx {
-                        if context.settings.frontlight_presets.len() > 1 {
-                            if context.settings.frontlight {
-                                let lightsensor_level = if CURRENT_DEVICE.has_lightsensor() {
-                                    context.lightsensor.level().ok()
-                                } else {
-                                    None
-                                };
-                                if let Some(ref frontlight_levels) = guess_frontlight(lightsensor_level, &context.settings.frontlight_presets) {
-                                    let LightLevels { intensity, warmth } = *frontlight_levels;
-                                    context.frontlight.set_intensity(intensity);
-                                    context.frontlight.set_warmth(warmth);
-                                }
-                            }
-                        } else {
-                            hub.send(Event::ToggleFrontlight).unwrap();
-                        }
-                    // Left ear.
-                    } else {
-                        if self.search.is_none() {
-                            self.go_to_chapter(CycleDir::Previous, hub);
-                        } else {
-                            self.go_to_results_page(0, hub);
-                        }
-                    }
-                } else if center.x > x2 {
-                    let dx = center.x - x2;
-                    // Top right corner.
-                    if center.y < self.rect.min.y + dx {
-                        self.go_to_bookmark(CycleDir::Next, hub);
-                    // Bottom right corner.
-                    } else if center.y > self.rect.max.y - dx {
-                        hub.send(Event::Select(EntryId::ToggleMonochrome)).unwrap();
-                    // Right ear.
-                    } else {
-                        if self.search.is_none() {
-                            self.go_to_chapter(CycleDir::Next, hub);
-                        } else {
-                            let last_page = self.search.as_ref().unwrap().highlights.len() - 1;
-                            self.go_to_results_page(last_page, hub);
-                        }
-                    }
-                } else {
-                    hub.send(Event::Render(self.rect, UpdateMode::Full)).unwrap();
+                match kind {
+                    GestureKind::Tap | GestureKind::HoldFinger |
+                    GestureKind::SwipeNorth | GestureKind::SwipeEast |
+                    GestureKind::SwipeSouth | GestureKind::SwipeWest => true,
+                    _ => action.is_some(),
                 }
-
-                true
             },
             Event::Submit(ViewId::GoToPageInput, ref text) => {
                 let re = Regex::new(r#"^([-+"])?(.+)$"#).unwrap();
                 if let Some(caps) = re.captures(text) {
+                    let label_index = self.page_labels.as_ref().and_then(|l| l.index(&caps[2]));
                     if let Ok(mut location) = caps[2].parse::<f64>() {
                         if !self.synthetic {
                             match caps.get(1).map(|m| m.as_str()) {
+                                Some("\"") if label_index.is_some() => {
+                                    location = label_index.unwrap() as f64;
+                                },
                                 Some("\"") => {
                                     location -= 1.0;
                                     location += self.info.reader.as_ref()
@@ -1509,6 +2957,8 @@ impl View for Reader {
                             }
                         }
                         self.go_to_page(location, true, hub);
+                    } else if let Some(index) = label_index {
+                        self.go_to_page(index as f64, true, hub);
                     }
                 }
                 true
@@ -1520,7 +2970,7 @@ impl View for Reader {
                 true
             },
             Event::Submit(ViewId::SearchInput, ref text) => {
-                match make_query(text) {
+                match make_search_query(text, self.case_sensitive, self.whole_word) {
                     Some(query) => {
                         self.search(text, query, hub);
                         self.toggle_keyboard(false, None, hub);
@@ -1530,6 +2980,7 @@ impl View for Reader {
                         let notif = Notification::new(ViewId::InvalidSearchQueryNotif,
                                                       "Invalid search query.".to_string(),
                                                       &mut context.notification_index,
+                                                      context.display.dims,
                                                       &mut context.fonts,
                                                       hub);
                         self.children.push(Box::new(notif) as Box<View>);
@@ -1563,17 +3014,33 @@ impl View for Reader {
                 true
             },
             Event::Toggle(ViewId::GoToPage) => {
-                self.toggle_go_to_page(None, ViewId::GoToPage, hub, &mut context.fonts);
+                self.toggle_go_to_page(None, ViewId::GoToPage, hub, context);
                 true
             },
             Event::Toggle(ViewId::GoToResultsPage) => {
-                self.toggle_go_to_page(None, ViewId::GoToResultsPage, hub, &mut context.fonts);
+                self.toggle_go_to_page(None, ViewId::GoToResultsPage, hub, context);
                 true
             },
             Event::Slider(SliderId::FontSize, font_size, FingerStatus::Up) => {
                 self.set_font_size(font_size, hub, context);
                 true
             },
+            Event::Slider(id, value, FingerStatus::Up) if id != SliderId::FontSize => {
+                self.set_image_adjustment(id, value, hub);
+                true
+            },
+            Event::Select(EntryId::SetRenderLayers(layers)) => {
+                self.set_render_layers(layers, hub);
+                true
+            },
+            Event::Select(EntryId::ShowImageAdjustments) | Event::Show(ViewId::ImageAdjustments) => {
+                self.toggle_adjustments_window(true, hub, context);
+                true
+            },
+            Event::Close(ViewId::ImageAdjustments) => {
+                self.toggle_adjustments_window(false, hub, context);
+                true
+            },
             Event::ToggleNear(ViewId::MainMenu, rect) => {
                 toggle_main_menu(self, rect, None, hub, context);
                 true
@@ -1587,11 +3054,11 @@ impl View for Reader {
                 true
             },
             Event::ToggleNear(ViewId::MarginCropperMenu, rect) => {
-                self.toggle_margin_cropper_menu(rect, None, hub, &mut context.fonts);
+                self.toggle_margin_cropper_menu(rect, None, hub, context);
                 true
             },
             Event::ToggleNear(ViewId::SearchMenu, rect) => {
-                self.toggle_search_menu(rect, None, hub, &mut context.fonts);
+                self.toggle_search_menu(rect, None, hub, context);
                 true
             },
             Event::ToggleNear(ViewId::FontFamilyMenu, rect) => {
@@ -1610,8 +3077,16 @@ impl View for Reader {
                 self.toggle_line_height_menu(rect, None, hub, context);
                 true
             },
+            Event::ToggleNear(ViewId::ReflowMenu, rect) => {
+                self.toggle_reflow_menu(rect, None, hub, context);
+                true
+            },
+            Event::ToggleNear(ViewId::ZoomMenu, rect) => {
+                self.toggle_zoom_menu(rect, None, hub, context);
+                true
+            },
             Event::ToggleNear(ViewId::PageMenu, rect) => {
-                self.toggle_page_menu(rect, None, hub, &mut context.fonts);
+                self.toggle_page_menu(rect, None, hub, context);
                 true
             },
             Event::Close(ViewId::MainMenu) => {
@@ -1623,20 +3098,52 @@ impl View for Reader {
                 true
             },
             Event::Close(ViewId::GoToPage) => {
-                self.toggle_go_to_page(Some(false), ViewId::GoToPage, hub, &mut context.fonts);
+                self.toggle_go_to_page(Some(false), ViewId::GoToPage, hub, context);
                 true
             },
             Event::Close(ViewId::GoToResultsPage) => {
-                self.toggle_go_to_page(Some(false), ViewId::GoToResultsPage, hub, &mut context.fonts);
+                self.toggle_go_to_page(Some(false), ViewId::GoToResultsPage, hub, context);
                 true
             },
             Event::Show(ViewId::TableOfContents) => {
                 {
                     self.toggle_bars(Some(false), hub, context);
                 }
-                let mut doc = self.doc.lock().unwrap();
-                if doc.has_toc() {
-                    hub.send(Event::OpenToc(doc.toc().unwrap(), self.current_page)).unwrap();
+                let toc = document_toc(self.doc.lock().unwrap().as_mut(), &self.info);
+                if let Some(toc) = toc {
+                    hub.send(Event::OpenToc(toc, self.current_page)).unwrap();
+                } else if self.info.reader.as_ref().map_or(false, |r| r.synthetic_toc.is_some()) {
+                    // The headings were already looked for, in vain.
+                    hub.send(Event::SynthesizedToc(Vec::new())).unwrap();
+                } else if !self.ephemeral {
+                    self.synthesize_toc(hub, context);
+                }
+                true
+            },
+            Event::Select(EntryId::ShowStatistics) => {
+                self.show_statistics(hub, context);
+                true
+            },
+            Event::SynthesizedToc(ref toc) => {
+                // The synthesizer was stopped by a layout change: the locations are stale.
+                match self.toc_synthesizer.take() {
+                    Some(worker) => worker.stop(),
+                    None => return true,
+                }
+                if let Some(ref mut r) = self.info.reader {
+                    r.synthetic_toc = Some(toc.clone());
+                }
+                if toc.is_empty() {
+                    let notif = Notification::new(ViewId::SynthesizeTocNotif,
+                                                  "No headings found.".to_string(),
+                                                  &mut context.notification_index,
+                                                  context.display.dims,
+                                                  &mut context.fonts,
+                                                  hub);
+                    self.children.push(Box::new(notif) as Box<View>);
+                } else {
+                    self.update_bottom_bar(hub);
+                    hub.send(Event::OpenToc(toc.clone(), self.current_page)).unwrap();
                 }
                 true
             },
@@ -1644,6 +3151,79 @@ impl View for Reader {
                 self.toggle_search_bar(true, hub, context);
                 true
             },
+            Event::Toggle(ViewId::SearchResults) => {
+                self.toggle_search_results(None, hub);
+                true
+            },
+            Event::Close(ViewId::SearchResults) => {
+                self.toggle_search_results(Some(false), hub);
+                true
+            },
+            Event::SelectHit(_, location) => {
+                self.toggle_search_results(Some(false), hub);
+                self.go_to_page(location, true, hub);
+                true
+            },
+            Event::Select(EntryId::ShowBookmarks) => {
+                self.toggle_bookmarks(Some(true), hub, context);
+                true
+            },
+            Event::Close(ViewId::Bookmarks) => {
+                self.toggle_bookmarks(Some(false), hub, context);
+                true
+            },
+            Event::SelectBookmark(index) => {
+                let location = self.info.reader.as_ref()
+                                   .and_then(|r| r.bookmarks.get(index))
+                                   .map(|b| b.location);
+                if let Some(location) = location {
+                    self.toggle_bookmarks(Some(false), hub, context);
+                    self.go_to_page(location, true, hub);
+                }
+                true
+            },
+            Event::ToggleBookmarkMenu(rect, index) => {
+                self.toggle_bookmark_menu(index, rect, None, hub, context);
+                true
+            },
+            Event::Select(EntryId::RenameBookmark(index)) => {
+                self.toggle_rename_bookmark(Some(index), hub, context);
+                true
+            },
+            Event::Submit(ViewId::RenameBookmarkInput, ref text) => {
+                if let Some(index) = self.renamed_bookmark {
+                    if let Some(bookmark) = self.info.reader.as_mut().and_then(|r| r.bookmarks.get_mut(index)) {
+                        bookmark.name = text.trim().to_string();
+                    }
+                }
+                true
+            },
+            Event::Close(ViewId::RenameBookmark) => {
+                self.toggle_rename_bookmark(None, hub, context);
+                true
+            },
+            Event::Select(EntryId::RemoveBookmark(index)) => {
+                let count = self.info.reader.as_mut().map_or(0, |r| {
+                    if index < r.bookmarks.len() {
+                        r.bookmarks.remove(index);
+                    }
+                    r.bookmarks.len()
+                });
+                if count == 0 {
+                    self.toggle_bookmarks(Some(false), hub, context);
+                } else {
+                    self.update_bookmarks(index.min(count - 1), hub);
+                }
+                true
+            },
+            Event::Select(EntryId::ToggleBookmark) => {
+                self.add_remove_bookmark(hub);
+                true
+            },
+            Event::Close(ViewId::Dictionary) => {
+                self.toggle_dictionary("", Point::default(), false, hub, context);
+                true
+            },
             Event::Show(ViewId::MarginCropper) => {
                 self.toggle_margin_cropper(true, hub, context);
                 true
@@ -1652,39 +3232,49 @@ impl View for Reader {
                 self.toggle_margin_cropper(false, hub, context);
                 true
             },
-            Event::SearchResult(location, rect) => {
+            Event::SearchResult(ref hit, ref positions) => {
                 if self.search.is_none() {
                     return true;
                 }
 
+                let location = hit.location;
                 let mut results_count = 0;
 
                 if let Some(ref mut s) = self.search {
-                    let pages_count = s.highlights.len();
-                    let search_page = s.highlights
-                                       .binary_search_by(|a| a.location.partial_cmp(&location)
-                                                              .unwrap_or(Ordering::Equal));
-                    if let Ok(index) = search_page {
-                        s.highlights[index].rects.push(rect);
-                    } else {
-                        s.highlights.push(Highlight { location, rects: vec![rect] });
-                        s.highlights.sort_unstable_by(|a, b| a.location.partial_cmp(&b.location)
-                                                              .unwrap_or(Ordering::Equal));
-                    }
-
                     s.results_count += 1;
                     results_count = s.results_count;
-                    if results_count > 1 && location <= self.current_page && s.highlights.len() > pages_count {
-                        s.current_page += 1;
+                    let index = s.hits.iter().position(|h| h.location > location)
+                                 .unwrap_or_else(|| s.hits.len());
+                    s.hits.insert(index, hit.as_ref().clone());
+
+                    for &(page_location, rect) in positions {
+                        let search_page = s.highlights
+                                           .binary_search_by(|a| a.location.partial_cmp(&page_location)
+                                                                  .unwrap_or(Ordering::Equal));
+                        if let Ok(index) = search_page {
+                            s.highlights[index].rects.push(rect);
+                        } else {
+                            s.highlights.push(Highlight { location: page_location, rects: vec![rect] });
+                            s.highlights.sort_unstable_by(|a, b| a.location.partial_cmp(&b.location)
+                                                                  .unwrap_or(Ordering::Equal));
+                            if results_count > 1 && page_location <= self.current_page {
+                                s.current_page += 1;
+                            }
+                        }
                     }
                 }
 
                 self.update_results_bar(hub);
 
+                if let Some(index) = locate_by_id(self, ViewId::SearchResults) {
+                    let search_results = self.child_mut(index).downcast_mut::<SearchResults>().unwrap();
+                    search_results.add_item(hit.as_ref().clone(), hub);
+                }
+
                 if results_count == 1 {
                     self.go_to_page(location, true, hub);
                     self.toggle_bars(Some(false), hub, context);
-                } else if (location - self.current_page).abs() < LOCATION_EPSILON {
+                } else if positions.iter().any(|p| (p.0 - self.current_page).abs() < LOCATION_EPSILON) {
                     self.update(hub);
                 }
 
@@ -1697,6 +3287,7 @@ impl View for Reader {
                     let notif = Notification::new(ViewId::NoSearchResultsNotif,
                                                   "No search results.".to_string(),
                                                   &mut context.notification_index,
+                                                  context.display.dims,
                                                   &mut context.fonts,
                                                   hub);
                     self.children.push(Box::new(notif) as Box<View>);
@@ -1706,12 +3297,30 @@ impl View for Reader {
                 true
             },
             Event::Select(EntryId::ApplyCroppings(index, scheme)) => {
+                let was_auto = self.info.reader.as_ref()
+                                   .and_then(|r| r.cropping_margins.as_ref())
+                                   .map_or(false, |c| c.is_auto());
+                // Start from the automatic margin when leaving the automatic mode.
+                let margin = if was_auto { self.cropping_margin() } else { Margin::default() };
                 self.info.reader.as_mut().map(|r| {
-                    if r.cropping_margins.is_none() {
-                        r.cropping_margins = Some(CroppingMargins::Any(Margin::default()));
+                    if r.cropping_margins.is_none() || (was_auto && scheme != PageScheme::Auto) {
+                        r.cropping_margins = Some(CroppingMargins::Any(margin));
                     }
                     r.cropping_margins.as_mut().map(|c| c.apply(index, scheme))
                 });
+                if was_auto != (scheme == PageScheme::Auto) {
+                    self.auto_margin = None;
+                    self.update(hub);
+                }
+                true
+            },
+            Event::Select(EntryId::ToggleIgnoreCroppingNoise) => {
+                if let Some(&mut CroppingMargins::Auto { ref mut ignore_noise }) = self.info.reader.as_mut()
+                                                                                       .and_then(|r| r.cropping_margins.as_mut()) {
+                    *ignore_noise = !*ignore_noise;
+                }
+                self.auto_margin = None;
+                self.update(hub);
                 true
             },
             Event::Select(EntryId::RemoveCroppings) => {
@@ -1725,10 +3334,63 @@ impl View for Reader {
                 self.search_direction = dir;
                 true
             },
+            Event::Select(EntryId::ToggleCaseSensitive) => {
+                self.case_sensitive = !self.case_sensitive;
+                true
+            },
+            Event::Select(EntryId::ToggleWholeWord) => {
+                self.whole_word = !self.whole_word;
+                true
+            },
             Event::Select(EntryId::SetFontFamily(ref font_family)) => {
                 self.set_font_family(font_family, hub, context);
                 true
             },
+            Event::Select(EntryId::ApplyTypographyProfile(index)) => {
+                self.apply_typography_profile(index, hub, context);
+                true
+            },
+            Event::Select(EntryId::ToggleTypographyProfileKind(index)) => {
+                let kind = self.info.file.kind.clone();
+                toggle_assignment(&mut context.settings.reader.typography_profiles, index,
+                                  |p| &mut p.kinds, &kind);
+                hub.send(Event::SaveSettings).unwrap();
+                true
+            },
+            Event::Select(EntryId::ToggleTypographyProfileCategory(index, ref category)) => {
+                toggle_assignment(&mut context.settings.reader.typography_profiles, index,
+                                  |p| &mut p.categories, category);
+                hub.send(Event::SaveSettings).unwrap();
+                true
+            },
+            Event::Select(EntryId::SaveTypographyProfile) => {
+                self.toggle_save_typography_profile(Some(true), hub, context);
+                true
+            },
+            Event::Submit(ViewId::SaveTypographyProfileInput, ref text) => {
+                self.save_typography_profile(text, hub, context);
+                true
+            },
+            Event::Close(ViewId::SaveTypographyProfile) => {
+                self.toggle_save_typography_profile(Some(false), hub, context);
+                true
+            },
+            Event::Select(EntryId::SetTextAlign(text_align)) => {
+                self.set_text_align(text_align, hub, context);
+                true
+            },
+            Event::Select(EntryId::ToggleHyphenation) => {
+                self.toggle_hyphenation(hub, context);
+                true
+            },
+            Event::Select(EntryId::ToggleIgnoreDocumentCss) => {
+                self.toggle_ignore_document_css(hub, context);
+                true
+            },
+            Event::Select(EntryId::ToggleStyleTweak(tweak)) => {
+                self.toggle_style_tweak(tweak, hub, context);
+                true
+            },
             Event::Select(EntryId::SetFontSize(v)) => {
                 let font_size = 10.0 + v as f32 / 10.0;
                 self.set_font_size(font_size, hub, context);
@@ -1743,6 +3405,30 @@ impl View for Reader {
                 self.set_line_height(line_height, hub, context);
                 true
             },
+            Event::Select(EntryId::ToggleReflow) => {
+                let reflow = if self.info.reader.as_ref().map_or(false, |r| r.reflow.is_some()) {
+                    None
+                } else {
+                    Some(DEFAULT_REFLOW_ZOOM)
+                };
+                self.set_reflow(reflow, hub, context);
+                true
+            },
+            Event::Rescale(id) => {
+                if id == self.rescale_id {
+                    self.apply_scale_target(hub, context);
+                }
+                true
+            },
+            Event::Select(EntryId::SetReflowZoom(z)) => {
+                let zoom = 1.0 + z as f32 / 4.0;
+                self.set_reflow(Some(zoom), hub, context);
+                true
+            },
+            Event::Select(EntryId::SetZoomMode(zoom_mode)) => {
+                self.set_zoom_mode(zoom_mode, hub, context);
+                true
+            },
             Event::Select(EntryId::ToggleFirstPage) => {
                 let current_page = self.current_page as usize;
                 if let Some(ref mut r) = self.info.reader {
@@ -1770,6 +3456,14 @@ impl View for Reader {
                 hub.send(Event::Back).unwrap();
                 true
             },
+            Event::Device(DeviceEvent::Button { code, status: ButtonStatus::Pressed, .. }) if self.focus.is_none() => {
+                if let Some(action) = context.settings.reader.keys.action(code.to_raw()) {
+                    self.perform_action(action, None, hub, context);
+                    true
+                } else {
+                    false
+                }
+            },
             Event::Select(EntryId::Quit) |
             Event::Select(EntryId::Reboot) |
             Event::Select(EntryId::StartNickel) |
@@ -1824,7 +3518,7 @@ impl View for Reader {
             }
         }
 
-        if self.info.reader.as_ref().map_or(false, |r| r.bookmarks.contains(&self.current_page)) {
+        if self.info.reader.as_ref().map_or(false, |r| r.find_bookmark(self.current_page).is_ok()) {
             let dpi = CURRENT_DEVICE.dpi;
             let thickness = scale_by_dpi(3.0, dpi) as u16;
             let radius = mm_to_px(0.4, dpi) as i32 + thickness as i32;
@@ -1858,12 +3552,308 @@ impl View for Reader {
     }
 }
 
-fn build_pixmap(rect: &Rectangle, doc: &mut Document, location: f64, margin: &Margin) -> ((Pixmap, f64), f32) {
+// Returns the scales that fit the cropped page to the width and to the height of the given rectangle.
+fn fit_scales(rect: &Rectangle, doc: &Document, location: f64, margin: &Margin) -> (f32, f32) {
     let (width, height) = doc.dims(location as usize).unwrap();
     let p_width = (1.0 - (margin.left + margin.right)) * width;
     let p_height = (1.0 - (margin.top + margin.bottom)) * height;
-    let w_ratio = rect.width() as f32 / p_width;
-    let h_ratio = rect.height() as f32 / p_height;
-    let scale = w_ratio.min(h_ratio);
+    (rect.width() as f32 / p_width, rect.height() as f32 / p_height)
+}
+
+// Sets the typography of the given document to the values of the given profile.
+fn set_typography(doc: &mut Document, profile: &TypographyProfile, dims: (u32, u32), font_path: &str) {
+    let font_path = if profile.font_family == DEFAULT_FONT_FAMILY {
+        "fonts"
+    } else {
+        font_path
+    };
+    doc.layout(dims.0, dims.1, profile.font_size, CURRENT_DEVICE.dpi);
+    doc.set_font_family(&profile.font_family, font_path);
+    doc.set_margin_width(profile.margin_width);
+    doc.set_line_height(profile.line_height);
+    doc.set_text_align(profile.text_align);
+    doc.set_hyphenation(profile.hyphenation);
+}
+
+fn record_typography(r: &mut ReaderInfo, profile: &TypographyProfile) {
+    r.font_family = Some(profile.font_family.clone());
+    r.font_size = Some(profile.font_size);
+    r.margin_width = Some(profile.margin_width);
+    r.line_height = Some(profile.line_height);
+    r.text_align = profile.text_align;
+    r.hyphenation = Some(profile.hyphenation);
+}
+
+// Gathers the rules of the style tweaks, of the categories and of the book itself.
+fn user_stylesheet(info: &Info, settings: &ReaderSettings) -> String {
+    let mut css = String::new();
+
+    if let Some(ref r) = info.reader {
+        for tweak in &r.style_tweaks {
+            css.push_str(&tweak.css());
+            css.push('\n');
+        }
+    }
+
+    for (category, text) in &settings.category_css {
+        if info.categories.contains(category) {
+            css.push_str(text);
+            css.push('\n');
+        }
+    }
+
+    if let Some(text) = info.reader.as_ref().and_then(|r| r.user_css.as_ref()) {
+        css.push_str(text);
+    }
+
+    css
+}
+
+// Assigns the given kind or category to the profile at the given index, or unassigns it.
+// A kind or a category is assigned to at most one profile.
+fn toggle_assignment<F>(profiles: &mut [TypographyProfile], index: usize, field: F, value: &str)
+                        where F: Fn(&mut TypographyProfile) -> &mut Vec<String> {
+    let assigned = match profiles.get_mut(index) {
+        Some(p) => field(p).iter().any(|v| v == value),
+        None => return,
+    };
+
+    for p in profiles.iter_mut() {
+        field(p).retain(|v| v != value);
+    }
+
+    if !assigned {
+        field(&mut profiles[index]).push(value.to_string());
+    }
+}
+
+fn page_scale(rect: &Rectangle, doc: &Document, location: f64, margin: &Margin, zoom_mode: ZoomMode) -> f32 {
+    let (w_ratio, h_ratio) = fit_scales(rect, doc, location, margin);
+    match zoom_mode {
+        ZoomMode::FitToPage => w_ratio.min(h_ratio),
+        ZoomMode::FitToWidth | ZoomMode::Columns => w_ratio,
+        ZoomMode::Custom(percent) => percent as f32 / 100.0 * w_ratio.min(h_ratio),
+    }
+}
+
+fn build_pixmap(rect: &Rectangle, doc: &mut Document, location: f64, margin: &Margin, zoom_mode: ZoomMode) -> ((Pixmap, f64), f32) {
+    let scale = page_scale(rect, doc, location, margin, zoom_mode);
     (doc.pixmap(Location::Exact(location), scale).unwrap(), scale)
 }
+
+// Applies the given adjustments to a rendered page.
+fn adjust_pixmap(pixmap: &mut Pixmap, adjustments: &ImageAdjustments) {
+    if adjustments.tint_removal > 0.0 {
+        filters::remove_tint(pixmap, adjustments.tint_removal);
+    }
+    if adjustments.contrast > 0.0 {
+        filters::stretch_contrast(pixmap, adjustments.contrast);
+    }
+    if (adjustments.gamma - 1.0).abs() > f32::EPSILON {
+        filters::gamma(pixmap, adjustments.gamma);
+    }
+    if adjustments.binarization > 0.0 {
+        filters::binarize(pixmap, MAX_SAUVOLA_K * adjustments.binarization);
+    }
+}
+
+// Returns the outline of the document, or the table of contents built from its headings.
+fn document_toc(doc: &mut Document, info: &Info) -> Option<Vec<TocEntry>> {
+    doc.toc().filter(|toc| !toc.is_empty())
+       .or_else(|| info.reader.as_ref().and_then(|r| r.synthetic_toc.clone())
+                       .filter(|toc| !toc.is_empty()))
+}
+
+// Authenticates protected documents with the password entered during this session,
+// or with the one remembered in the keystore.
+fn unlock(doc: &mut Document, path: &Path, context: &Context) -> bool {
+    if !doc.is_protected() {
+        return true;
+    }
+
+    let stored_password = load_password(path, context);
+
+    if let Some(password) = context.passwords.get(path) {
+        let unlocked = doc.authenticate(password);
+        if unlocked && context.settings.reader.remember_passwords &&
+           stored_password.as_ref() != Some(password) {
+            save_password(path, password, context)
+                         .map_err(|e| eprintln!("Can't save password: {}", e)).ok();
+        }
+        return unlocked;
+    }
+
+    stored_password.map_or(false, |password| doc.authenticate(&password))
+}
+
+// Returns the margins that isolate each column of the given page.
+fn column_margins(doc: &mut Document, index: usize) -> Vec<Margin> {
+    doc.dims(index).map(|(width, height)| {
+        detect_columns(doc, index).into_iter().map(|r| {
+            frame_margin(&r, width, height)
+        }).filter(|m| m.left + m.right < 1.0 && m.top + m.bottom < 1.0).collect()
+    }).unwrap_or_default()
+}
+
+// Returns the margin that crops the given page to its content.
+fn content_margin(doc: &mut Document, index: usize, ignore_noise: bool) -> Margin {
+    doc.dims(index).and_then(|(width, height)| {
+        content_frame(doc, index, ignore_noise).map(|r| frame_margin(&r, width, height))
+    }).filter(|m| m.left + m.right < 1.0 && m.top + m.bottom < 1.0)
+      .unwrap_or_default()
+}
+
+// Converts a rectangle, in page coordinates, into a padded margin.
+fn frame_margin(rect: &Rectangle, width: f32, height: f32) -> Margin {
+    Margin::new((rect.min.y as f32 / height - CONTENT_PADDING).max(0.0),
+                (1.0 - rect.max.x as f32 / width - CONTENT_PADDING).max(0.0),
+                (1.0 - rect.max.y as f32 / height - CONTENT_PADDING).max(0.0),
+                (rect.min.x as f32 / width - CONTENT_PADDING).max(0.0))
+}
+
+// Clamps the view port to the page frame and returns the visible part of the latter.
+fn visible_frame(page_frame: &Rectangle, view_port: &mut Point, rect: &Rectangle) -> Rectangle {
+    let max_x = (page_frame.width() as i32 - rect.width() as i32).max(0);
+    let max_y = (page_frame.height() as i32 - rect.height() as i32).max(0);
+    view_port.x = view_port.x.max(0).min(max_x);
+    view_port.y = view_port.y.max(0).min(max_y);
+    let min = page_frame.min + *view_port;
+    rect![min.x, min.y,
+          (min.x + rect.width() as i32).min(page_frame.max.x),
+          (min.y + rect.height() as i32).min(page_frame.max.y)]
+}
+
+// Searches the concatenated text of the given pages, and returns, for each match,
+// the location and the rectangle of each word it covers, as well as the surrounding
+// text and the index of the match's first character in this text.
+fn find_matches(pages: &[(f64, &Vec<BoundedText>)], query: &Regex) -> Vec<(Vec<(f64, Rectangle)>, String, usize)> {
+    let mut text = String::new();
+    let mut bounds = Vec::new();
+
+    for &(location, words) in pages {
+        for word in words.iter() {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            let start = text.len();
+            text.push_str(&word.text);
+            bounds.push((start, text.len(), location, word.rect));
+        }
+    }
+
+    query.find_iter(&text).filter(|m| m.start() < m.end()).map(|m| {
+        let first = bounds.iter().position(|b| b.1 > m.start()).unwrap_or(bounds.len());
+        let positions = bounds[first..].iter().take_while(|b| b.0 < m.end())
+                                       .map(|b| (b.2, b.3)).collect::<Vec<(f64, Rectangle)>>();
+        let (snippet, index) = snippet(&text, m.start(), m.end());
+        (positions, snippet, index)
+    }).filter(|m| !m.0.is_empty()).collect()
+}
+
+// Names the page at the given location. The locations of the documents with synthetic
+// page numbers aren't meaningful to the reader: the progress is shown instead.
+fn page_name(location: f64, pages_count: f64, synthetic: bool, page_labels: Option<&PageLabels>) -> String {
+    if synthetic {
+        format!("{:.0}%", (100.0 * location / pages_count.max(1.0)).min(100.0))
+    } else if let Some(label) = page_labels.and_then(|l| l.label(location as usize)) {
+        format!("Page {}", label)
+    } else {
+        format!("Page {}", location as usize + 1)
+    }
+}
+
+// The matches that are entirely on the previous page were already reported,
+// and so were those of the initial page, when it's searched again at the end.
+fn is_new_match(positions: &[(f64, Rectangle)], location: f64, revisited: bool) -> bool {
+    let on_current = positions.iter().any(|p| (p.0 - location).abs() < LOCATION_EPSILON);
+    let on_previous = positions.iter().any(|p| (p.0 - location).abs() >= LOCATION_EPSILON);
+    on_current && !(revisited && !on_previous)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str, y: i32) -> Vec<BoundedText> {
+        text.split_whitespace().enumerate().map(|(i, w)| {
+            let x = 100 * i as i32;
+            BoundedText { text: w.to_string(), rect: rect![x, y, x + 90, y + 20] }
+        }).collect()
+    }
+
+    #[test]
+    fn test_page_name() {
+        assert_eq!(page_name(24.0, 96.0, true, None), "25%");
+        assert_eq!(page_name(95.5, 96.0, true, None), "99%");
+        assert_eq!(page_name(4.0, 10.0, false, None), "Page 5");
+    }
+
+    #[test]
+    fn test_match_across_pages() {
+        let first = words("Call me", 0);
+        let second = words("Ishmael. Some years ago", 0);
+        let query = Regex::new(r"(?i)me\s+ishmael").unwrap();
+        let matches = find_matches(&[(0.0, &first), (1.0, &second)], &query);
+        assert_eq!(matches.len(), 1);
+        let (ref positions, ref snippet, index) = matches[0];
+        assert_eq!(positions, &vec![(0.0, rect![100, 0, 190, 20]), (1.0, rect![0, 0, 90, 20])]);
+        assert_eq!(snippet, "Call me Ishmael. Some years ago");
+        assert_eq!(index, 5);
+        // Reported when the second page is searched, whether it's the first or the last one.
+        assert!(is_new_match(positions, 1.0, false));
+        assert!(is_new_match(positions, 1.0, true));
+    }
+
+    #[test]
+    fn test_repeated_match() {
+        let page = words("the cat and the dog", 40);
+        let query = Regex::new(r"(?i)\bthe\b").unwrap();
+        let matches = find_matches(&[(3.0, &page)], &query);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].0, vec![(3.0, rect![0, 40, 90, 60])]);
+        assert_eq!(matches[1].0, vec![(3.0, rect![300, 40, 390, 60])]);
+        assert_eq!((matches[0].2, matches[1].2), (0, 12));
+        // Both are new the first time the page is searched, and neither when it's searched again.
+        assert!(matches.iter().all(|m| is_new_match(&m.0, 3.0, false)));
+        assert!(matches.iter().all(|m| !is_new_match(&m.0, 3.0, true)));
+        // Matches entirely on the previous page were already reported.
+        let next = words("ran away", 0);
+        let matches = find_matches(&[(3.0, &page), (4.0, &next)], &query);
+        assert!(matches.iter().all(|m| !is_new_match(&m.0, 4.0, false)));
+    }
+
+    fn profile(name: &str) -> TypographyProfile {
+        TypographyProfile { name: name.to_string(), .. Default::default() }
+    }
+
+    #[test]
+    fn test_toggle_assignment() {
+        let mut profiles = vec![profile("Novels"), profile("Essays")];
+        toggle_assignment(&mut profiles, 0, |p| &mut p.kinds, "epub");
+        assert_eq!(profiles[0].kinds, vec!["epub".to_string()]);
+        toggle_assignment(&mut profiles, 1, |p| &mut p.kinds, "epub");
+        assert!(profiles[0].kinds.is_empty());
+        assert_eq!(profiles[1].kinds, vec!["epub".to_string()]);
+        toggle_assignment(&mut profiles, 1, |p| &mut p.kinds, "epub");
+        assert!(profiles[1].kinds.is_empty());
+        toggle_assignment(&mut profiles, 2, |p| &mut p.categories, "Fiction");
+        assert!(profiles.iter().all(|p| p.categories.is_empty()));
+    }
+
+    #[test]
+    fn test_record_typography() {
+        let mut r = ReaderInfo::default();
+        let p = TypographyProfile { font_size: 9.5,
+                                    margin_width: 4,
+                                    text_align: Some(TextAlign::Left),
+                                    hyphenation: false,
+                                    .. profile("Narrow") };
+        record_typography(&mut r, &p);
+        assert_eq!(r.font_family, Some(p.font_family.clone()));
+        assert_eq!(r.font_size, Some(9.5));
+        assert_eq!(r.margin_width, Some(4));
+        assert_eq!(r.line_height, Some(p.line_height));
+        assert_eq!(r.text_align, Some(TextAlign::Left));
+        assert_eq!(r.hyphenation, Some(false));
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use std::collections::VecDeque;
+use framebuffer::Pixmap;
+use metadata::Margin;
+
+// Identifies a rendered page by its location, scale and cropping margin.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PixmapKey {
+    location: u64,
+    scale: u32,
+    margin: [u32; 4],
+}
+
+impl PixmapKey {
+    pub fn new(location: f64, scale: f32, margin: &Margin) -> PixmapKey {
+        PixmapKey {
+            location: location.to_bits(),
+            scale: scale.to_bits(),
+            margin: [margin.top.to_bits(), margin.right.to_bits(),
+                     margin.bottom.to_bits(), margin.left.to_bits()],
+        }
+    }
+}
+
+// A bounded cache of rendered pages, that evicts the least recently used first.
+// Each call to `clear` starts a new generation: the insertions of pages rendered
+// for a previous generation are ignored.
+pub struct PixmapCache {
+    capacity: usize,
+    generation: usize,
+    entries: VecDeque<(PixmapKey, Arc<Pixmap>, f64)>,
+}
+
+impl PixmapCache {
+    pub fn new(capacity: usize) -> PixmapCache {
+        PixmapCache {
+            capacity,
+            generation: 0,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn contains(&self, key: &PixmapKey) -> bool {
+        self.entries.iter().any(|e| e.0 == *key)
+    }
+
+    // Returns the pixmap of the given key, and the location of the page it represents.
+    pub fn get(&mut self, key: &PixmapKey) -> Option<(Arc<Pixmap>, f64)> {
+        let index = self.entries.iter().position(|e| e.0 == *key)?;
+        let entry = self.entries.remove(index)?;
+        let result = (Arc::clone(&entry.1), entry.2);
+        self.entries.push_back(entry);
+        Some(result)
+    }
+
+    pub fn insert(&mut self, generation: usize, key: PixmapKey, pixmap: Arc<Pixmap>, location: f64) {
+        if generation != self.generation || self.capacity == 0 {
+            return;
+        }
+
+        if let Some(index) = self.entries.iter().position(|e| e.0 == key) {
+            self.entries.remove(index);
+        }
+
+        while self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back((key, pixmap, location));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.generation = self.generation.wrapping_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(location: f64) -> PixmapKey {
+        PixmapKey::new(location, 1.0, &Margin::default())
+    }
+
+    #[test]
+    fn test_eviction() {
+        let mut cache = PixmapCache::new(2);
+        let pixmap = Arc::new(Pixmap::new(1, 1));
+        cache.insert(0, key(1.0), Arc::clone(&pixmap), 1.0);
+        cache.insert(0, key(2.0), Arc::clone(&pixmap), 2.0);
+        assert!(cache.get(&key(1.0)).is_some());
+        cache.insert(0, key(3.0), Arc::clone(&pixmap), 3.0);
+        assert!(cache.contains(&key(1.0)));
+        assert!(!cache.contains(&key(2.0)));
+        assert!(cache.contains(&key(3.0)));
+    }
+
+    #[test]
+    fn test_generation() {
+        let mut cache = PixmapCache::new(2);
+        let pixmap = Arc::new(Pixmap::new(1, 1));
+        let generation = cache.generation();
+        cache.clear();
+        cache.insert(generation, key(1.0), pixmap, 1.0);
+        assert!(!cache.contains(&key(1.0)));
+    }
+}
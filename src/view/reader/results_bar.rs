@@ -20,7 +20,7 @@ pub struct ResultsBar {
 }
 
 impl ResultsBar {
-    pub fn new(rect: Rectangle, current_page: usize, pages_count: usize, count: usize, completed: bool) -> ResultsBar {
+    pub fn new(rect: Rectangle, current_page: usize, pages_count: usize, count: usize, noun: &'static str, completed: bool) -> ResultsBar {
         let mut children = Vec::new();
         let side = rect.height() as i32;
         let is_prev_disabled = pages_count < 2 || current_page == 0;
@@ -42,6 +42,7 @@ impl ResultsBar {
         let results_label = ResultsLabel::new(rect![pt!(rect.min.x + side, rect.min.y),
                                                     pt!(rect.min.x + side + small_half_width, rect.max.y)],
                                               count,
+                                              noun,
                                               completed);
         children.push(Box::new(results_label) as Box<View>);
 
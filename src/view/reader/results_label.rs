@@ -3,22 +3,26 @@ use font::{Fonts, font_from_style, NORMAL_STYLE};
 use framebuffer::{Framebuffer, UpdateMode};
 use color::TEXT_NORMAL;
 use geom::{Rectangle};
-use view::{View, Event, Hub, Bus};
+use view::{View, Event, Hub, Bus, ViewId};
+use gesture::GestureEvent;
 use app::Context;
 
 pub struct ResultsLabel {
     rect: Rectangle,
     children: Vec<Box<View>>,
     count: usize,
+    // The singular name of the counted items.
+    noun: &'static str,
     completed: bool,
 }
 
 impl ResultsLabel {
-    pub fn new(rect: Rectangle, count: usize, completed: bool) -> ResultsLabel {
+    pub fn new(rect: Rectangle, count: usize, noun: &'static str, completed: bool) -> ResultsLabel {
         ResultsLabel {
             rect,
             children: vec![],
             count,
+            noun,
             completed,
         }
     }
@@ -30,9 +34,9 @@ impl ResultsLabel {
 
     fn text(&self) -> String {
         let qualifier = if self.count != 1 {
-            "results"
+            format!("{}s", self.noun)
         } else {
-            "result"
+            self.noun.to_string()
         };
 
         if self.count == 0 {
@@ -45,13 +49,17 @@ impl ResultsLabel {
 
 
 impl View for ResultsLabel {
-    fn handle_event(&mut self, evt: &Event, hub: &Hub, _bus: &mut Bus, _context: &mut Context) -> bool {
+    fn handle_event(&mut self, evt: &Event, hub: &Hub, bus: &mut Bus, _context: &mut Context) -> bool {
         match *evt {
             Event::EndOfSearch => {
                 self.completed = true;
                 hub.send(Event::Render(self.rect, UpdateMode::Gui)).unwrap();
                 false
             },
+            Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => {
+                bus.push_back(Event::Toggle(ViewId::SearchResults));
+                true
+            },
             _ => false,
         }
     }
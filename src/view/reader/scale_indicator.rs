@@ -0,0 +1,97 @@
+use device::CURRENT_DEVICE;
+use framebuffer::{Framebuffer, UpdateMode};
+use geom::{Rectangle, CornerSpec, BorderSpec};
+use font::{Fonts, font_from_style, NORMAL_STYLE};
+use color::{BLACK, WHITE, TEXT_NORMAL};
+use view::{View, Event, Hub, Bus, ViewId};
+use view::{THICKNESS_LARGE, BORDER_RADIUS_MEDIUM};
+use unit::scale_by_dpi;
+use app::Context;
+
+// Shows the font size or the zoom level while pinching or spreading.
+pub struct ScaleIndicator {
+    rect: Rectangle,
+    children: Vec<Box<View>>,
+    text: String,
+}
+
+impl ScaleIndicator {
+    pub fn new(parent_rect: &Rectangle, text: String, context: &mut Context) -> ScaleIndicator {
+        let dpi = CURRENT_DEVICE.dpi;
+        let font = font_from_style(&mut context.fonts, &NORMAL_STYLE, dpi);
+        let x_height = font.x_heights.0 as i32;
+        let padding = font.em() as i32;
+
+        // Leave room for longer values.
+        let plan = font.plan(&text, None, None);
+        let width = (plan.width as i32 + 5 * padding).max(parent_rect.width() as i32 / 3);
+        let height = 7 * x_height;
+
+        let dx = (parent_rect.width() as i32 - width) / 2;
+        let dy = (parent_rect.height() as i32 - height) / 2;
+        let rect = rect![parent_rect.min.x + dx, parent_rect.min.y + dy,
+                         parent_rect.min.x + dx + width, parent_rect.min.y + dy + height];
+
+        ScaleIndicator {
+            rect,
+            children: vec![],
+            text,
+        }
+    }
+
+    pub fn update(&mut self, text: String, hub: &Hub) {
+        self.text = text;
+        hub.send(Event::Render(self.rect, UpdateMode::Gui)).unwrap();
+    }
+}
+
+impl View for ScaleIndicator {
+    fn handle_event(&mut self, _evt: &Event, _hub: &Hub, _bus: &mut Bus, _context: &mut Context) -> bool {
+        false
+    }
+
+    fn render(&self, fb: &mut Framebuffer, fonts: &mut Fonts) {
+        let dpi = CURRENT_DEVICE.dpi;
+
+        let border_radius = scale_by_dpi(BORDER_RADIUS_MEDIUM, dpi) as i32;
+        let border_thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as u16;
+
+        fb.draw_rounded_rectangle_with_border(&self.rect,
+                                              &CornerSpec::Uniform(border_radius),
+                                              &BorderSpec { thickness: border_thickness,
+                                                            color: BLACK },
+                                              &WHITE);
+
+        let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+        let padding = font.em() as i32;
+        let max_width = self.rect.width() as i32 - 2 * padding;
+        let plan = font.plan(&self.text, Some(max_width as u32), None);
+        let x_height = font.x_heights.0 as i32;
+
+        let dx = (self.rect.width() - plan.width) as i32 / 2;
+        let dy = (self.rect.height() as i32 - x_height) / 2;
+        let pt = pt!(self.rect.min.x + dx, self.rect.max.y - dy);
+
+        font.render(fb, TEXT_NORMAL[1], &plan, pt);
+    }
+
+    fn rect(&self) -> &Rectangle {
+        &self.rect
+    }
+
+    fn rect_mut(&mut self) -> &mut Rectangle {
+        &mut self.rect
+    }
+
+    fn children(&self) -> &Vec<Box<View>> {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> &mut Vec<Box<View>> {
+        &mut self.children
+    }
+
+    fn id(&self) -> Option<ViewId> {
+        Some(ViewId::ScaleIndicator)
+    }
+}
@@ -7,13 +7,13 @@ use view::filler::Filler;
 use view::slider::Slider;
 use view::icon::Icon;
 use view::labeled_icon::LabeledIcon;
-use metadata::ReaderInfo;
+use metadata::{ReaderInfo, ZoomMode};
 use gesture::GestureEvent;
 use input::DeviceEvent;
 use unit::scale_by_dpi;
 use geom::Rectangle;
 use font::Fonts;
-use color::{WHITE, SEPARATOR_NORMAL};
+use color::SEPARATOR_NORMAL;
 use app::Context;
 
 #[derive(Debug)]
@@ -103,10 +103,23 @@ impl ToolBar {
                                       Event::Show(ViewId::MarginCropper));
             children.push(Box::new(crop_icon) as Box<View>);
 
-            let filler = Filler::new(rect![rect.min.x + side, rect.max.y - side,
-                                           rect.max.x - 2 * side, rect.max.y],
-                                     WHITE);
-            children.push(Box::new(filler) as Box<View>);
+            let remaining_width = rect.width() as i32 - 3 * side;
+            let reflow_width = remaining_width / 2;
+            let reflow = reader_info.and_then(|r| r.reflow);
+            let reflow_icon = LabeledIcon::new("reflow",
+                                               rect![rect.min.x + side, rect.max.y - side,
+                                                     rect.min.x + side + reflow_width, rect.max.y],
+                                               Event::Show(ViewId::ReflowMenu),
+                                               reflow_label(reflow));
+            children.push(Box::new(reflow_icon) as Box<View>);
+
+            let zoom_mode = reader_info.and_then(|r| r.zoom_mode).unwrap_or_default();
+            let zoom_icon = LabeledIcon::new("zoom",
+                                             rect![rect.min.x + side + reflow_width, rect.max.y - side,
+                                                   rect.max.x - 2 * side, rect.max.y],
+                                             Event::Show(ViewId::ZoomMenu),
+                                             zoom_label(zoom_mode));
+            children.push(Box::new(zoom_icon) as Box<View>);
         }
 
         // End of second row.
@@ -147,6 +160,17 @@ impl ToolBar {
         }
     }
 
+    pub fn update_reflow(&mut self, reflow: Option<f32>, hub: &Hub) {
+        if let Some(labeled_icon) = self.children[1].downcast_mut::<LabeledIcon>() {
+            labeled_icon.update(reflow_label(reflow), hub);
+        }
+    }
+
+    pub fn update_zoom_mode(&mut self, zoom_mode: ZoomMode, hub: &Hub) {
+        if let Some(labeled_icon) = self.children[2].downcast_mut::<LabeledIcon>() {
+            labeled_icon.update(zoom_label(zoom_mode), hub);
+        }
+    }
 
     pub fn update_slider(&mut self, font_size: f32, hub: &Hub) {
         if let Some(index) = locate::<Slider>(self) {
@@ -156,6 +180,20 @@ impl ToolBar {
     }
 }
 
+fn reflow_label(reflow: Option<f32>) -> String {
+    reflow.map_or_else(|| "Original Layout".to_string(),
+                       |zoom| format!("Reflow at {:.2}", zoom))
+}
+
+fn zoom_label(zoom_mode: ZoomMode) -> String {
+    match zoom_mode {
+        ZoomMode::FitToPage => "Fit to Page".to_string(),
+        ZoomMode::FitToWidth => "Fit to Width".to_string(),
+        ZoomMode::Columns => "Columns".to_string(),
+        ZoomMode::Custom(percent) => format!("Zoom {}%", percent),
+    }
+}
+
 impl View for ToolBar {
     fn handle_event(&mut self, evt: &Event, _hub: &Hub, _bus: &mut Bus, _context: &mut Context) -> bool {
         match *evt {
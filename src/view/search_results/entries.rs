@@ -0,0 +1,207 @@
+use device::{CURRENT_DEVICE, BAR_SIZES};
+use view::{View, Event, Hub, Bus, THICKNESS_MEDIUM};
+use view::filler::Filler;
+use super::ResultItem;
+use framebuffer::{Framebuffer, UpdateMode};
+use font::{Fonts, font_from_style, MD_TITLE, NORMAL_STYLE};
+use geom::{Rectangle, Dir, CycleDir};
+use color::{WHITE, SEPARATOR_NORMAL, TEXT_NORMAL};
+use gesture::GestureEvent;
+use unit::scale_by_dpi;
+use app::Context;
+
+pub struct Entries {
+    pub rect: Rectangle,
+    children: Vec<Box<View>>,
+    pub max_lines: usize,
+}
+
+impl Entries {
+    pub fn new(rect: Rectangle) -> Entries {
+        let dpi = CURRENT_DEVICE.dpi;
+        let (_, height) = CURRENT_DEVICE.dims;
+        let &(_, big_height) = BAR_SIZES.get(&(height, dpi)).unwrap();
+        let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+        let max_lines = ((rect.height() + thickness as u32) / big_height) as usize;
+
+        let filler = Filler::new(rect, WHITE);
+
+        Entries {
+            rect,
+            children: vec![Box::new(filler) as Box<View>],
+            max_lines: max_lines.max(1),
+        }
+    }
+
+    pub fn update<T: ResultItem>(&mut self, items: &[T], hub: &Hub) {
+        self.children.clear();
+        let dpi = CURRENT_DEVICE.dpi;
+        let (_, height) = CURRENT_DEVICE.dims;
+        let &(_, big_height) = BAR_SIZES.get(&(height, dpi)).unwrap();
+        let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+
+        for (index, item) in items.iter().enumerate() {
+            let y_min = self.rect.min.y + index as i32 * big_height as i32;
+            let y_max = y_min + big_height as i32 - thickness;
+            let entry = Entry::new(rect![self.rect.min.x, y_min,
+                                         self.rect.max.x, y_max],
+                                   item.clone());
+            self.children.push(Box::new(entry) as Box<View>);
+            if index < self.max_lines - 1 {
+                let separator = Filler::new(rect![self.rect.min.x, y_max,
+                                                  self.rect.max.x, y_max + thickness],
+                                            SEPARATOR_NORMAL);
+                self.children.push(Box::new(separator) as Box<View>);
+            }
+        }
+
+        if items.len() < self.max_lines {
+            let y_min = self.rect.min.y + items.len() as i32 * big_height as i32;
+            let filler = Filler::new(rect![self.rect.min.x, y_min,
+                                           self.rect.max.x, self.rect.max.y],
+                                     WHITE);
+            self.children.push(Box::new(filler) as Box<View>);
+        }
+
+        hub.send(Event::Render(self.rect, UpdateMode::Gui)).unwrap();
+    }
+}
+
+impl View for Entries {
+    fn handle_event(&mut self, evt: &Event, _hub: &Hub, bus: &mut Bus, _context: &mut Context) -> bool {
+        match *evt {
+            Event::Gesture(GestureEvent::Swipe { dir, start, .. }) if self.rect.includes(start) => {
+                match dir {
+                    Dir::West => {
+                        bus.push_back(Event::ResultsPage(CycleDir::Next));
+                        true
+                    },
+                    Dir::East => {
+                        bus.push_back(Event::ResultsPage(CycleDir::Previous));
+                        true
+                    },
+                    _ => false,
+                }
+            },
+            _ => false,
+        }
+    }
+
+    fn render(&self, _fb: &mut Framebuffer, _fonts: &mut Fonts) {}
+
+    fn rect(&self) -> &Rectangle {
+        &self.rect
+    }
+
+    fn rect_mut(&mut self) -> &mut Rectangle {
+        &mut self.rect
+    }
+
+    fn children(&self) -> &Vec<Box<View>> {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> &mut Vec<Box<View>> {
+        &mut self.children
+    }
+}
+
+struct Entry<T: ResultItem> {
+    rect: Rectangle,
+    children: Vec<Box<View>>,
+    item: T,
+}
+
+impl<T: ResultItem> Entry<T> {
+    fn new(rect: Rectangle, item: T) -> Entry<T> {
+        Entry {
+            rect,
+            children: vec![],
+            item,
+        }
+    }
+}
+
+impl<T: ResultItem> View for Entry<T> {
+    fn handle_event(&mut self, evt: &Event, _hub: &Hub, bus: &mut Bus, _context: &mut Context) -> bool {
+        match *evt {
+            Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => {
+                bus.push_back(self.item.tap_event());
+                true
+            },
+            Event::Gesture(GestureEvent::HoldFinger(center)) if self.rect.includes(center) => {
+                if let Some(evt) = self.item.hold_event(Rectangle::from_point(center)) {
+                    bus.push_back(evt);
+                }
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn render(&self, fb: &mut Framebuffer, fonts: &mut Fonts) {
+        let dpi = CURRENT_DEVICE.dpi;
+
+        fb.draw_rectangle(&self.rect, TEXT_NORMAL[0]);
+
+        let (x_height, padding, baseline) = {
+            let font = font_from_style(fonts, &MD_TITLE, dpi);
+            let x_height = font.x_heights.0 as i32;
+            (x_height, font.em() as i32, (self.rect.height() as i32 - 2 * x_height) / 3)
+        };
+
+        let max_width = self.rect.width() as i32 - 2 * padding;
+
+        // Label
+        let label_width = if self.item.label().is_empty() {
+            0
+        } else {
+            let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+            let plan = font.plan(self.item.label(), Some(max_width as u32 / 3), None);
+            let pt = pt!(self.rect.max.x - padding - plan.width as i32,
+                         self.rect.min.y + baseline + x_height);
+            font.render(fb, TEXT_NORMAL[1], &plan, pt);
+            plan.width as i32 + padding
+        };
+
+        // Heading
+        {
+            let font = font_from_style(fonts, &MD_TITLE, dpi);
+            let heading_width = (max_width - label_width).max(0);
+            let plan = font.plan(self.item.heading(), Some(heading_width as u32), None);
+            let pt = self.rect.min + pt!(padding, baseline + x_height);
+            font.render(fb, TEXT_NORMAL[1], &plan, pt);
+        }
+
+        // Text
+        {
+            let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+            let plan = match self.item.text_index() {
+                Some(index) => {
+                    let mut plan = font.plan(self.item.text(), None, None);
+                    font.crop_around(&mut plan, index, max_width as u32);
+                    plan
+                },
+                None => font.plan(self.item.text(), Some(max_width as u32), None),
+            };
+            let pt = pt!(self.rect.min.x + padding, self.rect.max.y - baseline);
+            font.render(fb, TEXT_NORMAL[1], &plan, pt);
+        }
+    }
+
+    fn rect(&self) -> &Rectangle {
+        &self.rect
+    }
+
+    fn rect_mut(&mut self) -> &mut Rectangle {
+        &mut self.rect
+    }
+
+    fn children(&self) -> &Vec<Box<View>> {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> &mut Vec<Box<View>> {
+        &mut self.children
+    }
+}
@@ -0,0 +1,291 @@
+mod entries;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering as AtomicOrdering;
+use device::{CURRENT_DEVICE, BAR_SIZES};
+use framebuffer::Framebuffer;
+use view::{View, Event, Hub, Bus, ViewId, Align, THICKNESS_MEDIUM};
+use view::icon::Icon;
+use view::label::Label;
+use view::filler::Filler;
+use view::reader::results_bar::ResultsBar;
+use self::entries::Entries;
+use input::DeviceEvent;
+use unit::scale_by_dpi;
+use geom::{Rectangle, CycleDir, halves};
+use color::{BLACK, WHITE};
+use font::Fonts;
+use app::Context;
+
+#[derive(Debug, Clone)]
+pub struct Hit {
+    // The title of the book or the chapter.
+    pub heading: String,
+    // The text surrounding the match.
+    pub snippet: String,
+    // Index of the first character of the match in the snippet.
+    pub index: usize,
+    // Empty for the hits of the current book.
+    pub path: PathBuf,
+    pub location: f64,
+}
+
+// An item of a results list.
+pub trait ResultItem: Clone + 'static {
+    // The first line of the entry.
+    fn heading(&self) -> &str;
+    // Shown at the end of the first line.
+    fn label(&self) -> &str {
+        ""
+    }
+    // The second line of the entry.
+    fn text(&self) -> &str;
+    // Index of a character of the text that needs to be visible.
+    fn text_index(&self) -> Option<usize> {
+        None
+    }
+    // The event sent when the entry is tapped.
+    fn tap_event(&self) -> Event;
+    // The event sent when the entry is held.
+    fn hold_event(&self, _rect: Rectangle) -> Option<Event> {
+        None
+    }
+    // The item carried by an event of an ongoing search.
+    fn from_event(_evt: &Event) -> Option<Self> {
+        None
+    }
+    // The index at which the item is inserted in the given items.
+    fn insertion_index(&self, items: &[Self]) -> usize {
+        items.len()
+    }
+}
+
+impl ResultItem for Hit {
+    fn heading(&self) -> &str {
+        &self.heading
+    }
+
+    fn text(&self) -> &str {
+        &self.snippet
+    }
+
+    fn text_index(&self) -> Option<usize> {
+        Some(self.index)
+    }
+
+    fn tap_event(&self) -> Event {
+        Event::SelectHit(self.path.clone(), self.location)
+    }
+
+    fn from_event(evt: &Event) -> Option<Hit> {
+        match *evt {
+            Event::SearchHit(ref hit) => Some(hit.as_ref().clone()),
+            _ => None,
+        }
+    }
+
+    // The hits of a book are kept in reading order.
+    fn insertion_index(&self, items: &[Hit]) -> usize {
+        items.iter()
+             .rposition(|h| h.path != self.path || h.location <= self.location)
+             .map_or(0, |i| i + 1)
+    }
+}
+
+pub type SearchResults = Results<Hit>;
+
+// A paginated list of items, e.g. the hits of a search.
+pub struct Results<T: ResultItem> {
+    rect: Rectangle,
+    children: Vec<Box<View>>,
+    id: ViewId,
+    items: Vec<T>,
+    current_page: usize,
+    running: Option<Arc<AtomicBool>>,
+}
+
+impl<T: ResultItem> Results<T> {
+    pub fn new(rect: Rectangle, id: ViewId, title: String, noun: &'static str,
+               completed: bool, running: Option<Arc<AtomicBool>>) -> Results<T> {
+        let mut children = Vec::new();
+        let dpi = CURRENT_DEVICE.dpi;
+        let (_, height) = CURRENT_DEVICE.dims;
+        let &(small_height, _) = BAR_SIZES.get(&(height, dpi)).unwrap();
+        let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+        let (small_thickness, big_thickness) = halves(thickness);
+        let side = small_height as i32 - small_thickness;
+
+        let back_icon = Icon::new("back",
+                                  rect![rect.min, rect.min + side],
+                                  Event::Close(id));
+        children.push(Box::new(back_icon) as Box<View>);
+
+        let title_label = Label::new(rect![rect.min.x + side, rect.min.y,
+                                           rect.max.x - side, rect.min.y + side],
+                                     title,
+                                     Align::Center);
+        children.push(Box::new(title_label) as Box<View>);
+
+        let filler = Filler::new(rect![rect.max.x - side, rect.min.y,
+                                       rect.max.x, rect.min.y + side],
+                                 WHITE);
+        children.push(Box::new(filler) as Box<View>);
+
+        let separator = Filler::new(rect![rect.min.x, rect.min.y + side,
+                                          rect.max.x, rect.min.y + side + thickness],
+                                    BLACK);
+        children.push(Box::new(separator) as Box<View>);
+
+        let entries = Entries::new(rect![rect.min.x, rect.min.y + side + thickness,
+                                         rect.max.x, rect.max.y - small_height as i32 - small_thickness]);
+        children.push(Box::new(entries) as Box<View>);
+
+        let separator = Filler::new(rect![rect.min.x, rect.max.y - small_height as i32 - small_thickness,
+                                          rect.max.x, rect.max.y - small_height as i32 + big_thickness],
+                                    BLACK);
+        children.push(Box::new(separator) as Box<View>);
+
+        let results_bar = ResultsBar::new(rect![rect.min.x, rect.max.y - small_height as i32 + big_thickness,
+                                                rect.max.x, rect.max.y],
+                                          0, 0, 0, noun, completed);
+        children.push(Box::new(results_bar) as Box<View>);
+
+        Results {
+            rect,
+            children,
+            id,
+            items: Vec::new(),
+            current_page: 0,
+            running,
+        }
+    }
+
+    #[inline]
+    fn max_lines(&self) -> usize {
+        self.child(4).downcast_ref::<Entries>().unwrap().max_lines
+    }
+
+    #[inline]
+    fn pages_count(&self) -> usize {
+        let max_lines = self.max_lines();
+        (self.items.len() + max_lines - 1) / max_lines
+    }
+
+    pub fn add_item(&mut self, item: T, hub: &Hub) {
+        let max_lines = self.max_lines();
+        let index = item.insertion_index(&self.items);
+        self.items.insert(index, item);
+        if index < (self.current_page + 1) * max_lines {
+            self.update_entries(hub);
+        }
+        self.update_results_bar(hub);
+    }
+
+    // Sets the items and shows the page of the item at the given index.
+    pub fn set_items(&mut self, items: Vec<T>, index: usize, hub: &Hub) {
+        self.items = items;
+        self.current_page = (index / self.max_lines()).min(self.pages_count().saturating_sub(1));
+        self.update_entries(hub);
+        self.update_results_bar(hub);
+    }
+
+    fn go_to_page(&mut self, index: usize, hub: &Hub) {
+        if index >= self.pages_count() {
+            return;
+        }
+        self.current_page = index;
+        self.update_entries(hub);
+        self.update_results_bar(hub);
+    }
+
+    fn go_to_neighbor(&mut self, dir: CycleDir, hub: &Hub) {
+        match dir {
+            CycleDir::Next if self.current_page < self.pages_count().saturating_sub(1) => {
+                let index = self.current_page + 1;
+                self.go_to_page(index, hub);
+            },
+            CycleDir::Previous if self.current_page > 0 => {
+                let index = self.current_page - 1;
+                self.go_to_page(index, hub);
+            },
+            _ => (),
+        }
+    }
+
+    fn update_entries(&mut self, hub: &Hub) {
+        let max_lines = self.max_lines();
+        let index_lower = self.current_page * max_lines;
+        let index_upper = (index_lower + max_lines).min(self.items.len());
+        let items = &self.items[index_lower..index_upper];
+        self.children[4].downcast_mut::<Entries>().unwrap().update(items, hub);
+    }
+
+    fn update_results_bar(&mut self, hub: &Hub) {
+        let current_page = self.current_page;
+        let pages_count = self.pages_count();
+        let count = self.items.len();
+        let results_bar = self.children[6].downcast_mut::<ResultsBar>().unwrap();
+        results_bar.update_results_label(count, hub);
+        results_bar.update_page_label(current_page, pages_count, hub);
+        results_bar.update_icons(current_page, pages_count, hub);
+    }
+
+    fn stop(&mut self) {
+        if let Some(ref running) = self.running {
+            running.store(false, AtomicOrdering::Relaxed);
+        }
+    }
+}
+
+impl<T: ResultItem> View for Results<T> {
+    fn handle_event(&mut self, evt: &Event, hub: &Hub, _bus: &mut Bus, _context: &mut Context) -> bool {
+        if let Some(item) = T::from_event(evt) {
+            self.add_item(item, hub);
+            return true;
+        }
+
+        match *evt {
+            Event::ResultsPage(dir) => {
+                self.go_to_neighbor(dir, hub);
+                true
+            },
+            Event::SelectHit(..) => {
+                self.stop();
+                false
+            },
+            Event::Close(id) if id == self.id => {
+                self.stop();
+                false
+            },
+            Event::Toggle(ViewId::GoToResultsPage) |
+            Event::Toggle(ViewId::SearchResults) => true,
+            Event::Gesture(..) => true,
+            Event::Device(DeviceEvent::Finger { .. }) => true,
+            _ => false,
+        }
+    }
+
+    fn render(&self, _fb: &mut Framebuffer, _fonts: &mut Fonts) {}
+
+    fn rect(&self) -> &Rectangle {
+        &self.rect
+    }
+
+    fn rect_mut(&mut self) -> &mut Rectangle {
+        &mut self.rect
+    }
+
+    fn children(&self) -> &Vec<Box<View>> {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> &mut Vec<Box<View>> {
+        &mut self.children
+    }
+
+    fn id(&self) -> Option<ViewId> {
+        Some(self.id)
+    }
+}